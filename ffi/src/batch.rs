@@ -0,0 +1,163 @@
+use std::ffi::{c_char, c_int, CStr};
+use std::num::NonZeroUsize;
+use std::panic::catch_unwind;
+use std::slice;
+use std::str::FromStr;
+use std::thread;
+
+use loot_condition_interpreter::{Expression, State};
+
+use crate::constants::{
+    LCI_ERROR_INVALID_ARGS, LCI_ERROR_PANICKED, LCI_ERROR_POISONED_THREAD_LOCK, LCI_OK,
+};
+use crate::expression::lci_expression;
+use crate::helpers::{error, eval_result_code, map_error, to_vec};
+use crate::state::lci_state;
+
+/// Evaluates each of the given condition strings, writing a result for each to
+/// the results array, which must be at least as long as the conditions array.
+///
+/// Each result is `LCI_RESULT_TRUE`, `LCI_RESULT_FALSE` or an error code.
+/// Errors that occur while parsing or evaluating a condition don't stop the
+/// others from being evaluated, and don't set the error message that
+/// `lci_get_error_message()` outputs.
+///
+/// If `parallel` is true, the conditions are evaluated using multiple threads.
+#[no_mangle]
+pub unsafe extern "C" fn lci_conditions_eval_batch(
+    conditions: *const *const c_char,
+    num_conditions: usize,
+    state: *mut lci_state,
+    parallel: bool,
+    results: *mut c_int,
+) -> c_int {
+    catch_unwind(|| {
+        if let Err(e) = check_batch_args(conditions, num_conditions, state, results) {
+            return e;
+        }
+
+        let conditions = match to_vec(conditions, num_conditions, |c| {
+            if c.is_null() {
+                Ok(None)
+            } else {
+                Ok(CStr::from_ptr(*c).to_str().ok())
+            }
+        }) {
+            Ok(x) => x,
+            Err(e) => return e,
+        };
+
+        eval_batch(&conditions, state, parallel, results, |state, condition| {
+            let Some(condition) = condition else {
+                return LCI_ERROR_INVALID_ARGS;
+            };
+
+            match Expression::from_str(condition) {
+                Ok(expression) => eval_result_code(expression.eval(state)),
+                Err(e) => map_error(&e),
+            }
+        })
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+/// Evaluates each of the given expressions, writing a result for each to the
+/// results array, as for `lci_conditions_eval_batch()`.
+#[no_mangle]
+pub unsafe extern "C" fn lci_expressions_eval_batch(
+    expressions: *const *const lci_expression,
+    num_expressions: usize,
+    state: *mut lci_state,
+    parallel: bool,
+    results: *mut c_int,
+) -> c_int {
+    catch_unwind(|| {
+        if let Err(e) = check_batch_args(expressions, num_expressions, state, results) {
+            return e;
+        }
+
+        let expressions = match to_vec(expressions, num_expressions, |e| {
+            Ok(e.as_ref().map(|e| &e.expression))
+        }) {
+            Ok(x) => x,
+            Err(e) => return e,
+        };
+
+        eval_batch(
+            &expressions,
+            state,
+            parallel,
+            results,
+            |state, expression| {
+                expression.map_or(LCI_ERROR_INVALID_ARGS, |e| eval_result_code(e.eval(state)))
+            },
+        )
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+fn check_batch_args<T>(
+    array: *const T,
+    array_size: usize,
+    state: *mut lci_state,
+    results: *mut c_int,
+) -> Result<(), c_int> {
+    if state.is_null() {
+        Err(error(LCI_ERROR_INVALID_ARGS, "Null state pointer passed"))
+    } else if (array.is_null() || results.is_null()) && array_size != 0 {
+        Err(error(
+            LCI_ERROR_INVALID_ARGS,
+            "Null array pointer passed but the array size is non-zero",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+unsafe fn eval_batch<T: Sync>(
+    inputs: &[T],
+    state: *mut lci_state,
+    parallel: bool,
+    results: *mut c_int,
+    evaluator: impl Fn(&State, &T) -> c_int + Sync,
+) -> c_int {
+    if inputs.is_empty() {
+        return LCI_OK;
+    }
+
+    let state = match (*state).0.read() {
+        Err(e) => return error(LCI_ERROR_POISONED_THREAD_LOCK, &e.to_string()),
+        Ok(s) => s,
+    };
+
+    let results = slice::from_raw_parts_mut(results, inputs.len());
+
+    let thread_count = if parallel {
+        thread::available_parallelism().map_or(1, NonZeroUsize::get)
+    } else {
+        1
+    };
+
+    let evaluate_chunk = |inputs: &[T], results: &mut [c_int]| {
+        for (input, result) in inputs.iter().zip(results) {
+            *result = evaluator(&state, input);
+        }
+    };
+
+    if thread_count == 1 {
+        evaluate_chunk(inputs, results);
+    } else {
+        let chunk_size = inputs.len().div_ceil(thread_count);
+
+        thread::scope(|scope| {
+            for (inputs, results) in inputs
+                .chunks(chunk_size)
+                .zip(results.chunks_mut(chunk_size))
+            {
+                scope.spawn(|| evaluate_chunk(inputs, results));
+            }
+        });
+    }
+
+    LCI_OK
+}
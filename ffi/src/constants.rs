@@ -41,6 +41,10 @@ pub static LCI_ERROR_TEXT_ENCODE_FAIL: c_int = -7;
 #[no_mangle]
 pub static LCI_ERROR_INTERNAL_LOGIC_ERROR: c_int = -8;
 
+/// Something went wrong while reading the list of files in a BSA or BA2 archive.
+#[no_mangle]
+pub static LCI_ERROR_ARCHIVE_PARSING_ERROR: c_int = -9;
+
 /// Game code for The Elder Scrolls III: Morrowind.
 #[no_mangle]
 pub static LCI_GAME_MORROWIND: c_int = 8;
@@ -84,3 +88,119 @@ pub static LCI_GAME_STARFIELD: c_int = 9;
 /// Game code for OpenMW.
 #[no_mangle]
 pub static LCI_GAME_OPENMW: c_int = 10;
+
+/// No error has been recorded.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_NONE: c_int = 0;
+
+/// The error occurred in the C API wrapper, e.g. because of invalid arguments.
+/// The error code gives more detail.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_WRAPPER: c_int = 1;
+
+/// The parser needed more input than it was given.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_PARSING_INCOMPLETE: c_int = 2;
+
+/// The parser did not consume all of its input.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_UNCONSUMED_INPUT: c_int = 3;
+
+/// The condition expression is invalid. The parsing error kind gives more
+/// detail.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_PARSING: c_int = 4;
+
+/// The version fields of an executable could not be read.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_PE_PARSING: c_int = 5;
+
+/// A BSA or BA2 archive could not be read.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_ARCHIVE_PARSING: c_int = 6;
+
+/// A path could not be accessed.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_IO: c_int = 7;
+
+/// The error is of a type that this version of the C API does not know about.
+#[no_mangle]
+pub static LCI_ERROR_CATEGORY_OTHER: c_int = 8;
+
+/// The error is not a parsing error.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_NONE: c_int = 0;
+
+/// A regex has invalid syntax.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_INVALID_REGEX_SYNTAX: c_int = 1;
+
+/// A regex is invalid for an unknown reason.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_INVALID_REGEX_UNKNOWN: c_int = 2;
+
+/// A number, such as a CRC, could not be parsed.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_INVALID_CRC: c_int = 3;
+
+/// A path ends in a directory separator.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_PATH_ENDS_IN_A_DIRECTORY_SEPARATOR: c_int = 4;
+
+/// A path is outside of the game directory.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_PATH_IS_NOT_IN_GAME_DIRECTORY: c_int = 5;
+
+/// The parser encountered an error that has no more specific kind.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_GENERIC_PARSER_ERROR: c_int = 6;
+
+/// A function name is not recognised.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_UNKNOWN_FUNCTION: c_int = 7;
+
+/// A function is missing an argument.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_EXPECTED_ARGUMENT: c_int = 8;
+
+/// A function was given too many arguments.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_UNEXPECTED_ARGUMENT: c_int = 9;
+
+/// A function name is not followed by an opening parenthesis.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_EXPECTED_OPENING_PARENTHESIS: c_int = 10;
+
+/// A keyword is not followed by a condition.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_EXPECTED_CONDITION: c_int = 11;
+
+/// A string is missing its closing quote.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_UNTERMINATED_STRING: c_int = 12;
+
+/// An opening parenthesis is missing its closing parenthesis.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_UNBALANCED_PARENTHESIS: c_int = 13;
+
+/// A closing parenthesis has no matching opening parenthesis.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_UNMATCHED_CLOSING_PARENTHESIS: c_int = 14;
+
+/// A condition is not followed by `and`, `or` or the end of its expression.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_EXPECTED_OPERATOR: c_int = 15;
+
+/// Something other than a function or parenthesised expression was found where
+/// a condition was expected.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_INVALID_CONDITION: c_int = 16;
+
+/// A function's arguments contain a character where it isn't allowed.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_UNEXPECTED_CHARACTER: c_int = 17;
+
+/// The parsing error is of a kind that this version of the C API does not know
+/// about.
+#[no_mangle]
+pub static LCI_PARSING_ERROR_KIND_OTHER: c_int = 18;
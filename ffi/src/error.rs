@@ -0,0 +1,210 @@
+use std::ffi::{c_char, c_int, CString};
+use std::panic::catch_unwind;
+use std::ptr;
+
+use loot_condition_interpreter::{Error, ParsingErrorKind};
+
+use super::{ERROR_DETAILS, ERROR_MESSAGE};
+use crate::constants::{
+    LCI_ERROR_CATEGORY_ARCHIVE_PARSING, LCI_ERROR_CATEGORY_IO, LCI_ERROR_CATEGORY_NONE,
+    LCI_ERROR_CATEGORY_OTHER, LCI_ERROR_CATEGORY_PARSING, LCI_ERROR_CATEGORY_PARSING_INCOMPLETE,
+    LCI_ERROR_CATEGORY_PE_PARSING, LCI_ERROR_CATEGORY_UNCONSUMED_INPUT, LCI_ERROR_CATEGORY_WRAPPER,
+    LCI_ERROR_INVALID_ARGS, LCI_ERROR_PANICKED, LCI_OK, LCI_PARSING_ERROR_KIND_EXPECTED_ARGUMENT,
+    LCI_PARSING_ERROR_KIND_EXPECTED_CONDITION, LCI_PARSING_ERROR_KIND_EXPECTED_OPENING_PARENTHESIS,
+    LCI_PARSING_ERROR_KIND_EXPECTED_OPERATOR, LCI_PARSING_ERROR_KIND_GENERIC_PARSER_ERROR,
+    LCI_PARSING_ERROR_KIND_INVALID_CONDITION, LCI_PARSING_ERROR_KIND_INVALID_CRC,
+    LCI_PARSING_ERROR_KIND_INVALID_REGEX_SYNTAX, LCI_PARSING_ERROR_KIND_INVALID_REGEX_UNKNOWN,
+    LCI_PARSING_ERROR_KIND_NONE, LCI_PARSING_ERROR_KIND_OTHER,
+    LCI_PARSING_ERROR_KIND_PATH_ENDS_IN_A_DIRECTORY_SEPARATOR,
+    LCI_PARSING_ERROR_KIND_PATH_IS_NOT_IN_GAME_DIRECTORY,
+    LCI_PARSING_ERROR_KIND_UNBALANCED_PARENTHESIS, LCI_PARSING_ERROR_KIND_UNEXPECTED_ARGUMENT,
+    LCI_PARSING_ERROR_KIND_UNEXPECTED_CHARACTER, LCI_PARSING_ERROR_KIND_UNKNOWN_FUNCTION,
+    LCI_PARSING_ERROR_KIND_UNMATCHED_CLOSING_PARENTHESIS,
+    LCI_PARSING_ERROR_KIND_UNTERMINATED_STRING,
+};
+use crate::helpers::{error, to_c_string};
+
+/// Details of the last error that occurred on the calling thread.
+///
+/// The strings are owned by the library, and remain valid until another error
+/// occurs on the same thread. Fields that aren't relevant to the error are null
+/// or zero.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct lci_error_details {
+    /// The code that the failing function returned.
+    pub code: c_int,
+    /// One of the `LCI_ERROR_CATEGORY_*` values.
+    pub category: c_int,
+    /// The same message that `lci_get_error_message()` outputs.
+    pub message: *const c_char,
+    /// The path that the error relates to, if any.
+    pub path: *const c_char,
+    /// For parsing errors, the byte offset in the condition string of the
+    /// input that the error relates to.
+    pub offset: usize,
+    /// For parsing errors, the length in bytes of the input that the error
+    /// relates to. This may be zero if the error just marks a point in the
+    /// input.
+    pub length: usize,
+    /// One of the `LCI_PARSING_ERROR_KIND_*` values.
+    pub parsing_error_kind: c_int,
+    /// For parsing errors, a description of the error without the condition
+    /// string.
+    pub parsing_error_message: *const c_char,
+}
+
+#[derive(Debug)]
+pub(crate) struct ErrorDetails {
+    code: c_int,
+    category: c_int,
+    path: Option<CString>,
+    offset: usize,
+    length: usize,
+    parsing_error_kind: c_int,
+    parsing_error_message: Option<CString>,
+}
+
+impl Default for ErrorDetails {
+    fn default() -> Self {
+        ErrorDetails::new(LCI_OK, LCI_ERROR_CATEGORY_NONE)
+    }
+}
+
+impl ErrorDetails {
+    pub(crate) fn new(code: c_int, category: c_int) -> Self {
+        ErrorDetails {
+            code,
+            category,
+            path: None,
+            offset: 0,
+            length: 0,
+            parsing_error_kind: LCI_PARSING_ERROR_KIND_NONE,
+            parsing_error_message: None,
+        }
+    }
+
+    pub(crate) fn code(&self) -> c_int {
+        self.code
+    }
+
+    pub(crate) fn wrapper(code: c_int) -> Self {
+        ErrorDetails::new(code, LCI_ERROR_CATEGORY_WRAPPER)
+    }
+
+    pub(crate) fn from_error(code: c_int, err: &Error) -> Self {
+        match err {
+            Error::ParsingIncomplete(_) => {
+                ErrorDetails::new(code, LCI_ERROR_CATEGORY_PARSING_INCOMPLETE)
+            }
+            Error::UnconsumedInput(_) => {
+                ErrorDetails::new(code, LCI_ERROR_CATEGORY_UNCONSUMED_INPUT)
+            }
+            Error::ParsingError(_, kind, ..) => {
+                let path = match kind {
+                    ParsingErrorKind::PathEndsInADirectorySeparator(p)
+                    | ParsingErrorKind::PathIsNotInGameDirectory(p) => {
+                        Some(to_c_string(&p.to_string_lossy()))
+                    }
+                    _ => None,
+                };
+                let range = err.span().map(|s| s.range()).unwrap_or_default();
+
+                ErrorDetails {
+                    path,
+                    offset: range.start,
+                    length: range.len(),
+                    parsing_error_kind: parsing_error_kind_code(kind),
+                    parsing_error_message: Some(to_c_string(&kind.to_string())),
+                    ..ErrorDetails::new(code, LCI_ERROR_CATEGORY_PARSING)
+                }
+            }
+            Error::PeParsingError(p, _) => ErrorDetails {
+                path: Some(to_c_string(&p.to_string_lossy())),
+                ..ErrorDetails::new(code, LCI_ERROR_CATEGORY_PE_PARSING)
+            },
+            Error::ArchiveParsingError(p, _) => ErrorDetails {
+                path: Some(to_c_string(&p.to_string_lossy())),
+                ..ErrorDetails::new(code, LCI_ERROR_CATEGORY_ARCHIVE_PARSING)
+            },
+            Error::IoError(p, _) => ErrorDetails {
+                path: Some(to_c_string(&p.to_string_lossy())),
+                ..ErrorDetails::new(code, LCI_ERROR_CATEGORY_IO)
+            },
+            _ => ErrorDetails::new(code, LCI_ERROR_CATEGORY_OTHER),
+        }
+    }
+}
+
+fn parsing_error_kind_code(kind: &ParsingErrorKind) -> c_int {
+    match kind {
+        ParsingErrorKind::InvalidRegexSyntax(_) => LCI_PARSING_ERROR_KIND_INVALID_REGEX_SYNTAX,
+        ParsingErrorKind::InvalidRegexUnknown => LCI_PARSING_ERROR_KIND_INVALID_REGEX_UNKNOWN,
+        ParsingErrorKind::InvalidCrc(_) => LCI_PARSING_ERROR_KIND_INVALID_CRC,
+        ParsingErrorKind::PathEndsInADirectorySeparator(_) => {
+            LCI_PARSING_ERROR_KIND_PATH_ENDS_IN_A_DIRECTORY_SEPARATOR
+        }
+        ParsingErrorKind::PathIsNotInGameDirectory(_) => {
+            LCI_PARSING_ERROR_KIND_PATH_IS_NOT_IN_GAME_DIRECTORY
+        }
+        ParsingErrorKind::GenericParserError(_) => LCI_PARSING_ERROR_KIND_GENERIC_PARSER_ERROR,
+        ParsingErrorKind::UnknownFunction(_, _) => LCI_PARSING_ERROR_KIND_UNKNOWN_FUNCTION,
+        ParsingErrorKind::ExpectedArgument { .. } => LCI_PARSING_ERROR_KIND_EXPECTED_ARGUMENT,
+        ParsingErrorKind::UnexpectedArgument(_) => LCI_PARSING_ERROR_KIND_UNEXPECTED_ARGUMENT,
+        ParsingErrorKind::UnexpectedCharacter(_, _) => LCI_PARSING_ERROR_KIND_UNEXPECTED_CHARACTER,
+        ParsingErrorKind::ExpectedOpeningParenthesis(_) => {
+            LCI_PARSING_ERROR_KIND_EXPECTED_OPENING_PARENTHESIS
+        }
+        ParsingErrorKind::ExpectedCondition(_) => LCI_PARSING_ERROR_KIND_EXPECTED_CONDITION,
+        ParsingErrorKind::UnterminatedString => LCI_PARSING_ERROR_KIND_UNTERMINATED_STRING,
+        ParsingErrorKind::UnbalancedParenthesis => LCI_PARSING_ERROR_KIND_UNBALANCED_PARENTHESIS,
+        ParsingErrorKind::UnmatchedClosingParenthesis => {
+            LCI_PARSING_ERROR_KIND_UNMATCHED_CLOSING_PARENTHESIS
+        }
+        ParsingErrorKind::ExpectedOperator => LCI_PARSING_ERROR_KIND_EXPECTED_OPERATOR,
+        ParsingErrorKind::InvalidCondition => LCI_PARSING_ERROR_KIND_INVALID_CONDITION,
+        _ => LCI_PARSING_ERROR_KIND_OTHER,
+    }
+}
+
+fn as_ptr_or_null(string: Option<&CString>) -> *const c_char {
+    string.map_or(ptr::null(), |s| s.as_ptr())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lci_get_error_details(details: *mut lci_error_details) -> c_int {
+    catch_unwind(|| {
+        if details.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            let message = ERROR_MESSAGE.with(|f| {
+                if f.borrow().as_bytes().is_empty() {
+                    ptr::null()
+                } else {
+                    f.borrow().as_ptr()
+                }
+            });
+
+            ERROR_DETAILS.with(|f| {
+                let error_details = f.borrow();
+
+                *details = lci_error_details {
+                    code: error_details.code,
+                    category: error_details.category,
+                    message,
+                    path: as_ptr_or_null(error_details.path.as_ref()),
+                    offset: error_details.offset,
+                    length: error_details.length,
+                    parsing_error_kind: error_details.parsing_error_kind,
+                    parsing_error_message: as_ptr_or_null(
+                        error_details.parsing_error_message.as_ref(),
+                    ),
+                };
+            });
+
+            LCI_OK
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
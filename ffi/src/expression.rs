@@ -0,0 +1,115 @@
+use std::ffi::{c_char, c_int, CString};
+use std::panic::catch_unwind;
+use std::ptr;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use loot_condition_interpreter::Expression;
+
+use crate::constants::{
+    LCI_ERROR_INVALID_ARGS, LCI_ERROR_PANICKED, LCI_ERROR_POISONED_THREAD_LOCK,
+    LCI_ERROR_TEXT_ENCODE_FAIL, LCI_OK,
+};
+use crate::helpers::{error, handle_error, map_eval_result, to_str};
+use crate::state::lci_state;
+
+#[expect(non_camel_case_types)]
+#[derive(Debug)]
+pub struct lci_expression {
+    pub(crate) expression: Expression,
+    /// The expression's condition string, created when first requested.
+    string: OnceLock<CString>,
+}
+
+impl lci_expression {
+    pub(crate) fn new(expression: Expression) -> Self {
+        lci_expression {
+            expression,
+            string: OnceLock::new(),
+        }
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lci_expression_parse(
+    expression: *mut *mut lci_expression,
+    condition: *const c_char,
+) -> c_int {
+    catch_unwind(|| {
+        if expression.is_null() || condition.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            *expression = ptr::null_mut();
+
+            let condition = match to_str(condition) {
+                Ok(x) => x,
+                Err(e) => return e,
+            };
+
+            match Expression::from_str(condition) {
+                Ok(x) => {
+                    *expression = Box::into_raw(Box::new(lci_expression::new(x)));
+                    LCI_OK
+                }
+                Err(e) => handle_error(&e),
+            }
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lci_expression_destroy(expression: *mut lci_expression) {
+    if !expression.is_null() {
+        drop(Box::from_raw(expression));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lci_expression_eval(
+    expression: *const lci_expression,
+    state: *mut lci_state,
+) -> c_int {
+    catch_unwind(|| {
+        if expression.is_null() || state.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            let state = match (*state).0.read() {
+                Err(e) => return error(LCI_ERROR_POISONED_THREAD_LOCK, &e.to_string()),
+                Ok(s) => s,
+            };
+
+            map_eval_result((*expression).expression.eval(&state))
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+/// Outputs the expression as a condition string. The string is owned by the
+/// expression, and remains valid until the expression is destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn lci_expression_to_string(
+    expression: *const lci_expression,
+    string: *mut *const c_char,
+) -> c_int {
+    catch_unwind(|| {
+        if expression.is_null() || string.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            let expression = &*expression;
+
+            let c_string = match expression.string.get() {
+                Some(x) => x,
+                None => match CString::new(expression.expression.to_string()) {
+                    Ok(x) => expression.string.get_or_init(|| x),
+                    Err(e) => return error(LCI_ERROR_TEXT_ENCODE_FAIL, &e.to_string()),
+                },
+            };
+
+            *string = c_string.as_ptr();
+
+            LCI_OK
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
@@ -4,35 +4,68 @@ use std::slice;
 
 use loot_condition_interpreter::{Error, GameType};
 
-use super::ERROR_MESSAGE;
+use super::{ERROR_DETAILS, ERROR_MESSAGE};
 use crate::constants::{
-    LCI_ERROR_INTERNAL_LOGIC_ERROR, LCI_ERROR_INVALID_ARGS, LCI_ERROR_IO_ERROR,
-    LCI_ERROR_PARSING_ERROR, LCI_ERROR_PE_PARSING_ERROR, LCI_GAME_FALLOUT_3, LCI_GAME_FALLOUT_4,
-    LCI_GAME_FALLOUT_4_VR, LCI_GAME_FALLOUT_NV, LCI_GAME_MORROWIND, LCI_GAME_OBLIVION,
-    LCI_GAME_OPENMW, LCI_GAME_SKYRIM, LCI_GAME_SKYRIM_SE, LCI_GAME_SKYRIM_VR, LCI_GAME_STARFIELD,
+    LCI_ERROR_ARCHIVE_PARSING_ERROR, LCI_ERROR_INTERNAL_LOGIC_ERROR, LCI_ERROR_INVALID_ARGS,
+    LCI_ERROR_IO_ERROR, LCI_ERROR_PARSING_ERROR, LCI_ERROR_PE_PARSING_ERROR, LCI_GAME_FALLOUT_3,
+    LCI_GAME_FALLOUT_4, LCI_GAME_FALLOUT_4_VR, LCI_GAME_FALLOUT_NV, LCI_GAME_MORROWIND,
+    LCI_GAME_OBLIVION, LCI_GAME_OPENMW, LCI_GAME_SKYRIM, LCI_GAME_SKYRIM_SE, LCI_GAME_SKYRIM_VR,
+    LCI_GAME_STARFIELD, LCI_RESULT_FALSE, LCI_RESULT_TRUE,
 };
+use crate::error::ErrorDetails;
 use crate::state::{plugin_crc, plugin_version};
 
 pub(crate) fn error(code: c_int, message: &str) -> c_int {
+    set_error(ErrorDetails::wrapper(code), message)
+}
+
+pub(crate) fn handle_error(err: &Error) -> c_int {
+    let code = map_error(err);
+    set_error(ErrorDetails::from_error(code, err), &format!("{err}"))
+}
+
+fn set_error(details: ErrorDetails, message: &str) -> c_int {
+    let code = details.code();
     ERROR_MESSAGE.with(|f| {
-        *f.borrow_mut() = CString::new(message.as_bytes())
-            .or_else(|_e| CString::new(message.replace('\0', "\\0").as_bytes()))
-            .unwrap_or_else(|_e| c"Failed to retrieve error message".into());
+        *f.borrow_mut() = to_c_string(message);
+    });
+    ERROR_DETAILS.with(|f| {
+        *f.borrow_mut() = details;
     });
     code
 }
 
-pub(crate) fn handle_error(err: &Error) -> c_int {
-    let code = map_error(err);
-    error(code, &format!("{err}"))
+/// Converts the given string to a C string, escaping any nul characters.
+pub(crate) fn to_c_string(string: &str) -> CString {
+    CString::new(string.as_bytes())
+        .or_else(|_e| CString::new(string.replace('\0', "\\0").as_bytes()))
+        .unwrap_or_else(|_e| c"Failed to retrieve error message".into())
+}
+
+pub(crate) fn map_eval_result(result: Result<bool, Error>) -> c_int {
+    match result {
+        Ok(true) => LCI_RESULT_TRUE,
+        Ok(false) => LCI_RESULT_FALSE,
+        Err(e) => handle_error(&e),
+    }
+}
+
+/// Like `map_eval_result()`, but doesn't set the error message.
+pub(crate) fn eval_result_code(result: Result<bool, Error>) -> c_int {
+    match result {
+        Ok(true) => LCI_RESULT_TRUE,
+        Ok(false) => LCI_RESULT_FALSE,
+        Err(e) => map_error(&e),
+    }
 }
 
-fn map_error(err: &Error) -> c_int {
+pub(crate) fn map_error(err: &Error) -> c_int {
     match err {
-        Error::ParsingIncomplete(_) | Error::UnconsumedInput(_) | Error::ParsingError(_, _) => {
+        Error::ParsingIncomplete(_) | Error::UnconsumedInput(_) | Error::ParsingError(..) => {
             LCI_ERROR_PARSING_ERROR
         }
         Error::PeParsingError(_, _) => LCI_ERROR_PE_PARSING_ERROR,
+        Error::ArchiveParsingError(_, _) => LCI_ERROR_ARCHIVE_PARSING_ERROR,
         Error::IoError(_, _) => LCI_ERROR_IO_ERROR,
         _ => LCI_ERROR_INTERNAL_LOGIC_ERROR,
     }
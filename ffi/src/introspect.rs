@@ -0,0 +1,341 @@
+use std::ffi::{c_char, c_int, CString};
+use std::panic::catch_unwind;
+use std::path::Path;
+use std::ptr;
+use std::str::FromStr;
+
+use loot_condition_interpreter::visit::Visitor;
+use loot_condition_interpreter::{ComparisonOperator, Expression, Function};
+
+use crate::constants::{LCI_ERROR_INVALID_ARGS, LCI_ERROR_PANICKED, LCI_OK};
+use crate::expression::lci_expression;
+use crate::helpers::{error, handle_error, to_c_string, to_str};
+
+/// A function call in a condition. Arguments that the function doesn't take
+/// are null, or for `number`, zero with `has_number` false.
+#[allow(non_camel_case_types)]
+#[repr(C)]
+#[derive(Debug)]
+pub struct lci_function_call {
+    /// The name of the function, e.g. `file`.
+    pub name: *const c_char,
+    /// The function call as a condition string, e.g. `file("Blank.esp")`.
+    pub condition: *const c_char,
+    /// The function's path argument. For functions that take a path with a
+    /// regex as its last component, this is the path's parent. For
+    /// `archive_contains()`, this is the archive's path.
+    pub path: *const c_char,
+    /// The function's second path argument: the master for `has_master()`,
+    /// the other plugin for `load_after()`, and the path in the archive for
+    /// `archive_contains()`, or its parent if it has a regex as its last
+    /// component.
+    pub second_path: *const c_char,
+    /// The function's regex argument. Regexes that are parsed from paths and
+    /// filenames are anchored so that they must match a whole filename.
+    pub regex: *const c_char,
+    /// The function's version argument. For `header_version()`, this is the
+    /// version number written as it is in a condition string.
+    pub version: *const c_char,
+    /// The operator that the function compares its version, count or load
+    /// order index argument with, e.g. `>=`.
+    pub comparison_operator: *const c_char,
+    /// Whether the function has a numeric argument.
+    pub has_number: bool,
+    /// The function's numeric argument: the size for `file_size()`, the CRC
+    /// for `checksum()`, the count for `record_count()` and
+    /// `override_record_count()`, and the index for `load_index()`.
+    pub number: u64,
+}
+
+#[expect(non_camel_case_types)]
+#[derive(Debug)]
+pub struct lci_function_calls {
+    calls: Vec<lci_function_call>,
+    /// The strings that the calls point to.
+    strings: Vec<CString>,
+}
+
+impl lci_function_calls {
+    fn new(expression: &Expression) -> Self {
+        let mut calls = lci_function_calls {
+            calls: Vec::new(),
+            strings: Vec::new(),
+        };
+
+        calls.visit_expression(expression);
+
+        calls
+    }
+
+    fn push_string(&mut self, string: &str) -> *const c_char {
+        let c_string = to_c_string(string);
+        // Moving the CString doesn't move the heap buffer that it points to.
+        let pointer = c_string.as_ptr();
+        self.strings.push(c_string);
+        pointer
+    }
+
+    fn push_optional_string(&mut self, string: Option<&str>) -> *const c_char {
+        string.map_or(ptr::null(), |s| self.push_string(s))
+    }
+}
+
+impl Visitor for lci_function_calls {
+    fn visit_function(&mut self, function: &Function) {
+        let (version, comparison_operator) = comparison(function);
+        let number = number(function);
+
+        let call = lci_function_call {
+            name: self.push_string(function.name()),
+            condition: self.push_string(&function.to_string()),
+            path: self
+                .push_optional_string(function.path().map(|p| p.to_string_lossy()).as_deref()),
+            second_path: self.push_optional_string(
+                second_path(function)
+                    .map(|p| p.to_string_lossy())
+                    .as_deref(),
+            ),
+            regex: self.push_optional_string(function.regex().map(ToString::to_string).as_deref()),
+            version: self.push_optional_string(version.as_deref()),
+            comparison_operator: self
+                .push_optional_string(comparison_operator.map(|c| c.to_string()).as_deref()),
+            has_number: number.is_some(),
+            number: number.unwrap_or_default(),
+        };
+
+        self.calls.push(call);
+    }
+}
+
+fn second_path(function: &Function) -> Option<&Path> {
+    match function {
+        Function::ArchiveContains(_, p)
+        | Function::ArchiveContainsRegex(_, p, _)
+        | Function::HasMaster(_, p)
+        | Function::LoadAfter(_, p) => Some(p),
+        _ => None,
+    }
+}
+
+/// Gets the function's version argument, if it has one, and the operator
+/// that it compares its version, count or index argument with.
+fn comparison(function: &Function) -> (Option<String>, Option<ComparisonOperator>) {
+    match function {
+        Function::HeaderVersion(_, v, c) => (Some(v.to_string()), Some(*c)),
+        Function::RecordCount(_, _, c)
+        | Function::OverrideRecordCount(_, _, c)
+        | Function::LoadIndex(_, _, c) => (None, Some(*c)),
+        _ => function
+            .version_argument()
+            .map_or((None, None), |(v, c)| (Some(v.to_owned()), Some(c))),
+    }
+}
+
+fn number(function: &Function) -> Option<u64> {
+    match function {
+        Function::FileSize(_, s) => Some(*s),
+        Function::Checksum(_, c)
+        | Function::RecordCount(_, c, _)
+        | Function::OverrideRecordCount(_, c, _)
+        | Function::LoadIndex(_, c, _) => Some(u64::from(*c)),
+        _ => None,
+    }
+}
+
+/// Parses the given condition and outputs the function calls that it
+/// contains, in the order that they appear. Calls that appear more than once
+/// are listed each time that they appear.
+///
+/// On success, the calls must be freed using `lci_function_calls_destroy()`.
+/// If an error occurs, the calls are set to null.
+#[no_mangle]
+pub unsafe extern "C" fn lci_condition_function_calls(
+    calls: *mut *mut lci_function_calls,
+    condition: *const c_char,
+) -> c_int {
+    catch_unwind(|| {
+        if calls.is_null() || condition.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            *calls = ptr::null_mut();
+
+            let condition = match to_str(condition) {
+                Ok(x) => x,
+                Err(e) => return e,
+            };
+
+            match Expression::from_str(condition) {
+                Ok(x) => {
+                    *calls = Box::into_raw(Box::new(lci_function_calls::new(&x)));
+                    LCI_OK
+                }
+                Err(e) => handle_error(&e),
+            }
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+/// Outputs the function calls that the given expression contains, as for
+/// `lci_condition_function_calls()`.
+#[no_mangle]
+pub unsafe extern "C" fn lci_expression_function_calls(
+    calls: *mut *mut lci_function_calls,
+    expression: *const lci_expression,
+) -> c_int {
+    catch_unwind(|| {
+        if calls.is_null() || expression.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            *calls = Box::into_raw(Box::new(lci_function_calls::new(&(*expression).expression)));
+            LCI_OK
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+/// Outputs an array of the function calls and its size. The array and the
+/// strings that it points to are owned by the function calls object, and
+/// remain valid until it is destroyed.
+#[no_mangle]
+pub unsafe extern "C" fn lci_function_calls_get(
+    calls: *const lci_function_calls,
+    array: *mut *const lci_function_call,
+    array_size: *mut usize,
+) -> c_int {
+    catch_unwind(|| {
+        if calls.is_null() || array.is_null() || array_size.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            let calls = &(*calls).calls;
+
+            *array = if calls.is_empty() {
+                ptr::null()
+            } else {
+                calls.as_ptr()
+            };
+            *array_size = calls.len();
+
+            LCI_OK
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lci_function_calls_destroy(calls: *mut lci_function_calls) {
+    if !calls.is_null() {
+        drop(Box::from_raw(calls));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ffi::CStr;
+
+    fn calls(condition: &str) -> lci_function_calls {
+        lci_function_calls::new(&Expression::from_str(condition).unwrap())
+    }
+
+    fn string(pointer: *const c_char) -> Option<String> {
+        if pointer.is_null() {
+            None
+        } else {
+            // SAFETY: Non-null pointers point to strings that are owned by
+            // calls that are still in scope.
+            let string = unsafe { CStr::from_ptr(pointer) };
+            Some(string.to_str().unwrap().to_owned())
+        }
+    }
+
+    #[test]
+    fn new_should_list_each_function_call_with_its_arguments() {
+        let calls = calls(
+            "file(\"Blank.esp\") and not (many_active(\"Blank.*\") or version(\"Blank.exe\", \"1.2\", >=))",
+        );
+
+        assert_eq!(3, calls.calls.len());
+
+        let call = &calls.calls[0];
+        assert_eq!(Some("file"), string(call.name).as_deref());
+        assert_eq!(
+            Some("file(\"Blank.esp\")"),
+            string(call.condition).as_deref()
+        );
+        assert_eq!(Some("Blank.esp"), string(call.path).as_deref());
+        assert_eq!(None, string(call.second_path));
+        assert_eq!(None, string(call.regex));
+        assert_eq!(None, string(call.version));
+        assert_eq!(None, string(call.comparison_operator));
+        assert!(!call.has_number);
+
+        let call = &calls.calls[1];
+        assert_eq!(Some("many_active"), string(call.name).as_deref());
+        assert_eq!(None, string(call.path));
+        assert_eq!(Some("^Blank.*$"), string(call.regex).as_deref());
+
+        let call = &calls.calls[2];
+        assert_eq!(Some("Blank.exe"), string(call.path).as_deref());
+        assert_eq!(Some("1.2"), string(call.version).as_deref());
+        assert_eq!(Some(">="), string(call.comparison_operator).as_deref());
+    }
+
+    #[test]
+    fn new_should_give_the_version_and_comparison_operator_of_header_version() {
+        let calls = calls("header_version(\"Blank.esp\", >=, 1.7)");
+        let call = &calls.calls[0];
+
+        assert_eq!(Some("Blank.esp"), string(call.path).as_deref());
+        assert_eq!(Some("1.7"), string(call.version).as_deref());
+        assert_eq!(Some(">="), string(call.comparison_operator).as_deref());
+        assert!(!call.has_number);
+    }
+
+    #[test]
+    fn new_should_give_numeric_arguments_and_their_comparison_operators() {
+        let calls = calls(
+            "file_size(\"Blank.esp\", 10) or checksum(\"Blank.esp\", DEADBEEF) or record_count(\"Blank.esp\", <, 100) or override_record_count(\"Blank.esp\", ==, 0) or load_index(\"Blank.esp\", !=, 3)",
+        );
+
+        let expected = [
+            (10, None),
+            (0xDEAD_BEEF, None),
+            (100, Some("<")),
+            (0, Some("==")),
+            (3, Some("!=")),
+        ];
+
+        assert_eq!(expected.len(), calls.calls.len());
+        for (call, (number, comparison_operator)) in calls.calls.iter().zip(expected) {
+            assert!(call.has_number);
+            assert_eq!(number, call.number);
+            assert_eq!(
+                comparison_operator,
+                string(call.comparison_operator).as_deref()
+            );
+        }
+    }
+
+    #[test]
+    fn new_should_give_second_path_arguments() {
+        let calls = calls(
+            "has_master(\"Blank.esp\", \"Blank.esm\") or load_after(\"Blank.esp\", \"Other.esp\") or archive_contains(\"Blank.bsa\", \"meshes/a.nif\") or archive_contains(\"Blank.bsa\", \"meshes/.*\\.nif\")",
+        );
+
+        let expected = [
+            ("Blank.esp", "Blank.esm", None),
+            ("Blank.esp", "Other.esp", None),
+            ("Blank.bsa", "meshes/a.nif", None),
+            ("Blank.bsa", "meshes", Some("^.*\\.nif$")),
+        ];
+
+        assert_eq!(expected.len(), calls.calls.len());
+        for (call, (path, second_path, regex)) in calls.calls.iter().zip(expected) {
+            assert_eq!(Some(path), string(call.path).as_deref());
+            assert_eq!(Some(second_path), string(call.second_path).as_deref());
+            assert_eq!(regex, string(call.regex).as_deref());
+        }
+    }
+}
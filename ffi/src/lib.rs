@@ -16,9 +16,14 @@
         clippy::unwrap_used,
     )
 )]
+mod batch;
 mod constants;
+mod error;
+mod expression;
 mod helpers;
+mod introspect;
 mod state;
+mod trace;
 
 use std::cell::RefCell;
 use std::ffi::{c_char, c_int, CString};
@@ -28,11 +33,17 @@ use std::str::FromStr;
 
 use loot_condition_interpreter::Expression;
 
+pub use batch::*;
 pub use constants::*;
-use helpers::{error, handle_error, to_str};
+pub use error::*;
+pub use expression::*;
+use helpers::{error, handle_error, map_eval_result, to_str};
+pub use introspect::*;
 pub use state::*;
+pub use trace::*;
 
 thread_local!(static ERROR_MESSAGE: RefCell<CString> = RefCell::default());
+thread_local!(static ERROR_DETAILS: RefCell<ErrorDetails> = RefCell::default());
 
 #[no_mangle]
 pub unsafe extern "C" fn lci_condition_parse(condition: *const c_char) -> c_int {
@@ -79,11 +90,7 @@ pub unsafe extern "C" fn lci_condition_eval(
                 Ok(s) => s,
             };
 
-            match expression.eval(&state) {
-                Ok(true) => LCI_RESULT_TRUE,
-                Ok(false) => LCI_RESULT_FALSE,
-                Err(e) => handle_error(&e),
-            }
+            map_eval_result(expression.eval(&state))
         }
     })
     .unwrap_or(LCI_ERROR_PANICKED)
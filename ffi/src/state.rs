@@ -107,6 +107,44 @@ pub unsafe extern "C" fn lci_state_set_active_plugins(
     .unwrap_or(LCI_ERROR_PANICKED)
 }
 
+#[no_mangle]
+pub unsafe extern "C" fn lci_state_set_load_order(
+    state: *mut lci_state,
+    plugin_names: *const *const c_char,
+    num_plugins: usize,
+) -> c_int {
+    catch_unwind(|| {
+        if state.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null state pointer passed")
+        } else if plugin_names.is_null() && num_plugins != 0 {
+            error(
+                LCI_ERROR_INVALID_ARGS,
+                "Null plugin_names pointer passed but num_plugins is non-zero",
+            )
+        } else if !plugin_names.is_null() && num_plugins == 0 {
+            error(
+                LCI_ERROR_INVALID_ARGS,
+                "Non-null plugin_names pointer passed but num_plugins is zero",
+            )
+        } else {
+            let plugins: Vec<&str> = match to_str_vec(plugin_names, num_plugins) {
+                Ok(x) => x,
+                Err(e) => return e,
+            };
+
+            let mut state = match (*state).0.write() {
+                Err(e) => return error(LCI_ERROR_POISONED_THREAD_LOCK, &e.to_string()),
+                Ok(h) => h,
+            };
+
+            state.set_load_order(&plugins);
+
+            LCI_OK
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn lci_state_set_plugin_versions(
     state: *mut lci_state,
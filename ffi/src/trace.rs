@@ -0,0 +1,242 @@
+use std::ffi::{c_char, c_int, CString};
+use std::fmt::Write;
+use std::panic::catch_unwind;
+use std::ptr;
+use std::str::FromStr;
+
+use loot_condition_interpreter::{
+    CompoundConditionTrace, ConditionTrace, Expression, ExpressionTrace, FunctionEvaluation,
+    ResultSource,
+};
+
+use crate::constants::{
+    LCI_ERROR_INVALID_ARGS, LCI_ERROR_PANICKED, LCI_ERROR_POISONED_THREAD_LOCK,
+    LCI_ERROR_TEXT_ENCODE_FAIL,
+};
+use crate::expression::lci_expression;
+use crate::helpers::{error, handle_error, map_eval_result, to_str};
+use crate::state::lci_state;
+
+/// Parses and evaluates the given condition as for `lci_condition_eval()`,
+/// and outputs a JSON trace of the evaluation.
+///
+/// The trace is an object with a `result` and a list of
+/// `compound_conditions`, which are the expression's 'or'-separated parts.
+/// Each compound condition has a `result` and a list of `conditions`, which
+/// are its 'and'-separated parts. Each condition has a `type` that is
+/// `function`, `expression` or `invalid`:
+///
+/// - Function conditions have the function's `condition` string, its `name`,
+///   whether it is `inverted` by 'not', the condition's `result`, the
+///   function's result before any inversion as `function_result`, the
+///   `source` of that result (`evaluated`, `condition_cache` or `crc_cache`),
+///   and the `resolved_path` of its path argument, if it has one.
+/// - Expression conditions have whether they are `inverted`, the condition's
+///   `result` and a nested `trace` of the parenthesised expression.
+/// - Invalid conditions have their `condition` string.
+///
+/// Results are `null` for conditions that were skipped because the overall
+/// result was already known.
+///
+/// On success, the trace must be freed using `lci_eval_trace_destroy()`. If
+/// an error occurs, the trace is set to null.
+#[no_mangle]
+pub unsafe extern "C" fn lci_condition_eval_trace(
+    condition: *const c_char,
+    state: *mut lci_state,
+    trace: *mut *mut c_char,
+) -> c_int {
+    catch_unwind(|| {
+        if condition.is_null() || state.is_null() || trace.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            *trace = ptr::null_mut();
+
+            let condition = match to_str(condition) {
+                Ok(x) => x,
+                Err(e) => return e,
+            };
+
+            let expression = match Expression::from_str(condition) {
+                Ok(x) => x,
+                Err(e) => return handle_error(&e),
+            };
+
+            eval_trace(&expression, state, trace)
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+/// Evaluates the given expression and outputs a JSON trace of the evaluation,
+/// as for `lci_condition_eval_trace()`.
+#[no_mangle]
+pub unsafe extern "C" fn lci_expression_eval_trace(
+    expression: *const lci_expression,
+    state: *mut lci_state,
+    trace: *mut *mut c_char,
+) -> c_int {
+    catch_unwind(|| {
+        if expression.is_null() || state.is_null() || trace.is_null() {
+            error(LCI_ERROR_INVALID_ARGS, "Null pointer passed")
+        } else {
+            *trace = ptr::null_mut();
+
+            eval_trace(&(*expression).expression, state, trace)
+        }
+    })
+    .unwrap_or(LCI_ERROR_PANICKED)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn lci_eval_trace_destroy(trace: *mut c_char) {
+    if !trace.is_null() {
+        drop(CString::from_raw(trace));
+    }
+}
+
+unsafe fn eval_trace(
+    expression: &Expression,
+    state: *mut lci_state,
+    trace: *mut *mut c_char,
+) -> c_int {
+    let state = match (*state).0.read() {
+        Err(e) => return error(LCI_ERROR_POISONED_THREAD_LOCK, &e.to_string()),
+        Ok(s) => s,
+    };
+
+    let expression_trace = match expression.eval_explained(&state) {
+        Ok(x) => x,
+        Err(e) => return handle_error(&e),
+    };
+
+    // The trace of an evaluated expression always has a result.
+    let result = expression_trace.result() == Some(true);
+
+    match CString::new(to_json(&expression_trace)) {
+        Ok(x) => {
+            *trace = x.into_raw();
+            map_eval_result(Ok(result))
+        }
+        Err(e) => error(LCI_ERROR_TEXT_ENCODE_FAIL, &e.to_string()),
+    }
+}
+
+fn to_json(trace: &ExpressionTrace) -> String {
+    let mut json = String::new();
+    write_expression(&mut json, trace);
+    json
+}
+
+fn write_expression(json: &mut String, trace: &ExpressionTrace) {
+    json.push_str("{\"result\":");
+    write_result(json, trace.result());
+    json.push_str(",\"compound_conditions\":[");
+
+    for (index, compound_condition) in trace.compound_conditions().iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        write_compound_condition(json, compound_condition);
+    }
+
+    json.push_str("]}");
+}
+
+fn write_compound_condition(json: &mut String, trace: &CompoundConditionTrace) {
+    json.push_str("{\"result\":");
+    write_result(json, trace.result());
+    json.push_str(",\"conditions\":[");
+
+    for (index, condition) in trace.conditions().iter().enumerate() {
+        if index > 0 {
+            json.push(',');
+        }
+        write_condition(json, condition);
+    }
+
+    json.push_str("]}");
+}
+
+fn write_condition(json: &mut String, trace: &ConditionTrace) {
+    match trace {
+        ConditionTrace::Function {
+            function,
+            inverted,
+            evaluation,
+        } => {
+            json.push_str("{\"type\":\"function\",\"condition\":");
+            write_string(json, &function.to_string());
+            json.push_str(",\"name\":");
+            write_string(json, function.name());
+            json.push_str(",\"inverted\":");
+            json.push_str(if *inverted { "true" } else { "false" });
+            json.push_str(",\"result\":");
+            write_result(json, trace.result());
+            json.push_str(",\"function_result\":");
+            write_result(json, evaluation.as_ref().map(FunctionEvaluation::result));
+            json.push_str(",\"source\":");
+            match evaluation.as_ref().map(FunctionEvaluation::source) {
+                Some(ResultSource::Evaluated) => json.push_str("\"evaluated\""),
+                Some(ResultSource::ConditionCache) => json.push_str("\"condition_cache\""),
+                Some(ResultSource::CrcCache) => json.push_str("\"crc_cache\""),
+                None => json.push_str("null"),
+            }
+            json.push_str(",\"resolved_path\":");
+            match evaluation.as_ref().and_then(|e| e.resolved_path()) {
+                Some(path) => write_string(json, &path.to_string_lossy()),
+                None => json.push_str("null"),
+            }
+            json.push('}');
+        }
+        ConditionTrace::Expression {
+            inverted,
+            trace: expression_trace,
+        } => {
+            json.push_str("{\"type\":\"expression\",\"inverted\":");
+            json.push_str(if *inverted { "true" } else { "false" });
+            json.push_str(",\"result\":");
+            write_result(json, trace.result());
+            json.push_str(",\"trace\":");
+            write_expression(json, expression_trace);
+            json.push('}');
+        }
+        ConditionTrace::Invalid(condition) => {
+            json.push_str("{\"type\":\"invalid\",\"condition\":");
+            write_string(json, condition);
+            json.push_str(",\"result\":null}");
+        }
+        _ => {
+            json.push_str("{\"type\":\"unknown\",\"result\":");
+            write_result(json, trace.result());
+            json.push('}');
+        }
+    }
+}
+
+fn write_result(json: &mut String, result: Option<bool>) {
+    match result {
+        Some(true) => json.push_str("true"),
+        Some(false) => json.push_str("false"),
+        None => json.push_str("null"),
+    }
+}
+
+fn write_string(json: &mut String, string: &str) {
+    json.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                // Writing to a String can't fail.
+                let _result = write!(json, "\\u{:04x}", u32::from(c));
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
@@ -49,7 +49,89 @@ void test_lci_get_error_message() {
     return_code = lci_get_error_message(&message);
     assert(return_code == LCI_OK);
     assert(message != nullptr);
-    assert(strcmp(message, "The parser did not consume the following input: \"file(\"Blank.\"") == 0);
+    assert(strcmp(message, "An error was encountered while parsing the expression \"\\\"Blank.\": String has no closing quote") == 0);
+}
+
+void test_lci_get_error_details() {
+    printf("testing lci_get_error_details()...\n");
+
+    int return_code = lci_condition_parse("file(\"Blank.");
+    assert(return_code == LCI_ERROR_PARSING_ERROR);
+
+    lci_error_details details;
+    return_code = lci_get_error_details(&details);
+
+    const char * message = nullptr;
+    assert(lci_get_error_message(&message) == LCI_OK);
+
+    assert(return_code == LCI_OK);
+    assert(details.code == LCI_ERROR_PARSING_ERROR);
+    assert(details.category == LCI_ERROR_CATEGORY_PARSING);
+    assert(details.message == message);
+    assert(details.path == nullptr);
+    assert(details.offset == 5);
+    assert(details.length == 1);
+    assert(details.parsing_error_kind == LCI_PARSING_ERROR_KIND_UNTERMINATED_STRING);
+    assert(strcmp(details.parsing_error_message, "String has no closing quote") == 0);
+
+    return_code = lci_condition_parse("file(\"targ.*et/\")");
+    assert(return_code == LCI_ERROR_PARSING_ERROR);
+
+    return_code = lci_get_error_details(&details);
+
+    assert(return_code == LCI_OK);
+    assert(details.category == LCI_ERROR_CATEGORY_PARSING);
+    assert(details.parsing_error_kind == LCI_PARSING_ERROR_KIND_PATH_ENDS_IN_A_DIRECTORY_SEPARATOR);
+    assert(strcmp(details.path, "targ.*et/") == 0);
+    assert(details.offset == 6);
+    assert(details.length == 9);
+
+    return_code = lci_condition_parse(nullptr);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    return_code = lci_get_error_details(&details);
+
+    assert(return_code == LCI_OK);
+    assert(details.code == LCI_ERROR_INVALID_ARGS);
+    assert(details.category == LCI_ERROR_CATEGORY_WRAPPER);
+    assert(details.path == nullptr);
+    assert(details.offset == 0);
+    assert(details.length == 0);
+    assert(details.parsing_error_kind == LCI_PARSING_ERROR_KIND_NONE);
+    assert(details.parsing_error_message == nullptr);
+
+    return_code = lci_get_error_details(nullptr);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+}
+
+void test_lci_condition_eval_with_an_invalid_archive() {
+    printf("testing lci_condition_eval() with an invalid archive...\n");
+
+    FILE * file = fopen("invalid.bsa", "wb");
+    assert(file != nullptr);
+    fputs("not an archive", file);
+    fclose(file);
+
+    lci_state * state = nullptr;
+    int return_code = lci_state_create(&state, LCI_GAME_OBLIVION, ".");
+
+    assert(return_code == LCI_OK);
+    assert(state != nullptr);
+
+    return_code = lci_condition_eval("archive_contains(\"invalid.bsa\", \"a.txt\")", state);
+
+    assert(return_code == LCI_ERROR_ARCHIVE_PARSING_ERROR);
+
+    lci_error_details details;
+    return_code = lci_get_error_details(&details);
+
+    assert(return_code == LCI_OK);
+    assert(details.code == LCI_ERROR_ARCHIVE_PARSING_ERROR);
+    assert(details.category == LCI_ERROR_CATEGORY_ARCHIVE_PARSING);
+    assert(strcmp(details.path, "./invalid.bsa") == 0);
+
+    lci_state_destroy(state);
+    remove("invalid.bsa");
 }
 
 void test_lci_state_create() {
@@ -84,6 +166,314 @@ void test_lci_condition_eval() {
     lci_state_destroy(state);
 }
 
+void test_lci_expression_parse() {
+    printf("testing lci_expression_parse()...\n");
+
+    lci_expression * expression = nullptr;
+    int return_code = lci_expression_parse(&expression, "file(\"Blank.esm\")");
+
+    assert(return_code == LCI_OK);
+    assert(expression != nullptr);
+
+    lci_expression_destroy(expression);
+
+    return_code = lci_expression_parse(&expression, "file(\"Blank.");
+
+    assert(return_code == LCI_ERROR_PARSING_ERROR);
+    assert(expression == nullptr);
+
+    expression = reinterpret_cast<lci_expression *>(&return_code);
+    return_code = lci_expression_parse(&expression, "file(\"Blank\xff.esp\")");
+
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+    assert(expression == nullptr);
+}
+
+void test_lci_expression_eval() {
+    printf("testing lci_expression_eval()...\n");
+
+    lci_state * state = nullptr;
+    int return_code = lci_state_create(&state, LCI_GAME_OBLIVION, ".");
+
+    assert(return_code == LCI_OK);
+    assert(state != nullptr);
+
+    lci_expression * expression = nullptr;
+    return_code = lci_expression_parse(&expression, "active(\"Blank.esm\")");
+
+    assert(return_code == LCI_OK);
+
+    return_code = lci_expression_eval(expression, state);
+    assert(return_code == LCI_RESULT_FALSE);
+
+    char const * plugins[] = { "Blank.esm" };
+    return_code = lci_state_set_active_plugins(state, plugins, 1);
+    assert(return_code == LCI_OK);
+
+    return_code = lci_expression_eval(expression, state);
+    assert(return_code == LCI_RESULT_TRUE);
+
+    return_code = lci_expression_eval(nullptr, state);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    lci_expression_destroy(expression);
+    lci_state_destroy(state);
+}
+
+void test_lci_expression_to_string() {
+    printf("testing lci_expression_to_string()...\n");
+
+    lci_expression * expression = nullptr;
+    int return_code = lci_expression_parse(&expression, "file(\"Blank.esm\")  or  active(\"Blank.esp\")");
+
+    assert(return_code == LCI_OK);
+
+    const char * string = nullptr;
+    return_code = lci_expression_to_string(expression, &string);
+
+    assert(return_code == LCI_OK);
+    assert(strcmp(string, "file(\"Blank.esm\") or active(\"Blank.esp\")") == 0);
+
+    lci_expression_destroy(expression);
+}
+
+void test_lci_condition_eval_trace() {
+    printf("testing lci_condition_eval_trace()...\n");
+
+    lci_state * state = nullptr;
+    int return_code = lci_state_create(&state, LCI_GAME_OBLIVION, ".");
+
+    assert(return_code == LCI_OK);
+    assert(state != nullptr);
+
+    char const * plugins[] = { "Blank.esm" };
+    return_code = lci_state_set_active_plugins(state, plugins, 1);
+    assert(return_code == LCI_OK);
+
+    char * trace = nullptr;
+    return_code = lci_condition_eval_trace("not active(\"Blank.esm\") or (file(\"missing.esp\") or active(\"Blank.esm\")) or active(\"Blank.esp\")", state, &trace);
+
+    assert(return_code == LCI_RESULT_TRUE);
+    assert(trace != nullptr);
+    assert(strcmp(trace, "{\"result\":true,\"compound_conditions\":[{\"result\":false,\"conditions\":[{\"type\":\"function\",\"condition\":\"active(\\\"Blank.esm\\\")\",\"name\":\"active\",\"inverted\":true,\"result\":false,\"function_result\":true,\"source\":\"evaluated\",\"resolved_path\":null}]},{\"result\":true,\"conditions\":[{\"type\":\"expression\",\"inverted\":false,\"result\":true,\"trace\":{\"result\":true,\"compound_conditions\":[{\"result\":false,\"conditions\":[{\"type\":\"function\",\"condition\":\"file(\\\"missing.esp\\\")\",\"name\":\"file\",\"inverted\":false,\"result\":false,\"function_result\":false,\"source\":\"evaluated\",\"resolved_path\":\"./missing.esp.ghost\"}]},{\"result\":true,\"conditions\":[{\"type\":\"function\",\"condition\":\"active(\\\"Blank.esm\\\")\",\"name\":\"active\",\"inverted\":false,\"result\":true,\"function_result\":true,\"source\":\"evaluated\",\"resolved_path\":null}]}]}}]},{\"result\":null,\"conditions\":[{\"type\":\"function\",\"condition\":\"active(\\\"Blank.esp\\\")\",\"name\":\"active\",\"inverted\":false,\"result\":null,\"function_result\":null,\"source\":null,\"resolved_path\":null}]}]}") == 0);
+
+    lci_eval_trace_destroy(trace);
+
+    return_code = lci_condition_eval_trace("file(\"Blank.", state, &trace);
+
+    assert(return_code == LCI_ERROR_PARSING_ERROR);
+    assert(trace == nullptr);
+
+    return_code = lci_condition_eval_trace(nullptr, state, &trace);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    lci_state_destroy(state);
+}
+
+void test_lci_expression_eval_trace() {
+    printf("testing lci_expression_eval_trace()...\n");
+
+    lci_state * state = nullptr;
+    int return_code = lci_state_create(&state, LCI_GAME_OBLIVION, ".");
+
+    assert(return_code == LCI_OK);
+    assert(state != nullptr);
+
+    lci_expression * expression = nullptr;
+    return_code = lci_expression_parse(&expression, "active(\"Blank.esm\")");
+
+    assert(return_code == LCI_OK);
+
+    char * trace = nullptr;
+    return_code = lci_expression_eval_trace(expression, state, &trace);
+
+    assert(return_code == LCI_RESULT_FALSE);
+    assert(strcmp(trace, "{\"result\":false,\"compound_conditions\":[{\"result\":false,\"conditions\":[{\"type\":\"function\",\"condition\":\"active(\\\"Blank.esm\\\")\",\"name\":\"active\",\"inverted\":false,\"result\":false,\"function_result\":false,\"source\":\"evaluated\",\"resolved_path\":null}]}]}") == 0);
+
+    lci_eval_trace_destroy(trace);
+
+    return_code = lci_expression_eval_trace(nullptr, state, &trace);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    lci_expression_destroy(expression);
+    lci_state_destroy(state);
+}
+
+void test_lci_condition_function_calls() {
+    printf("testing lci_condition_function_calls()...\n");
+
+    lci_function_calls * calls = nullptr;
+    int return_code = lci_condition_function_calls(&calls, "file(\"Blank.esp\") and not (many_active(\"Blank.*\") or version(\"Blank.exe\", \"1.2\", >=))");
+
+    assert(return_code == LCI_OK);
+    assert(calls != nullptr);
+
+    const lci_function_call * array = nullptr;
+    size_t array_size = 0;
+    return_code = lci_function_calls_get(calls, &array, &array_size);
+
+    assert(return_code == LCI_OK);
+    assert(array_size == 3);
+
+    assert(strcmp(array[0].name, "file") == 0);
+    assert(strcmp(array[0].condition, "file(\"Blank.esp\")") == 0);
+    assert(strcmp(array[0].path, "Blank.esp") == 0);
+    assert(array[0].second_path == nullptr);
+    assert(array[0].regex == nullptr);
+    assert(array[0].version == nullptr);
+    assert(array[0].comparison_operator == nullptr);
+    assert(!array[0].has_number);
+
+    assert(strcmp(array[1].name, "many_active") == 0);
+    assert(array[1].path == nullptr);
+    assert(strcmp(array[1].regex, "^Blank.*$") == 0);
+
+    assert(strcmp(array[2].name, "version") == 0);
+    assert(strcmp(array[2].path, "Blank.exe") == 0);
+    assert(strcmp(array[2].version, "1.2") == 0);
+    assert(strcmp(array[2].comparison_operator, ">=") == 0);
+
+    lci_function_calls_destroy(calls);
+
+    return_code = lci_condition_function_calls(&calls, "has_master(\"Blank.esp\", \"Blank.esm\") or load_index(\"Blank.esp\", <, 3)");
+
+    assert(return_code == LCI_OK);
+
+    return_code = lci_function_calls_get(calls, &array, &array_size);
+
+    assert(return_code == LCI_OK);
+    assert(array_size == 2);
+
+    assert(strcmp(array[0].second_path, "Blank.esm") == 0);
+    assert(strcmp(array[1].comparison_operator, "<") == 0);
+    assert(array[1].has_number);
+    assert(array[1].number == 3);
+
+    lci_function_calls_destroy(calls);
+
+    return_code = lci_condition_function_calls(&calls, "file(\"Blank.");
+
+    assert(return_code == LCI_ERROR_PARSING_ERROR);
+    assert(calls == nullptr);
+}
+
+void test_lci_expression_function_calls() {
+    printf("testing lci_expression_function_calls()...\n");
+
+    lci_expression * expression = nullptr;
+    int return_code = lci_expression_parse(&expression, "active(\"Blank.esm\") or active(\"Blank.esm\")");
+
+    assert(return_code == LCI_OK);
+
+    lci_function_calls * calls = nullptr;
+    return_code = lci_expression_function_calls(&calls, expression);
+
+    assert(return_code == LCI_OK);
+    assert(calls != nullptr);
+
+    const lci_function_call * array = nullptr;
+    size_t array_size = 0;
+    return_code = lci_function_calls_get(calls, &array, &array_size);
+
+    assert(return_code == LCI_OK);
+    assert(array_size == 2);
+    assert(strcmp(array[0].path, "Blank.esm") == 0);
+    assert(strcmp(array[1].path, "Blank.esm") == 0);
+
+    return_code = lci_function_calls_get(calls, nullptr, &array_size);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    lci_function_calls_destroy(calls);
+
+    return_code = lci_expression_function_calls(&calls, nullptr);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    lci_expression_destroy(expression);
+}
+
+void test_lci_conditions_eval_batch() {
+    printf("testing lci_conditions_eval_batch()...\n");
+
+    lci_state * state = nullptr;
+    int return_code = lci_state_create(&state, LCI_GAME_OBLIVION, ".");
+
+    assert(return_code == LCI_OK);
+    assert(state != nullptr);
+
+    char const * plugins[] = { "Blank.esm" };
+    return_code = lci_state_set_active_plugins(state, plugins, 1);
+    assert(return_code == LCI_OK);
+
+    char const * conditions[] = {
+        "active(\"Blank.esm\")",
+        "active(\"Blank.esp\")",
+        "file(\"Blank.",
+        nullptr,
+    };
+    int results[4] = { 0 };
+
+    return_code = lci_conditions_eval_batch(conditions, 4, nullptr, false, results);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    return_code = lci_conditions_eval_batch(conditions, 4, state, false, nullptr);
+    assert(return_code == LCI_ERROR_INVALID_ARGS);
+
+    return_code = lci_conditions_eval_batch(nullptr, 0, state, false, nullptr);
+    assert(return_code == LCI_OK);
+
+    for (bool parallel : { false, true }) {
+        memset(results, 0, sizeof(results));
+
+        return_code = lci_conditions_eval_batch(conditions, 4, state, parallel, results);
+        assert(return_code == LCI_OK);
+
+        assert(results[0] == LCI_RESULT_TRUE);
+        assert(results[1] == LCI_RESULT_FALSE);
+        assert(results[2] == LCI_ERROR_PARSING_ERROR);
+        assert(results[3] == LCI_ERROR_INVALID_ARGS);
+    }
+
+    lci_state_destroy(state);
+}
+
+void test_lci_expressions_eval_batch() {
+    printf("testing lci_expressions_eval_batch()...\n");
+
+    lci_state * state = nullptr;
+    int return_code = lci_state_create(&state, LCI_GAME_OBLIVION, ".");
+
+    assert(return_code == LCI_OK);
+    assert(state != nullptr);
+
+    char const * plugins[] = { "Blank.esm" };
+    return_code = lci_state_set_active_plugins(state, plugins, 1);
+    assert(return_code == LCI_OK);
+
+    std::vector<lci_expression *> expressions(100, nullptr);
+    for (size_t i = 0; i < expressions.size(); i += 1) {
+        const char * condition = i % 2 == 0 ? "active(\"Blank.esm\")" : "active(\"Blank.esp\")";
+        return_code = lci_expression_parse(&expressions[i], condition);
+        assert(return_code == LCI_OK);
+    }
+
+    std::vector<int> results(expressions.size(), -1);
+
+    for (bool parallel : { false, true }) {
+        return_code = lci_expressions_eval_batch(expressions.data(), expressions.size(), state, parallel, results.data());
+        assert(return_code == LCI_OK);
+
+        for (size_t i = 0; i < results.size(); i += 1) {
+            assert(results[i] == (i % 2 == 0 ? LCI_RESULT_TRUE : LCI_RESULT_FALSE));
+        }
+    }
+
+    for (auto expression : expressions) {
+        lci_expression_destroy(expression);
+    }
+
+    lci_state_destroy(state);
+}
+
 void test_lci_state_set_active_plugins() {
     printf("testing lci_state_set_active_plugins()...\n");
 
@@ -116,6 +506,41 @@ void test_lci_state_set_active_plugins() {
     lci_state_destroy(state);
 }
 
+void test_lci_state_set_load_order() {
+    printf("testing lci_state_set_load_order()...\n");
+
+    lci_state * state = nullptr;
+    int return_code = lci_state_create(&state, LCI_GAME_SKYRIM_SE, ".");
+
+    assert(return_code == LCI_OK);
+    assert(state != nullptr);
+
+    char const * plugins[] = { "Blank.esm", "Blank.esp" };
+
+    return_code = lci_state_set_load_order(state, plugins, 0);
+    assert(return_code != LCI_OK);
+
+    return_code = lci_state_set_load_order(state, nullptr, 2);
+    assert(return_code != LCI_OK);
+
+    return_code = lci_state_set_load_order(state, plugins, 2);
+    assert(return_code == LCI_OK);
+
+    return_code = lci_condition_eval("load_after(\"Blank.esp\", \"Blank.esm\")", state);
+    assert(return_code == LCI_RESULT_TRUE);
+
+    return_code = lci_condition_eval("load_index(\"Blank.esp\", ==, 1)", state);
+    assert(return_code == LCI_RESULT_TRUE);
+
+    return_code = lci_state_set_load_order(state, nullptr, 0);
+    assert(return_code == LCI_OK);
+
+    return_code = lci_condition_eval("load_after(\"Blank.esp\", \"Blank.esm\")", state);
+    assert(return_code == LCI_RESULT_FALSE);
+
+    lci_state_destroy(state);
+}
+
 void test_lci_state_set_plugin_versions() {
     printf("testing lci_state_set_plugin_versions()...\n");
 
@@ -219,10 +644,22 @@ int main(void) {
 
     test_lci_condition_parse();
     test_lci_get_error_message();
+    test_lci_get_error_details();
 
     test_lci_state_create();
+    test_lci_expression_parse();
+    test_lci_expression_eval();
+    test_lci_expression_to_string();
+    test_lci_condition_eval_trace();
+    test_lci_expression_eval_trace();
+    test_lci_condition_function_calls();
+    test_lci_expression_function_calls();
+    test_lci_conditions_eval_batch();
+    test_lci_expressions_eval_batch();
     test_lci_condition_eval();
+    test_lci_condition_eval_with_an_invalid_archive();
     test_lci_state_set_active_plugins();
+    test_lci_state_set_load_order();
     test_lci_state_set_plugin_versions();
     test_lci_state_set_crc_cache();
     test_lci_state_set_additional_data_paths();
@@ -0,0 +1,601 @@
+//! Reading the lists of files that are stored in the BSA and BA2 archives that
+//! the games load, so that functions can see archived files as well as loose
+//! files.
+mod ba2;
+mod bsa;
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::{self, BufReader, Read, Seek};
+use std::path::{Component, Path};
+use std::sync::Arc;
+
+use crate::{Error, FileSystem, GameType, State};
+
+/// The files stored in one or more archives.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ArchiveIndex {
+    /// Lowercased file paths using forward slashes as separators, and the
+    /// files' sizes, if they are known.
+    files: HashMap<String, Option<u64>>,
+    /// Lowercased directory paths using forward slashes as separators, and
+    /// the lowercased names of their files and subdirectories. The root
+    /// directory's path is an empty string.
+    dirs: HashMap<String, BTreeSet<String>>,
+}
+
+impl ArchiveIndex {
+    pub(crate) fn read(file_system: &dyn FileSystem, path: &Path) -> Result<Self, Error> {
+        let mut index = ArchiveIndex::default();
+        index.add_archive(file_system, path)?;
+
+        Ok(index)
+    }
+
+    /// Adds the files in the archive at the given path, replacing the sizes of
+    /// any files that are already in the index.
+    fn add_archive(&mut self, file_system: &dyn FileSystem, path: &Path) -> Result<(), Error> {
+        let file = file_system
+            .open(path)
+            .map_err(|e| Error::IoError(path.to_path_buf(), e))?;
+        let mut reader = BufReader::new(file);
+
+        let files = read_files(&mut reader)
+            .map_err(|e| Error::ArchiveParsingError(path.to_path_buf(), e.into()))?;
+
+        for (path, size) in files {
+            self.insert(&path, size);
+        }
+
+        Ok(())
+    }
+
+    fn insert(&mut self, path: &str, size: Option<u64>) {
+        let path = path.replace('\\', "/").to_lowercase();
+        let path = path.trim_start_matches('/');
+
+        let mut child = path;
+        while !child.is_empty() {
+            let (parent, name) = child.rsplit_once('/').unwrap_or(("", child));
+            let is_new = self
+                .dirs
+                .entry(parent.to_owned())
+                .or_default()
+                .insert(name.to_owned());
+
+            if !is_new {
+                break;
+            }
+            child = parent;
+        }
+
+        self.files.insert(path.to_owned(), size);
+    }
+
+    /// Checks if the index contains a file or directory at the given path.
+    pub(crate) fn contains(&self, path: &Path) -> bool {
+        archive_path(path).is_some_and(|p| {
+            self.files.contains_key(&p) || (!p.is_empty() && self.dirs.contains_key(&p))
+        })
+    }
+
+    pub(crate) fn contains_file(&self, path: &Path) -> bool {
+        archive_path(path).is_some_and(|p| self.files.contains_key(&p))
+    }
+
+    /// Gets the size of the file at the given path, if the file is in an
+    /// archive and its size is known.
+    pub(crate) fn file_size(&self, path: &Path) -> Option<u64> {
+        let path = archive_path(path)?;
+        self.files.get(&path).copied().flatten()
+    }
+
+    /// Gets the lowercased names of the files and subdirectories in the given
+    /// directory.
+    pub(crate) fn dir_entries(&self, path: &Path) -> impl Iterator<Item = &str> + use<'_> {
+        archive_path(path)
+            .and_then(|p| self.dirs.get(&p))
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+}
+
+/// Converts a path relative to the data path into the form used as a key in
+/// an archive index, or `None` if the path is outside the data path.
+fn archive_path(path: &Path) -> Option<String> {
+    let mut components = Vec::new();
+
+    for component in path.components() {
+        match component {
+            Component::Normal(c) => components.push(c.to_str()?.to_lowercase()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                components.pop()?;
+            }
+            Component::RootDir | Component::Prefix(_) => return None,
+        }
+    }
+
+    Some(components.join("/"))
+}
+
+fn read_files<T: Read + Seek>(reader: &mut T) -> io::Result<Vec<(String, Option<u64>)>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+
+    match &magic {
+        bsa::MAGIC => bsa::read_files(reader),
+        ba2::MAGIC => ba2::read_files(reader),
+        _ => Err(invalid_data("Unknown archive file magic")),
+    }
+}
+
+/// Checks if the game loads the given archive when the given plugin is active,
+/// based on their filenames. Archives that are loaded through INI settings
+/// aren't taken into account.
+fn is_loaded_by(game_type: GameType, archive_name: &str, plugin_name: &str) -> bool {
+    let archive_name = archive_name.to_lowercase();
+    let plugin_name = plugin_name.to_lowercase();
+
+    let Some((plugin_stem, _)) = plugin_name.rsplit_once('.') else {
+        return false;
+    };
+    let Some((archive_stem, archive_extension)) = archive_name.rsplit_once('.') else {
+        return false;
+    };
+    let suffix = archive_stem.strip_prefix(plugin_stem);
+
+    match game_type {
+        GameType::Oblivion => archive_extension == "bsa" && suffix.is_some(),
+        GameType::Skyrim | GameType::SkyrimSE | GameType::SkyrimVR => {
+            archive_extension == "bsa" && matches!(suffix, Some("" | " - textures"))
+        }
+        GameType::Fallout3 | GameType::FalloutNV => {
+            archive_extension == "bsa"
+                && suffix.is_some_and(|s| s.is_empty() || s.starts_with(" - "))
+        }
+        GameType::Fallout4 | GameType::Fallout4VR | GameType::Starfield => {
+            archive_extension == "ba2" && suffix.is_some_and(|s| s.starts_with(" - "))
+        }
+        GameType::Morrowind | GameType::OpenMW => false,
+    }
+}
+
+fn read_loaded_archives(state: &State) -> Result<ArchiveIndex, Error> {
+    let mut index = ArchiveIndex::default();
+
+    for base_path in state
+        .additional_data_paths
+        .iter()
+        .chain(std::iter::once(&state.data_path))
+    {
+        let Ok(entries) = state.file_system.read_dir(base_path) else {
+            continue;
+        };
+
+        for name in entries {
+            let name = name.map_err(|e| Error::IoError(base_path.clone(), e))?;
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+
+            if !state
+                .active_plugins
+                .iter()
+                .any(|p| is_loaded_by(state.game_type, name, p))
+            {
+                continue;
+            }
+
+            // An archive that can't be parsed is treated as empty, so that it
+            // doesn't stop the files in other archives from being found.
+            match index.add_archive(state.file_system.as_ref(), &base_path.join(name)) {
+                Ok(()) | Err(Error::ArchiveParsingError(_, _)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(index)
+}
+
+/// Gets the index of the files in the archives that are loaded by the active
+/// plugins, reading the archives if they haven't already been read. Returns
+/// `None` if archive lookup is disabled.
+pub(crate) fn loaded_archives(state: &State) -> Result<Option<Arc<ArchiveIndex>>, Error> {
+    if !state.archive_lookup {
+        return Ok(None);
+    }
+
+    if let Some(index) = state.archive_index.read().ok().and_then(|i| i.clone()) {
+        return Ok(Some(index));
+    }
+
+    let index = Arc::new(read_loaded_archives(state)?);
+
+    if let Ok(mut writer) = state.archive_index.write() {
+        *writer = Some(Arc::clone(&index));
+    }
+
+    Ok(Some(index))
+}
+
+fn read_u8<T: Read>(reader: &mut T) -> io::Result<u8> {
+    let mut bytes = [0u8; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(u8::from_le_bytes(bytes))
+}
+
+fn read_u16<T: Read>(reader: &mut T) -> io::Result<u16> {
+    let mut bytes = [0u8; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32<T: Read>(reader: &mut T) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<T: Read>(reader: &mut T) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Reads a string of the given length, which is not necessarily valid UTF-8
+/// as the games use Windows-1252. The length comes from the archive, so the
+/// string is read incrementally instead of allocating the whole length up
+/// front, in case the length is larger than the archive.
+fn read_string<T: Read>(reader: &mut T, length: usize) -> io::Result<String> {
+    let length = u64::try_from(length).map_err(io::Error::other)?;
+
+    let mut bytes = Vec::new();
+    reader.take(length).read_to_end(&mut bytes)?;
+
+    if u64::try_from(bytes.len()).map_err(io::Error::other)? != length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_owned())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    use crate::{Expression, MemoryFileSystem};
+
+    type File<'a> = (&'a str, &'a [u8]);
+
+    fn to_u32(value: usize) -> u32 {
+        u32::try_from(value).unwrap()
+    }
+
+    /// Builds an uncompressed BSA of the given version that contains the given
+    /// files, which must be grouped by folder.
+    pub(crate) fn bsa(version: u32, files: &[File]) -> Vec<u8> {
+        let mut folders: Vec<(&str, Vec<File>)> = Vec::new();
+        for (path, data) in files {
+            let (folder, name) = path.rsplit_once('\\').unwrap();
+            match folders.last_mut() {
+                Some((f, files)) if *f == folder => files.push((name, data)),
+                _ => folders.push((folder, vec![(name, data)])),
+            }
+        }
+
+        let folder_record_size = if version == 105 { 24 } else { 16 };
+        let file_names_length: usize = files
+            .iter()
+            .map(|(p, _)| p.rsplit_once('\\').unwrap().1.len() + 1)
+            .sum();
+        let file_record_blocks_length: usize = folders
+            .iter()
+            .map(|(f, files)| f.len() + 2 + files.len() * 16)
+            .sum();
+        let mut data_offset =
+            36 + folders.len() * folder_record_size + file_record_blocks_length + file_names_length;
+
+        let mut bytes = b"BSA\0".to_vec();
+        for value in [
+            version,
+            36,
+            0x3,
+            to_u32(folders.len()),
+            to_u32(files.len()),
+            to_u32(folders.iter().map(|(f, _)| f.len() + 1).sum()),
+            to_u32(file_names_length),
+            0,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+
+        for (_, files) in &folders {
+            bytes.extend([0; 8]);
+            bytes.extend(to_u32(files.len()).to_le_bytes());
+            bytes.extend([0; 4]);
+            if version == 105 {
+                bytes.extend([0; 8]);
+            }
+        }
+
+        for (folder, files) in &folders {
+            bytes.push(u8::try_from(folder.len() + 1).unwrap());
+            bytes.extend(folder.as_bytes());
+            bytes.push(0);
+            for (_, data) in files {
+                bytes.extend([0; 8]);
+                bytes.extend(to_u32(data.len()).to_le_bytes());
+                bytes.extend(to_u32(data_offset).to_le_bytes());
+                data_offset += data.len();
+            }
+        }
+
+        for (_, files) in &folders {
+            for (name, _) in files {
+                bytes.extend(name.as_bytes());
+                bytes.push(0);
+            }
+        }
+
+        for (_, data) in files {
+            bytes.extend(*data);
+        }
+
+        bytes
+    }
+
+    /// Builds an uncompressed general BA2 that contains the given files.
+    pub(crate) fn ba2(files: &[File]) -> Vec<u8> {
+        let mut data_offset = 24 + files.len() * 36;
+        let name_table_offset = data_offset + files.iter().map(|(_, d)| d.len()).sum::<usize>();
+
+        let mut bytes = b"BTDX".to_vec();
+        bytes.extend(1_u32.to_le_bytes());
+        bytes.extend(b"GNRL");
+        bytes.extend(to_u32(files.len()).to_le_bytes());
+        bytes.extend(u64::try_from(name_table_offset).unwrap().to_le_bytes());
+
+        for (_, data) in files {
+            bytes.extend([0; 16]);
+            bytes.extend(u64::try_from(data_offset).unwrap().to_le_bytes());
+            bytes.extend(0_u32.to_le_bytes());
+            bytes.extend(to_u32(data.len()).to_le_bytes());
+            bytes.extend(0xBAAD_F00D_u32.to_le_bytes());
+            data_offset += data.len();
+        }
+
+        for (_, data) in files {
+            bytes.extend(*data);
+        }
+
+        for (path, _) in files {
+            bytes.extend(u16::try_from(path.len()).unwrap().to_le_bytes());
+            bytes.extend(path.as_bytes());
+        }
+
+        bytes
+    }
+
+    /// Builds a texture BA2 that contains the given paths, each with a single
+    /// empty chunk.
+    pub(crate) fn ba2_textures(paths: &[&str]) -> Vec<u8> {
+        let name_table_offset = 24 + paths.len() * 48;
+
+        let mut bytes = b"BTDX".to_vec();
+        bytes.extend(8_u32.to_le_bytes());
+        bytes.extend(b"DX10");
+        bytes.extend(to_u32(paths.len()).to_le_bytes());
+        bytes.extend(u64::try_from(name_table_offset).unwrap().to_le_bytes());
+
+        for _ in paths {
+            bytes.extend([0; 13]);
+            bytes.push(1);
+            bytes.extend(24_u16.to_le_bytes());
+            bytes.extend([0; 8]);
+            bytes.extend([0; 24]);
+        }
+
+        for path in paths {
+            bytes.extend(u16::try_from(path.len()).unwrap().to_le_bytes());
+            bytes.extend(path.as_bytes());
+        }
+
+        bytes
+    }
+
+    fn read(bytes: Vec<u8>) -> ArchiveIndex {
+        let fs = MemoryFileSystem::new().with_file("a", bytes);
+
+        let mut index = ArchiveIndex::default();
+        index.add_archive(&fs, Path::new("a")).unwrap();
+        index
+    }
+
+    fn entries(index: &ArchiveIndex, path: &str) -> Vec<String> {
+        index
+            .dir_entries(Path::new(path))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[test]
+    fn read_should_read_the_file_paths_and_sizes_of_all_bsa_versions() {
+        for version in [103, 104, 105] {
+            let index = read(bsa(
+                version,
+                &[
+                    ("meshes\\armor", b"abc"),
+                    ("textures\\Armor\\A.dds", b""),
+                    ("textures\\Armor\\B.dds", b"de"),
+                ],
+            ));
+
+            assert!(index.contains(Path::new("meshes/armor")));
+            assert!(index.contains(Path::new("textures/armor/a.dds")));
+            assert_eq!(Some(3), index.file_size(Path::new("meshes/armor")));
+            assert_eq!(Some(2), index.file_size(Path::new("Textures/Armor/B.dds")));
+            assert_eq!(vec!["meshes", "textures"], entries(&index, ""));
+            assert_eq!(vec!["a.dds", "b.dds"], entries(&index, "textures/armor"));
+        }
+    }
+
+    #[test]
+    fn read_should_read_the_file_paths_and_sizes_of_ba2s() {
+        let index = read(ba2(&[("Meshes\\A.nif", b"abc"), ("Scripts\\B.pex", b"")]));
+
+        assert!(index.contains(Path::new("meshes/a.nif")));
+        assert_eq!(Some(3), index.file_size(Path::new("meshes/a.nif")));
+        assert_eq!(Some(0), index.file_size(Path::new("scripts/b.pex")));
+
+        let index = read(ba2_textures(&["Textures\\A.dds"]));
+
+        assert!(index.contains(Path::new("textures/a.dds")));
+        assert_eq!(None, index.file_size(Path::new("textures/a.dds")));
+    }
+
+    #[test]
+    fn read_should_error_if_the_file_is_not_an_archive() {
+        let fs = MemoryFileSystem::new().with_file("a", "not an archive");
+
+        let mut index = ArchiveIndex::default();
+
+        assert!(index.add_archive(&fs, Path::new("a")).is_err());
+        assert!(index.add_archive(&fs, Path::new("b")).is_err());
+    }
+
+    #[test]
+    fn read_should_error_if_a_length_is_larger_than_the_archive() {
+        let mut bsa = bsa(105, &[("meshes\\a.nif", b"abc")]);
+        // Set the total file name length.
+        bsa.splice(28..32, u32::MAX.to_le_bytes());
+
+        let mut ba2 = ba2_textures(&["Textures\\A.dds"]);
+        // Set the file count.
+        ba2.splice(12..16, u32::MAX.to_le_bytes());
+
+        let fs = MemoryFileSystem::new()
+            .with_file("a", bsa)
+            .with_file("b", ba2);
+
+        let mut index = ArchiveIndex::default();
+
+        assert!(index.add_archive(&fs, Path::new("a")).is_err());
+        assert!(index.add_archive(&fs, Path::new("b")).is_err());
+    }
+
+    #[test]
+    fn archive_path_should_be_none_for_paths_outside_the_data_path() {
+        assert_eq!(
+            Some("a/b".to_owned()),
+            archive_path(Path::new("./A/c/../B"))
+        );
+        assert_eq!(None, archive_path(Path::new("../a")));
+    }
+
+    #[test]
+    fn is_loaded_by_should_match_archive_names_to_plugin_names() {
+        assert!(is_loaded_by(GameType::Oblivion, "Blank.bsa", "Blank.esp"));
+        assert!(is_loaded_by(GameType::Oblivion, "Blank2.bsa", "Blank.esp"));
+        assert!(is_loaded_by(GameType::SkyrimSE, "blank.bsa", "Blank.esm"));
+        assert!(is_loaded_by(
+            GameType::SkyrimSE,
+            "Blank - Textures.bsa",
+            "Blank.esm"
+        ));
+        assert!(!is_loaded_by(
+            GameType::SkyrimSE,
+            "Blank - Meshes.bsa",
+            "Blank.esm"
+        ));
+        assert!(is_loaded_by(
+            GameType::FalloutNV,
+            "Blank - Meshes.bsa",
+            "Blank.esm"
+        ));
+        assert!(is_loaded_by(
+            GameType::Fallout4,
+            "Blank - Main.ba2",
+            "Blank.esm"
+        ));
+        assert!(!is_loaded_by(GameType::Fallout4, "Blank.ba2", "Blank.esm"));
+        assert!(!is_loaded_by(
+            GameType::Fallout4,
+            "Blank - Main.bsa",
+            "Blank.esm"
+        ));
+        assert!(!is_loaded_by(GameType::Morrowind, "Blank.bsa", "Blank.esm"));
+    }
+
+    #[test]
+    fn state_should_only_see_archived_files_if_archive_lookup_is_enabled() {
+        let fs = MemoryFileSystem::new()
+            .with_file("Data/Loose.nif", "")
+            .with_file(
+                "Data/Blank.bsa",
+                bsa(
+                    105,
+                    &[
+                        ("meshes\\a.nif", b"abc"),
+                        ("meshes\\b.nif", b""),
+                        ("meshes\\sub\\c.nif", b""),
+                    ],
+                ),
+            )
+            .with_file("Data/Other.bsa", bsa(105, &[("meshes\\d.nif", b"")]));
+        let state = |archive_lookup| {
+            State::new(GameType::SkyrimSE, PathBuf::from("Data"))
+                .with_active_plugins(&["Blank.esp"])
+                .with_file_system(fs.clone())
+                .with_archive_lookup(archive_lookup)
+        };
+        let eval = |state: &State, condition: &str| {
+            Expression::from_str(condition)
+                .unwrap()
+                .eval(state)
+                .unwrap()
+        };
+
+        assert!(!eval(&state(false), "file(\"meshes/a.nif\")"));
+
+        let state = state(true);
+
+        assert!(eval(
+            &state,
+            "file(\"meshes/a.nif\") and file(\"Loose.nif\")"
+        ));
+        assert!(eval(&state, "file(\"meshes/sub\")"));
+        assert!(!eval(&state, "file(\"meshes/d.nif\")"));
+        assert!(eval(&state, "file_size(\"meshes/a.nif\", 3)"));
+        assert!(eval(&state, "file(\"meshes/s.*\")"));
+        assert!(eval(&state, "many(\"meshes/.\\.nif\")"));
+        assert!(!eval(&state, "many(\"meshes/a\\.nif\")"));
+        assert!(!eval(&state, "many(\"Loose\\.nif\")"));
+    }
+
+    #[test]
+    fn state_should_skip_archives_that_cannot_be_parsed() {
+        let fs = MemoryFileSystem::new()
+            .with_file("Data/Blank.bsa", bsa(105, &[("meshes\\a.nif", b"abc")]))
+            .with_file("Data/Blank - Textures.bsa", "not an archive");
+        let state = State::new(GameType::SkyrimSE, PathBuf::from("Data"))
+            .with_active_plugins(&["Blank.esp"])
+            .with_file_system(fs)
+            .with_archive_lookup(true);
+
+        let expression = Expression::from_str(
+            "file(\"meshes/a.nif\") and file_size(\"meshes/a.nif\", 3) and not many(\"meshes/.*\")",
+        )
+        .unwrap();
+
+        assert!(expression.eval(&state).unwrap());
+    }
+}
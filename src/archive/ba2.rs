@@ -0,0 +1,92 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::{invalid_data, read_string, read_u16, read_u32, read_u64, read_u8};
+
+pub(super) const MAGIC: &[u8; 4] = b"BTDX";
+
+const GENERAL_TYPE: &[u8; 4] = b"GNRL";
+const TEXTURE_TYPE: &[u8; 4] = b"DX10";
+
+const GENERAL_FILE_RECORD_SIZE: i64 = 36;
+const TEXTURE_FILE_RECORD_SIZE: i64 = 24;
+const TEXTURE_CHUNK_SIZE: i64 = 24;
+
+// <https://en.uesp.net/wiki/Fallout4Mod:Archive2_File_Format>
+// <https://en.uesp.net/wiki/Starfield_Mod:Archive2_File_Format>
+pub(super) fn read_files<T: Read + Seek>(reader: &mut T) -> io::Result<Vec<(String, Option<u64>)>> {
+    let version = read_u32(reader)?;
+
+    let mut archive_type = [0u8; 4];
+    reader.read_exact(&mut archive_type)?;
+
+    let file_count = read_u32(reader)?;
+    let name_table_offset = read_u64(reader)?;
+
+    // Starfield's archives have extra header fields.
+    match version {
+        1 | 7 | 8 => {}
+        2 => reader.seek_relative(8)?,
+        3 => reader.seek_relative(12)?,
+        _ => return Err(invalid_data("Unsupported BA2 version")),
+    }
+
+    let sizes = match &archive_type {
+        GENERAL_TYPE => read_general_file_sizes(reader, file_count)?,
+        TEXTURE_TYPE => skip_texture_records(reader, file_count)?,
+        _ => return Err(invalid_data("Unsupported BA2 archive type")),
+    };
+
+    reader.seek(SeekFrom::Start(name_table_offset))?;
+
+    let mut files = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        let length = read_u16(reader)?;
+        let path = read_string(reader, usize::from(length))?;
+
+        files.push((path, size));
+    }
+
+    Ok(files)
+}
+
+fn read_general_file_sizes<T: Read + Seek>(
+    reader: &mut T,
+    file_count: u32,
+) -> io::Result<Vec<Option<u64>>> {
+    let mut sizes = Vec::new();
+
+    for _ in 0..file_count {
+        // Skip the hashes, extension, flags, offset and packed size.
+        reader.seek_relative(GENERAL_FILE_RECORD_SIZE - 8)?;
+        let unpacked_size = read_u32(reader)?;
+        // Skip the alignment padding.
+        read_u32(reader)?;
+
+        sizes.push(Some(u64::from(unpacked_size)));
+    }
+
+    Ok(sizes)
+}
+
+/// Skips over texture file records, giving an unknown size for each. The
+/// sizes of textures aren't known, as they're stored without the DDS headers
+/// that get added when the textures are extracted.
+fn skip_texture_records<T: Read + Seek>(
+    reader: &mut T,
+    file_count: u32,
+) -> io::Result<Vec<Option<u64>>> {
+    let mut sizes = Vec::new();
+
+    for _ in 0..file_count {
+        // Skip the hashes, extension and an unknown byte.
+        reader.seek_relative(13)?;
+        let chunk_count = read_u8(reader)?;
+        // Skip the rest of the record, then its chunks.
+        reader.seek_relative(TEXTURE_FILE_RECORD_SIZE - 14)?;
+        reader.seek_relative(i64::from(chunk_count) * TEXTURE_CHUNK_SIZE)?;
+
+        sizes.push(None);
+    }
+
+    Ok(sizes)
+}
@@ -0,0 +1,177 @@
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::{invalid_data, read_string, read_u32, read_u64, read_u8};
+
+pub(super) const MAGIC: &[u8; 4] = b"BSA\0";
+
+const INCLUDE_DIRECTORY_NAMES: u32 = 0x1;
+const INCLUDE_FILE_NAMES: u32 = 0x2;
+const COMPRESSED_BY_DEFAULT: u32 = 0x4;
+const EMBED_FILE_NAMES: u32 = 0x100;
+
+const COMPRESSION_TOGGLE: u32 = 0x4000_0000;
+const SIZE_MASK: u32 = 0x3FFF_FFFF;
+
+struct Header {
+    version: u32,
+    folder_records_offset: u32,
+    archive_flags: u32,
+    folder_count: u32,
+    total_file_name_length: u32,
+}
+
+impl Header {
+    fn read<T: Read>(reader: &mut T) -> io::Result<Self> {
+        let version = read_u32(reader)?;
+        if !matches!(version, 103..=105) {
+            return Err(invalid_data("Unsupported BSA version"));
+        }
+
+        let folder_records_offset = read_u32(reader)?;
+        let archive_flags = read_u32(reader)?;
+        let folder_count = read_u32(reader)?;
+
+        // Skip the file count and total folder name length, which aren't
+        // needed.
+        read_u64(reader)?;
+
+        let total_file_name_length = read_u32(reader)?;
+
+        Ok(Header {
+            version,
+            folder_records_offset,
+            archive_flags,
+            folder_count,
+            total_file_name_length,
+        })
+    }
+
+    fn has_flag(&self, flag: u32) -> bool {
+        self.archive_flags & flag != 0
+    }
+
+    /// Only v104 and v105 archives can embed file names in file data, v103
+    /// uses the same flag bit for something else.
+    fn embeds_file_names(&self) -> bool {
+        self.version >= 104 && self.has_flag(EMBED_FILE_NAMES)
+    }
+}
+
+struct FileRecord {
+    folder: String,
+    size: u32,
+    offset: u32,
+}
+
+impl FileRecord {
+    fn is_compressed(&self, header: &Header) -> bool {
+        header.has_flag(COMPRESSED_BY_DEFAULT) != (self.size & COMPRESSION_TOGGLE != 0)
+    }
+}
+
+// <https://en.uesp.net/wiki/Skyrim_Mod:Archive_File_Format>
+// <https://en.uesp.net/wiki/Oblivion_Mod:BSA_File_Format>
+pub(super) fn read_files<T: Read + Seek>(reader: &mut T) -> io::Result<Vec<(String, Option<u64>)>> {
+    let header = Header::read(reader)?;
+
+    if !header.has_flag(INCLUDE_DIRECTORY_NAMES) || !header.has_flag(INCLUDE_FILE_NAMES) {
+        return Err(invalid_data(
+            "BSA does not include directory and file names",
+        ));
+    }
+
+    reader.seek(SeekFrom::Start(u64::from(header.folder_records_offset)))?;
+
+    let mut file_counts = Vec::new();
+    for _ in 0..header.folder_count {
+        // Skip the folder name hash.
+        read_u64(reader)?;
+        file_counts.push(read_u32(reader)?);
+
+        // Skip the offset, and also padding in v105.
+        if header.version == 105 {
+            read_u32(reader)?;
+            read_u64(reader)?;
+        } else {
+            read_u32(reader)?;
+        }
+    }
+
+    let mut records = Vec::new();
+    for file_count in file_counts {
+        let length = read_u8(reader)?;
+        let folder = read_string(reader, usize::from(length))?;
+        let folder = folder.trim_end_matches('\0').to_owned();
+
+        for _ in 0..file_count {
+            // Skip the file name hash.
+            read_u64(reader)?;
+            let size = read_u32(reader)?;
+            let offset = read_u32(reader)?;
+
+            records.push(FileRecord {
+                folder: folder.clone(),
+                size,
+                offset,
+            });
+        }
+    }
+
+    let file_names = read_string(reader, to_usize(header.total_file_name_length)?)?;
+    let mut file_names = file_names.split('\0');
+
+    let mut files = Vec::with_capacity(records.len());
+    for record in records {
+        let file_name = file_names
+            .next()
+            .ok_or_else(|| invalid_data("BSA has fewer file names than files"))?;
+
+        let path = if record.folder.is_empty() || record.folder == "." {
+            file_name.to_owned()
+        } else {
+            format!("{}\\{}", record.folder, file_name)
+        };
+
+        let size = read_file_size(reader, &header, &record)?;
+
+        files.push((path, Some(size)));
+    }
+
+    Ok(files)
+}
+
+/// Gets a file's uncompressed size. This may involve reading the start of the
+/// file's data, as the size in the file record includes any embedded file name
+/// and is the compressed size for compressed files.
+fn read_file_size<T: Read + Seek>(
+    reader: &mut T,
+    header: &Header,
+    record: &FileRecord,
+) -> io::Result<u64> {
+    let size = u64::from(record.size & SIZE_MASK);
+    let is_compressed = record.is_compressed(header);
+
+    if !is_compressed && !header.embeds_file_names() {
+        return Ok(size);
+    }
+
+    reader.seek(SeekFrom::Start(u64::from(record.offset)))?;
+
+    let mut prefix_length = 0;
+    if header.embeds_file_names() {
+        let name_length = read_u8(reader)?;
+        reader.seek_relative(i64::from(name_length))?;
+        prefix_length = u64::from(name_length) + 1;
+    }
+
+    if is_compressed {
+        read_u32(reader).map(u64::from)
+    } else {
+        size.checked_sub(prefix_length)
+            .ok_or_else(|| invalid_data("BSA file record size is too small"))
+    }
+}
+
+fn to_usize(value: u32) -> io::Result<usize> {
+    usize::try_from(value).map_err(io::Error::other)
+}
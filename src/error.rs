@@ -8,7 +8,9 @@ use std::path::PathBuf;
 use std::slice::EscapeAscii;
 
 use nom::error::ErrorKind;
-use nom::Err;
+use nom::{Err, Offset};
+
+use crate::span::Span;
 
 #[expect(clippy::error_impl_error)]
 #[derive(Debug)]
@@ -17,9 +19,11 @@ pub enum Error {
     ParsingIncomplete(MoreDataNeeded),
     // The string is the input that was not parsed.
     UnconsumedInput(String),
-    /// The string is the input at which the error was encountered.
-    ParsingError(String, ParsingErrorKind),
+    /// The string is the input at which the error was encountered, and the
+    /// span is the part of the parsed string that the error relates to.
+    ParsingError(String, ParsingErrorKind, Span),
     PeParsingError(PathBuf, Box<dyn error::Error + Send + Sync + 'static>),
+    ArchiveParsingError(PathBuf, Box<dyn error::Error + Send + Sync + 'static>),
     IoError(PathBuf, io::Error),
 }
 
@@ -27,8 +31,10 @@ fn escape<I: fmt::Display>(input: I) -> String {
     input.to_string().replace('"', "\\\"")
 }
 
-impl<I: fmt::Debug + fmt::Display> From<Err<ParsingError<I>>> for Error {
-    fn from(error: Err<ParsingError<I>>) -> Self {
+impl Error {
+    /// Converts an error returned by a parser that was given `source` as its
+    /// input, so that the error's span is relative to the start of `source`.
+    pub(crate) fn from_parsing_error(source: &str, error: Err<ParsingError<&str>>) -> Self {
         match error {
             Err::Incomplete(nom::Needed::Unknown) => {
                 Error::ParsingIncomplete(MoreDataNeeded::UnknownSize)
@@ -36,7 +42,22 @@ impl<I: fmt::Debug + fmt::Display> From<Err<ParsingError<I>>> for Error {
             Err::Incomplete(nom::Needed::Size(size)) => {
                 Error::ParsingIncomplete(MoreDataNeeded::Size(size))
             }
-            Err::Error(e) | Err::Failure(e) => Error::ParsingError(escape(e.input), e.kind),
+            Err::Error(e) | Err::Failure(e) => {
+                let start = source.offset(e.input);
+                let end = (start + e.length).min(source.len());
+                let span = Span::new(source, start, end);
+
+                Error::ParsingError(escape(e.input), e.kind, span)
+            }
+        }
+    }
+
+    /// The part of the parsed string that a parsing error relates to. Other
+    /// errors have no span.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::ParsingError(_, _, span) => Some(*span),
+            _ => None,
         }
     }
 }
@@ -55,7 +76,7 @@ impl fmt::Display for Error {
             Error::UnconsumedInput(i) => {
                 write!(f, "The parser did not consume the following input: \"{i}\"")
             }
-            Error::ParsingError(i, e) => write!(
+            Error::ParsingError(i, e, _) => write!(
                 f,
                 "An error was encountered while parsing the expression \"{i}\": {e}"
             ),
@@ -65,6 +86,12 @@ impl fmt::Display for Error {
                 escape_ascii(p),
                 e
             ),
+            Error::ArchiveParsingError(p, e) => write!(
+                f,
+                "An error was encountered while reading the archive \"{}\": {}",
+                escape_ascii(p),
+                e
+            ),
             Error::IoError(p, e) => write!(
                 f,
                 "An error was encountered while accessing the path \"{}\": {}",
@@ -78,8 +105,8 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
-            Error::ParsingError(_, e) => Some(e),
-            Error::PeParsingError(_, e) => Some(e.as_ref()),
+            Error::ParsingError(_, e, _) => Some(e),
+            Error::PeParsingError(_, e) | Error::ArchiveParsingError(_, e) => Some(e.as_ref()),
             Error::IoError(_, e) => Some(e),
             _ => None,
         }
@@ -98,6 +125,15 @@ pub enum MoreDataNeeded {
 pub struct ParsingError<I: fmt::Debug + fmt::Display> {
     input: I,
     kind: ParsingErrorKind,
+    /// The length of the input that the error relates to, starting from the
+    /// start of the input.
+    length: usize,
+}
+
+impl<I: fmt::Debug + fmt::Display> ParsingError<I> {
+    pub(crate) fn with_length(self, length: usize) -> Self {
+        ParsingError { length, ..self }
+    }
 }
 
 impl<I: fmt::Debug + fmt::Display> From<(I, ErrorKind)> for ParsingError<I> {
@@ -135,6 +171,7 @@ impl<I: fmt::Debug + fmt::Display> nom::error::ParseError<I> for ParsingError<I>
         ParsingError {
             input,
             kind: ParsingErrorKind::GenericParserError(kind.description().to_owned()),
+            length: 0,
         }
     }
 
@@ -144,6 +181,7 @@ impl<I: fmt::Debug + fmt::Display> nom::error::ParseError<I> for ParsingError<I>
 }
 
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
 pub enum ParsingErrorKind {
     InvalidRegexSyntax(String),
     InvalidRegexUnknown,
@@ -151,11 +189,49 @@ pub enum ParsingErrorKind {
     PathEndsInADirectorySeparator(PathBuf),
     PathIsNotInGameDirectory(PathBuf),
     GenericParserError(String),
+    /// The name of the unknown function, and the most similar known function
+    /// name, if any are similar enough.
+    UnknownFunction(String, Option<&'static str>),
+    /// An argument was expected in a call to the named function, after the
+    /// given argument or after the opening parenthesis if there is none.
+    ExpectedArgument {
+        function: &'static str,
+        expected: FunctionArgument,
+        after: Option<FunctionArgument>,
+    },
+    /// The named function was given more arguments than it accepts.
+    UnexpectedArgument(&'static str),
+    /// The named function's arguments contain a character where it isn't
+    /// allowed, such as whitespace after the opening parenthesis.
+    UnexpectedCharacter(&'static str, char),
+    /// The named function's arguments were not enclosed in parentheses.
+    ExpectedOpeningParenthesis(&'static str),
+    /// A condition was expected after the given keyword.
+    ExpectedCondition(&'static str),
+    UnterminatedString,
+    UnbalancedParenthesis,
+    UnmatchedClosingParenthesis,
+    /// A condition was not followed by 'and', 'or' or the end of its
+    /// expression.
+    ExpectedOperator,
+    /// Input that is not a function or a parenthesised expression was found
+    /// where a condition was expected.
+    InvalidCondition,
 }
 
 impl ParsingErrorKind {
+    /// The error occurred at the start of the given input.
     pub fn at<I: fmt::Debug + fmt::Display>(self, input: I) -> ParsingError<I> {
-        ParsingError { input, kind: self }
+        ParsingError {
+            input,
+            kind: self,
+            length: 0,
+        }
+    }
+
+    /// The error relates to the whole of the given input.
+    pub(crate) fn spanning(self, input: &str) -> ParsingError<&str> {
+        self.at(input).with_length(input.len())
     }
 }
 
@@ -196,6 +272,77 @@ impl fmt::Display for ParsingErrorKind {
                 write!(f, "\"{}\" is not in the game directory", escape_ascii(p))
             }
             ParsingErrorKind::GenericParserError(e) => write!(f, "Error in parser: {e}"),
+            ParsingErrorKind::UnknownFunction(name, Some(suggestion)) => {
+                write!(f, "Unknown function `{name}`, did you mean `{suggestion}`?")
+            }
+            ParsingErrorKind::UnknownFunction(name, None) => {
+                write!(f, "Unknown function `{name}`")
+            }
+            ParsingErrorKind::ExpectedArgument {
+                function,
+                expected,
+                after: Some(after),
+            } => write!(f, "Expected {expected} after {after} in `{function}(...)`"),
+            ParsingErrorKind::ExpectedArgument {
+                function,
+                expected,
+                after: None,
+            } => write!(f, "Expected {expected} after `(` in `{function}(...)`"),
+            ParsingErrorKind::UnexpectedArgument(function) => {
+                write!(f, "Too many arguments in `{function}(...)`")
+            }
+            ParsingErrorKind::UnexpectedCharacter(function, c) => write!(
+                f,
+                "Unexpected character '{}' in `{function}(...)`",
+                c.escape_default()
+            ),
+            ParsingErrorKind::ExpectedOpeningParenthesis(function) => {
+                write!(f, "Expected `(` after `{function}`")
+            }
+            ParsingErrorKind::ExpectedCondition(keyword) => {
+                write!(f, "Expected a condition after `{keyword}`")
+            }
+            ParsingErrorKind::UnterminatedString => write!(f, "String has no closing quote"),
+            ParsingErrorKind::UnbalancedParenthesis => {
+                write!(f, "Opening parenthesis has no matching closing parenthesis")
+            }
+            ParsingErrorKind::UnmatchedClosingParenthesis => {
+                write!(f, "Closing parenthesis has no matching opening parenthesis")
+            }
+            ParsingErrorKind::ExpectedOperator => write!(f, "Expected `and` or `or`"),
+            ParsingErrorKind::InvalidCondition => {
+                write!(f, "Expected a function or a parenthesised expression")
+            }
+        }
+    }
+}
+
+/// The types of argument that functions take, for describing parsing errors.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum FunctionArgument {
+    Path,
+    Regex,
+    FileSize,
+    Crc,
+    Version,
+    Comparator,
+    RecordCount,
+    HeaderVersion,
+    LoadIndex,
+}
+
+impl fmt::Display for FunctionArgument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FunctionArgument::Path => write!(f, "path"),
+            FunctionArgument::Regex => write!(f, "regex"),
+            FunctionArgument::FileSize => write!(f, "file size"),
+            FunctionArgument::Crc => write!(f, "CRC"),
+            FunctionArgument::Version => write!(f, "version"),
+            FunctionArgument::Comparator => write!(f, "comparator"),
+            FunctionArgument::RecordCount => write!(f, "record count"),
+            FunctionArgument::HeaderVersion => write!(f, "header version"),
+            FunctionArgument::LoadIndex => write!(f, "load index"),
         }
     }
 }
@@ -0,0 +1,505 @@
+//! Evaluation that records why an expression evaluated to what it did, as a
+//! tree of traces that mirrors the expression's structure.
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::{CompoundCondition, Condition, Error, Expression, Function, State};
+
+/// Where a function's result came from.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum ResultSource {
+    /// The function was evaluated against the current state.
+    Evaluated,
+    /// The result was cached by an earlier evaluation of the same function.
+    ConditionCache,
+    /// The function is a checksum, and the CRC it was compared against was
+    /// cached, either by an earlier evaluation or by [`State::set_cached_crcs`].
+    CrcCache,
+}
+
+/// The result of evaluating a function.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FunctionEvaluation {
+    pub(crate) result: bool,
+    pub(crate) source: ResultSource,
+    pub(crate) resolved_path: Option<PathBuf>,
+}
+
+impl FunctionEvaluation {
+    /// The function's result, before any negation by 'not'.
+    pub fn result(&self) -> bool {
+        self.result
+    }
+
+    pub fn source(&self) -> ResultSource {
+        self.source
+    }
+
+    /// The path that the function's path argument resolved to, taking the
+    /// additional data paths and ghosted plugins into account. Functions that
+    /// don't look up a single file, like those that take a regex, and
+    /// functions that only found their file in an archive, have no resolved
+    /// path.
+    pub fn resolved_path(&self) -> Option<&Path> {
+        self.resolved_path.as_deref()
+    }
+}
+
+/// The trace of an expression's evaluation. An expression's compound
+/// conditions are evaluated in order until one is true, and any after that
+/// are skipped.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExpressionTrace {
+    result: Option<bool>,
+    compound_conditions: Vec<CompoundConditionTrace>,
+}
+
+impl ExpressionTrace {
+    /// The expression's result, or `None` if it was skipped.
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    pub fn compound_conditions(&self) -> &[CompoundConditionTrace] {
+        &self.compound_conditions
+    }
+
+    /// The index of the compound condition that was true and so caused the
+    /// compound conditions after it to be skipped, if any were skipped.
+    pub fn short_circuited_at(&self) -> Option<usize> {
+        short_circuited_at(self.compound_conditions.iter().map(|c| c.result), true)
+    }
+}
+
+/// The trace of a compound condition's evaluation. A compound condition's
+/// conditions are evaluated in order until one is false, and any after that
+/// are skipped.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompoundConditionTrace {
+    result: Option<bool>,
+    conditions: Vec<ConditionTrace>,
+}
+
+impl CompoundConditionTrace {
+    /// The compound condition's result, or `None` if it was skipped.
+    pub fn result(&self) -> Option<bool> {
+        self.result
+    }
+
+    pub fn conditions(&self) -> &[ConditionTrace] {
+        &self.conditions
+    }
+
+    /// The index of the condition that was false and so caused the conditions
+    /// after it to be skipped, if any were skipped.
+    pub fn short_circuited_at(&self) -> Option<usize> {
+        short_circuited_at(self.conditions.iter().map(ConditionTrace::result), false)
+    }
+}
+
+/// The trace of a condition's evaluation.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum ConditionTrace {
+    /// The evaluation is `None` if the condition was skipped.
+    Function {
+        function: Function,
+        inverted: bool,
+        evaluation: Option<FunctionEvaluation>,
+    },
+    Expression {
+        inverted: bool,
+        trace: ExpressionTrace,
+    },
+    /// A condition that could not be parsed, which can only be skipped, as
+    /// evaluating it is an error.
+    Invalid(String),
+}
+
+impl ConditionTrace {
+    /// The condition's result, after any negation by 'not', or `None` if it
+    /// was skipped.
+    pub fn result(&self) -> Option<bool> {
+        match self {
+            ConditionTrace::Function {
+                inverted,
+                evaluation,
+                ..
+            } => evaluation.as_ref().map(|e| e.result != *inverted),
+            ConditionTrace::Expression { inverted, trace } => trace.result.map(|r| r != *inverted),
+            ConditionTrace::Invalid(_) => None,
+        }
+    }
+}
+
+fn short_circuited_at(
+    results: impl ExactSizeIterator<Item = Option<bool>>,
+    short_circuit_result: bool,
+) -> Option<usize> {
+    let length = results.len();
+    let mut results = results.enumerate();
+
+    results
+        .find(|(_, result)| *result == Some(short_circuit_result))
+        .map(|(index, _)| index)
+        .filter(|index| index + 1 < length)
+}
+
+/// Evaluates the given expression in the same way as [`Expression::eval`].
+pub(crate) fn explain(expression: &Expression, state: &State) -> Result<ExpressionTrace, Error> {
+    trace_expression(expression, Some(state))
+}
+
+/// Nodes are traced as skipped if no state is given.
+fn trace_expression(
+    expression: &Expression,
+    mut state: Option<&State>,
+) -> Result<ExpressionTrace, Error> {
+    let mut result = state.map(|_| false);
+    let mut compound_conditions = Vec::new();
+
+    for compound_condition in expression.compound_conditions() {
+        let trace = trace_compound_condition(compound_condition, state)?;
+        if trace.result == Some(true) {
+            result = Some(true);
+            state = None;
+        }
+        compound_conditions.push(trace);
+    }
+
+    Ok(ExpressionTrace {
+        result,
+        compound_conditions,
+    })
+}
+
+fn trace_compound_condition(
+    compound_condition: &CompoundCondition,
+    mut state: Option<&State>,
+) -> Result<CompoundConditionTrace, Error> {
+    let mut result = state.map(|_| true);
+    let mut conditions = Vec::new();
+
+    for condition in compound_condition.conditions() {
+        let trace = trace_condition(condition, state)?;
+        if trace.result() == Some(false) {
+            result = Some(false);
+            state = None;
+        }
+        conditions.push(trace);
+    }
+
+    Ok(CompoundConditionTrace { result, conditions })
+}
+
+fn trace_condition(condition: &Condition, state: Option<&State>) -> Result<ConditionTrace, Error> {
+    let (function, inverted) = match condition {
+        Condition::Function(f) => (f, false),
+        Condition::InvertedFunction(f) => (f, true),
+        Condition::Expression(e) => {
+            return Ok(ConditionTrace::Expression {
+                inverted: false,
+                trace: trace_expression(e, state)?,
+            })
+        }
+        Condition::InvertedExpression(e) => {
+            return Ok(ConditionTrace::Expression {
+                inverted: true,
+                trace: trace_expression(e, state)?,
+            })
+        }
+        Condition::Invalid(i) => {
+            return match state {
+                Some(_) => Err(Error::UnconsumedInput(i.clone())),
+                None => Ok(ConditionTrace::Invalid(i.clone())),
+            }
+        }
+    };
+
+    Ok(ConditionTrace::Function {
+        function: function.clone(),
+        inverted,
+        evaluation: state.map(|s| function.explain(s)).transpose()?,
+    })
+}
+
+/// Writes the trace as an indented tree, with one condition per line and each
+/// line ending with the condition's result.
+impl fmt::Display for ExpressionTrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_expression(f, self, 0)
+    }
+}
+
+fn write_result(f: &mut fmt::Formatter, result: Option<bool>) -> fmt::Result {
+    match result {
+        Some(result) => write!(f, " => {result}"),
+        None => write!(f, " => skipped"),
+    }
+}
+
+fn write_expression(f: &mut fmt::Formatter, trace: &ExpressionTrace, depth: usize) -> fmt::Result {
+    for (index, compound_condition) in trace.compound_conditions.iter().enumerate() {
+        for (condition_index, condition) in compound_condition.conditions.iter().enumerate() {
+            if index > 0 || condition_index > 0 {
+                writeln!(f)?;
+            }
+
+            let keyword = match (index, condition_index) {
+                (0, 0) => "",
+                (_, 0) => "or ",
+                _ => "and ",
+            };
+            write!(f, "{:indent$}{keyword}", "", indent = depth * 4)?;
+            write_condition(f, condition, depth)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn write_condition(
+    f: &mut fmt::Formatter,
+    condition: &ConditionTrace,
+    depth: usize,
+) -> fmt::Result {
+    match condition {
+        ConditionTrace::Function {
+            function,
+            inverted,
+            evaluation,
+        } => {
+            if *inverted {
+                write!(f, "not ")?;
+            }
+            write!(f, "{function}")?;
+            write_result(f, condition.result())?;
+
+            if let Some(evaluation) = evaluation {
+                match evaluation.source {
+                    ResultSource::Evaluated => {}
+                    ResultSource::ConditionCache => write!(f, " (cached)")?,
+                    ResultSource::CrcCache => write!(f, " (cached CRC)")?,
+                }
+                if let Some(path) = &evaluation.resolved_path {
+                    write!(f, " [{}]", path.display())?;
+                }
+            }
+
+            Ok(())
+        }
+        ConditionTrace::Expression { inverted, trace } => {
+            if *inverted {
+                write!(f, "not ")?;
+            }
+            write!(f, "(")?;
+            write_result(f, condition.result())?;
+            writeln!(f)?;
+            write_expression(f, trace, depth + 1)?;
+            writeln!(f)?;
+            write!(f, "{:indent$})", "", indent = depth * 4)
+        }
+        ConditionTrace::Invalid(i) => {
+            write!(f, "{i}")?;
+            write_result(f, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::GameType;
+
+    fn state() -> State {
+        State::new(
+            GameType::Oblivion,
+            "tests/testing-plugins/Oblivion/Data".into(),
+        )
+        .with_active_plugins(&["Blank.esp"])
+    }
+
+    fn explained(string: &str, state: &State) -> ExpressionTrace {
+        Expression::from_str(string)
+            .unwrap()
+            .eval_explained(state)
+            .unwrap()
+    }
+
+    #[test]
+    fn eval_explained_should_give_the_same_result_as_eval() {
+        let state = state();
+        for string in [
+            "active(\"Blank.esp\")",
+            "not active(\"Blank.esp\")",
+            "active(\"a.esp\") or not (active(\"b.esp\") and active(\"Blank.esp\"))",
+            "",
+        ] {
+            let expression = Expression::from_str(string).unwrap();
+            let trace = expression.eval_explained(&state).unwrap();
+
+            assert_eq!(Some(expression.eval(&state).unwrap()), trace.result());
+        }
+    }
+
+    #[test]
+    fn eval_explained_should_mark_branches_skipped_by_short_circuiting() {
+        let trace = explained(
+            "active(\"a.esp\") and active(\"b.esp\") or active(\"Blank.esp\") or (active(\"c.esp\"))",
+            &state(),
+        );
+
+        assert_eq!(Some(1), trace.short_circuited_at());
+
+        let first = &trace.compound_conditions()[0];
+        assert_eq!(Some(false), first.result());
+        assert_eq!(Some(0), first.short_circuited_at());
+        assert_eq!(Some(false), first.conditions()[0].result());
+        assert_eq!(None, first.conditions()[1].result());
+
+        assert_eq!(Some(true), trace.compound_conditions()[1].result());
+
+        let skipped = &trace.compound_conditions()[2];
+        assert_eq!(None, skipped.result());
+        match &skipped.conditions()[0] {
+            ConditionTrace::Expression { trace, .. } => {
+                assert_eq!(None, trace.result());
+                assert_eq!(
+                    None,
+                    trace.compound_conditions()[0].conditions()[0].result()
+                );
+            }
+            t => panic!("Expected an expression trace, got {t:?}"),
+        }
+    }
+
+    #[test]
+    fn eval_explained_should_record_function_results_before_negation() {
+        let trace = explained("not active(\"Blank.esp\")", &state());
+
+        match &trace.compound_conditions()[0].conditions()[0] {
+            ConditionTrace::Function {
+                inverted,
+                evaluation: Some(evaluation),
+                ..
+            } => {
+                assert!(inverted);
+                assert!(evaluation.result());
+                assert_eq!(ResultSource::Evaluated, evaluation.source());
+                assert!(evaluation.resolved_path().is_none());
+            }
+            t => panic!("Expected an evaluated function trace, got {t:?}"),
+        }
+        assert_eq!(Some(false), trace.result());
+    }
+
+    #[test]
+    fn eval_explained_should_record_the_resolved_path() {
+        let data_path = tempfile::tempdir().unwrap();
+        std::fs::write(data_path.path().join("a.esp.ghost"), "").unwrap();
+        let state = State::new(GameType::Oblivion, data_path.path().into());
+
+        let trace = explained("file(\"a.esp\")", &state);
+
+        match &trace.compound_conditions()[0].conditions()[0] {
+            ConditionTrace::Function {
+                evaluation: Some(evaluation),
+                ..
+            } => assert_eq!(
+                Some(data_path.path().join("a.esp.ghost").as_path()),
+                evaluation.resolved_path()
+            ),
+            t => panic!("Expected an evaluated function trace, got {t:?}"),
+        }
+        assert_eq!(Some(true), trace.result());
+    }
+
+    #[test]
+    fn eval_explained_should_not_record_a_resolved_path_for_an_archived_file() {
+        use crate::archive::tests::bsa;
+        use crate::MemoryFileSystem;
+
+        let file_system = MemoryFileSystem::new()
+            .with_file("Data/Blank.bsa", bsa(105, &[("meshes\\a.nif", b"abc")]));
+        let state = State::new(GameType::SkyrimSE, "Data".into())
+            .with_active_plugins(&["Blank.esp"])
+            .with_file_system(file_system)
+            .with_archive_lookup(true);
+
+        let trace = explained(
+            "file(\"meshes/a.nif\") and file_size(\"meshes/a.nif\", 3)",
+            &state,
+        );
+
+        for condition in trace.compound_conditions()[0].conditions() {
+            match condition {
+                ConditionTrace::Function {
+                    evaluation: Some(evaluation),
+                    ..
+                } => {
+                    assert!(evaluation.result());
+                    assert_eq!(None, evaluation.resolved_path());
+                }
+                t => panic!("Expected an evaluated function trace, got {t:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn eval_explained_should_record_results_that_came_from_caches() {
+        let data_path = tempfile::tempdir().unwrap();
+        let mut state = State::new(GameType::Oblivion, data_path.path().into());
+        state.set_cached_crcs(&[("a.esp", 0xDEAD_BEEF)]).unwrap();
+
+        let expression =
+            Expression::from_str("file(\"a.esp\") or checksum(\"a.esp\", DEADBEEF)").unwrap();
+        expression.eval(&state).unwrap();
+        let trace = expression.eval_explained(&state).unwrap();
+
+        let sources: Vec<_> = trace
+            .compound_conditions()
+            .iter()
+            .map(|c| match &c.conditions()[0] {
+                ConditionTrace::Function {
+                    evaluation: Some(evaluation),
+                    ..
+                } => evaluation.source(),
+                t => panic!("Expected an evaluated function trace, got {t:?}"),
+            })
+            .collect();
+
+        assert_eq!(
+            vec![ResultSource::ConditionCache, ResultSource::CrcCache],
+            sources
+        );
+    }
+
+    #[test]
+    fn eval_explained_should_error_if_an_invalid_condition_is_evaluated() {
+        let recovered = Expression::parse_recovering("active(\"Blank.esp\") or fiel(\"a.esp\")");
+        assert!(recovered.expression().eval_explained(&state()).is_ok());
+
+        let recovered = Expression::parse_recovering("fiel(\"a.esp\") or active(\"Blank.esp\")");
+        assert!(recovered.expression().eval_explained(&state()).is_err());
+    }
+
+    #[test]
+    fn expression_trace_display_should_write_one_condition_per_line() {
+        let trace = explained(
+            "active(\"a.esp\") and active(\"b.esp\") or not (active(\"Blank.esp\"))",
+            &state(),
+        );
+
+        assert_eq!(
+            "active(\"a.esp\") => false
+and active(\"b.esp\") => skipped
+or not ( => false
+    active(\"Blank.esp\") => true
+)",
+            trace.to_string()
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! The filesystem that functions are evaluated against. By default this is the
+//! real disk, but [`State`](crate::State) can be given any implementation of
+//! [`FileSystem`], e.g. to evaluate conditions against an in-memory tree or a
+//! listing of a remote directory.
+mod memory;
+mod mod_organizer;
+
+use std::ffi::OsString;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path, PathBuf};
+
+pub use memory::MemoryFileSystem;
+pub use mod_organizer::ModOrganizerFileSystem;
+
+/// A reader that can seek, as needed to read plugin headers and executables.
+pub trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+/// An iterator over the names of a directory's entries.
+pub type DirEntryNames<'a> = Box<dyn Iterator<Item = io::Result<OsString>> + 'a>;
+
+/// The metadata of a file or directory.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Metadata {
+    is_dir: bool,
+    len: u64,
+}
+
+impl Metadata {
+    pub fn file(len: u64) -> Self {
+        Metadata { is_dir: false, len }
+    }
+
+    pub fn dir() -> Self {
+        Metadata {
+            is_dir: true,
+            len: 0,
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        !self.is_dir
+    }
+
+    /// The size of the file in bytes.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+}
+
+/// The filesystem operations that functions need to be evaluated. The paths
+/// given are the data path or an additional data path joined with the path
+/// from a function's arguments, so implementations can treat paths outside of
+/// those directories as not existing.
+pub trait FileSystem: fmt::Debug + Send + Sync {
+    /// Gets the metadata of the file or directory at the given path,
+    /// following symbolic links.
+    fn metadata(&self, path: &Path) -> io::Result<Metadata>;
+
+    /// Lists the names of the entries in the given directory, in no
+    /// particular order.
+    fn read_dir(&self, path: &Path) -> io::Result<DirEntryNames<'_>>;
+
+    /// Opens the file at the given path for reading.
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok_and(|m| m.is_file())
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path).is_ok_and(|m| m.is_dir())
+    }
+}
+
+/// The real filesystem, accessed using [`std::fs`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DiskFileSystem;
+
+impl FileSystem for DiskFileSystem {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        let metadata = std::fs::metadata(path)?;
+
+        if metadata.is_dir() {
+            Ok(Metadata::dir())
+        } else {
+            Ok(Metadata::file(metadata.len()))
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<DirEntryNames<'_>> {
+        let entries = std::fs::read_dir(path)?;
+
+        Ok(Box::new(entries.map(|e| e.map(|e| e.file_name()))))
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+        Ok(Box::new(File::open(path)?))
+    }
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("\"{}\" does not exist", path.display()),
+    )
+}
+
+/// Resolves `.` and `..` components without accessing any filesystem.
+fn normalise(path: &Path) -> PathBuf {
+    let mut normalised = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalised.pop() {
+                    normalised.push(component);
+                }
+            }
+            c => normalised.push(c),
+        }
+    }
+
+    normalised
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use tempfile::tempdir;
+
+    use crate::{Expression, GameType, State};
+
+    /// A filesystem in which every path is an empty directory, except for
+    /// paths ending in ".esp", which are files of the given size.
+    #[derive(Debug)]
+    struct PluginsOnly(u64);
+
+    impl FileSystem for PluginsOnly {
+        fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+            if path.extension().is_some_and(|e| e == "esp") {
+                Ok(Metadata::file(self.0))
+            } else {
+                Ok(Metadata::dir())
+            }
+        }
+
+        fn read_dir(&self, _: &Path) -> io::Result<DirEntryNames<'_>> {
+            Ok(Box::new(std::iter::empty()))
+        }
+
+        fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+            Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                path.display().to_string(),
+            ))
+        }
+    }
+
+    #[test]
+    fn disk_file_system_metadata_should_distinguish_files_and_directories() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("a.txt");
+        std::fs::write(&file_path, "abc").unwrap();
+
+        let fs = DiskFileSystem;
+
+        assert_eq!(Metadata::file(3), fs.metadata(&file_path).unwrap());
+        assert_eq!(Metadata::dir(), fs.metadata(tmp_dir.path()).unwrap());
+        assert!(fs.is_file(&file_path));
+        assert!(fs.is_dir(tmp_dir.path()));
+        assert!(!fs.exists(&tmp_dir.path().join("missing")));
+    }
+
+    #[test]
+    fn disk_file_system_read_dir_should_list_entry_names() {
+        let tmp_dir = tempdir().unwrap();
+        std::fs::write(tmp_dir.path().join("a.txt"), "").unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("b")).unwrap();
+
+        let mut names = DiskFileSystem
+            .read_dir(tmp_dir.path())
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        names.sort();
+
+        assert_eq!(vec![OsString::from("a.txt"), OsString::from("b")], names);
+        assert!(DiskFileSystem
+            .read_dir(&tmp_dir.path().join("missing"))
+            .is_err());
+    }
+
+    #[test]
+    fn disk_file_system_open_should_read_the_file() {
+        let tmp_dir = tempdir().unwrap();
+        let file_path = tmp_dir.path().join("a.txt");
+        std::fs::write(&file_path, "abc").unwrap();
+
+        let mut content = String::new();
+        DiskFileSystem
+            .open(&file_path)
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+
+        assert_eq!("abc", content);
+    }
+
+    #[test]
+    fn state_should_evaluate_functions_against_its_file_system() {
+        let state =
+            State::new(GameType::Oblivion, "missing".into()).with_file_system(PluginsOnly(5));
+
+        let expression = Expression::from_str(
+            "file(\"a.esp\") and file_size(\"b.esp\", 5) and not many(\"c\\.esp\")",
+        )
+        .unwrap();
+
+        assert!(expression.eval(&state).unwrap());
+        assert!(Expression::from_str("checksum(\"a.esp\", 0)")
+            .unwrap()
+            .eval(&state)
+            .is_err());
+    }
+}
@@ -0,0 +1,596 @@
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::{normalise, not_found, DirEntryNames, FileSystem, Metadata, ReadSeek};
+
+#[derive(Clone, Debug)]
+enum Contents {
+    Bytes(Arc<[u8]>),
+    /// A file of the given size that reads as zeroes, for when only the size
+    /// of a file matters.
+    Zeroes(u64),
+}
+
+#[derive(Clone, Debug)]
+enum Node {
+    /// Entry names keyed by their lowercased form.
+    Dir(BTreeMap<String, OsString>),
+    File(Contents),
+}
+
+/// A filesystem that exists only in memory, for evaluating conditions without
+/// the files that they check for being present on disk.
+///
+/// Paths are compared case-insensitively, as they are on Windows, and `.` and
+/// `..` components are resolved before paths are looked up. Adding a file or
+/// directory also adds its parent directories. Paths that don't name a file
+/// or directory once normalised, such as an empty path or a root, are
+/// ignored.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFileSystem {
+    /// Nodes keyed by their normalised and lowercased paths, ordered so that
+    /// a node's descendants immediately follow it.
+    nodes: BTreeMap<PathBuf, Node>,
+}
+
+impl MemoryFileSystem {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn with_dir<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.add_dir(path);
+        self
+    }
+
+    #[must_use]
+    pub fn with_file<P: AsRef<Path>, C: Into<Vec<u8>>>(mut self, path: P, contents: C) -> Self {
+        self.add_file(path, contents);
+        self
+    }
+
+    #[must_use]
+    pub fn with_file_of_size<P: AsRef<Path>>(mut self, path: P, size: u64) -> Self {
+        self.add_file_of_size(path, size);
+        self
+    }
+
+    #[must_use]
+    pub fn with_ghosted_file<P: AsRef<Path>, C: Into<Vec<u8>>>(
+        mut self,
+        path: P,
+        contents: C,
+    ) -> Self {
+        self.add_ghosted_file(path, contents);
+        self
+    }
+
+    #[must_use]
+    pub fn with_executable<P: AsRef<Path>>(
+        mut self,
+        path: P,
+        file_version: Option<[u16; 4]>,
+        product_version: Option<&str>,
+    ) -> Self {
+        self.add_executable(path, file_version, product_version);
+        self
+    }
+
+    /// Adds an empty directory, replacing any existing file at the same path.
+    /// Adding a directory that already exists does nothing.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, path: P) {
+        let path = normalise(path.as_ref());
+        if let Some(Node::Dir(_)) = self.nodes.get(&lowercase(&path)) {
+            return;
+        }
+
+        self.insert_node(&path, Node::Dir(BTreeMap::new()));
+    }
+
+    /// Adds a file with the given contents, replacing any existing file or
+    /// directory (along with its contents) at the same path.
+    pub fn add_file<P: AsRef<Path>, C: Into<Vec<u8>>>(&mut self, path: P, contents: C) {
+        self.insert_file(path.as_ref(), Contents::Bytes(contents.into().into()));
+    }
+
+    /// Adds a file of the given size that reads as zeroes, for functions like
+    /// `file_size()` that only look at a file's size.
+    pub fn add_file_of_size<P: AsRef<Path>>(&mut self, path: P, size: u64) {
+        self.insert_file(path.as_ref(), Contents::Zeroes(size));
+    }
+
+    /// Adds a plugin that has been ghosted, i.e. that has `.ghost` appended to
+    /// its filename. The given path is the plugin's path without the `.ghost`
+    /// extension.
+    pub fn add_ghosted_file<P: AsRef<Path>, C: Into<Vec<u8>>>(&mut self, path: P, contents: C) {
+        let mut path = path.as_ref().as_os_str().to_owned();
+        path.push(".ghost");
+
+        self.add_file(path, contents);
+    }
+
+    /// Adds a minimal Windows executable that contains a version information
+    /// resource with the given file and product versions. The file version's
+    /// components are in order of decreasing significance, e.g. `[1, 2, 3, 4]`
+    /// for 1.2.3.4.
+    pub fn add_executable<P: AsRef<Path>>(
+        &mut self,
+        path: P,
+        file_version: Option<[u16; 4]>,
+        product_version: Option<&str>,
+    ) {
+        self.add_file(path, executable(file_version, product_version));
+    }
+
+    fn insert_file(&mut self, path: &Path, contents: Contents) {
+        self.insert_node(&normalise(path), Node::File(contents));
+    }
+
+    /// Inserts a node at the given normalised path, replacing any existing
+    /// node there, and removing the existing node's descendants so that they
+    /// can't be reached through the new node.
+    fn insert_node(&mut self, path: &Path, node: Node) {
+        if path.file_name().is_none() {
+            return;
+        }
+
+        self.insert_parents(path);
+
+        let key = lowercase(path);
+        let descendants: Vec<PathBuf> = self
+            .nodes
+            .range::<PathBuf, _>((Bound::Excluded(&key), Bound::Unbounded))
+            .map(|(other_key, _)| other_key)
+            .take_while(|other_key| other_key.starts_with(&key))
+            .cloned()
+            .collect();
+        for descendant in descendants {
+            self.nodes.remove(&descendant);
+        }
+
+        self.nodes.insert(key, node);
+    }
+
+    /// Inserts any of the path's ancestors that are missing, and adds the
+    /// path's filename to its parent's entries.
+    fn insert_parents(&mut self, path: &Path) {
+        let mut child = path;
+        while let (Some(parent), Some(name)) = (child.parent(), child.file_name()) {
+            let node = self
+                .nodes
+                .entry(lowercase(parent))
+                .or_insert_with(|| Node::Dir(BTreeMap::new()));
+
+            // Files are replaced by directories if a path goes through them.
+            if let Node::File(_) = node {
+                *node = Node::Dir(BTreeMap::new());
+            }
+
+            if let Node::Dir(entries) = node {
+                entries.insert(name.to_string_lossy().to_lowercase(), name.to_owned());
+            }
+
+            child = parent;
+        }
+    }
+
+    fn node(&self, path: &Path) -> io::Result<&Node> {
+        self.nodes
+            .get(&lowercase(&normalise(path)))
+            .ok_or_else(|| not_found(path))
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        match self.node(path)? {
+            Node::Dir(_) => Ok(Metadata::dir()),
+            Node::File(Contents::Bytes(bytes)) => Ok(Metadata::file(
+                u64::try_from(bytes.len()).unwrap_or(u64::MAX),
+            )),
+            Node::File(Contents::Zeroes(size)) => Ok(Metadata::file(*size)),
+        }
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<DirEntryNames<'_>> {
+        match self.node(path)? {
+            Node::Dir(entries) => Ok(Box::new(entries.values().cloned().map(Ok))),
+            Node::File(_) => Err(io::Error::new(
+                io::ErrorKind::NotADirectory,
+                format!("\"{}\" is not a directory", path.display()),
+            )),
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+        match self.node(path)? {
+            Node::Dir(_) => Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                format!("\"{}\" is a directory", path.display()),
+            )),
+            Node::File(Contents::Bytes(bytes)) => Ok(Box::new(Cursor::new(Arc::clone(bytes)))),
+            Node::File(Contents::Zeroes(size)) => Ok(Box::new(Zeroes {
+                size: *size,
+                position: 0,
+            })),
+        }
+    }
+}
+
+fn lowercase(path: &Path) -> PathBuf {
+    PathBuf::from(path.as_os_str().to_string_lossy().to_lowercase())
+}
+
+/// A reader over a file of zeroes.
+#[derive(Clone, Copy, Debug)]
+struct Zeroes {
+    size: u64,
+    position: u64,
+}
+
+impl Read for Zeroes {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.size.saturating_sub(self.position);
+        let length = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+
+        if let Some(buf) = buf.get_mut(..length) {
+            buf.fill(0);
+        }
+        self.position += u64::try_from(length).unwrap_or(remaining);
+
+        Ok(length)
+    }
+}
+
+impl Seek for Zeroes {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.size.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+
+        self.position = position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+
+        Ok(self.position)
+    }
+}
+
+fn utf16_bytes(string: &str) -> Vec<u8> {
+    string
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .flat_map(u16::to_le_bytes)
+        .collect()
+}
+
+fn pad_to_dword(bytes: &mut Vec<u8>) {
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+}
+
+/// Builds a version resource struct, with the given value and children, and
+/// a header that holds its length.
+// <https://learn.microsoft.com/en-us/windows/win32/menurc/vs-versioninfo>
+fn version_struct(
+    key: &str,
+    value_length: u16,
+    is_text: bool,
+    value: &[u8],
+    children: &[u8],
+) -> Vec<u8> {
+    let mut bytes = vec![0, 0];
+    bytes.extend(value_length.to_le_bytes());
+    bytes.extend(u16::from(is_text).to_le_bytes());
+    bytes.extend(utf16_bytes(key));
+    pad_to_dword(&mut bytes);
+    bytes.extend(value);
+    if !children.is_empty() {
+        pad_to_dword(&mut bytes);
+        bytes.extend(children);
+    }
+
+    let length = u16::try_from(bytes.len()).unwrap_or(u16::MAX).to_le_bytes();
+    bytes.splice(0..2, length);
+
+    bytes
+}
+
+fn version_info(file_version: Option<[u16; 4]>, product_version: Option<&str>) -> Vec<u8> {
+    let fixed_file_info = file_version.map_or_else(Vec::new, |[major, minor, patch, build]| {
+        let mut bytes = Vec::with_capacity(0x34);
+        bytes.extend(0xFEEF_04BD_u32.to_le_bytes());
+        bytes.extend(0x0001_0000_u32.to_le_bytes());
+        for part in [minor, major, build, patch] {
+            bytes.extend(part.to_le_bytes());
+        }
+        // The product version fields are the same as the file version fields.
+        for part in [minor, major, build, patch] {
+            bytes.extend(part.to_le_bytes());
+        }
+        // Leave the flags, OS, type and date fields zeroed.
+        bytes.resize(0x34, 0);
+        bytes
+    });
+
+    let string_file_info = product_version.map_or_else(Vec::new, |product_version| {
+        // The value length is in UTF-16 code units, including the terminator.
+        let value_length = product_version.encode_utf16().count() + 1;
+        let value_length = u16::try_from(value_length).unwrap_or(u16::MAX);
+        let value = utf16_bytes(product_version);
+        let string = version_struct("ProductVersion", value_length, true, &value, &[]);
+        let string_table = version_struct("040904B0", 0, true, &[], &string);
+
+        version_struct("StringFileInfo", 0, true, &[], &string_table)
+    });
+
+    version_struct(
+        "VS_VERSION_INFO",
+        u16::try_from(fixed_file_info.len()).unwrap_or(u16::MAX),
+        false,
+        &fixed_file_info,
+        &string_file_info,
+    )
+}
+
+/// Builds a 32-bit PE image with a single `.rsrc` section that holds a version
+/// information resource, and no code.
+// <https://coffi.readthedocs.io/en/latest/pecoff_v11.pdf>
+fn executable(file_version: Option<[u16; 4]>, product_version: Option<&str>) -> Vec<u8> {
+    const PE_HEADER_OFFSET: u32 = 0x40;
+    const OPTIONAL_HEADER_SIZE: u16 = 0xE0;
+    const SECTION_OFFSET: u32 = 0x200;
+    const SECTION_RVA: u32 = 0x1000;
+    const RT_VERSION: u32 = 16;
+    const LANGUAGE_ID: u32 = 0x409;
+    const SUBDIRECTORY_FLAG: u32 = 1 << 31;
+    const VERSION_DATA_OFFSET: u32 = 0x58;
+
+    let version_info = version_info(file_version, product_version);
+    let version_info_size = u32::try_from(version_info.len()).unwrap_or(u32::MAX);
+    let section_size = VERSION_DATA_OFFSET + version_info_size;
+
+    let mut bytes = b"MZ".to_vec();
+    bytes.resize(0x3C, 0);
+    bytes.extend(PE_HEADER_OFFSET.to_le_bytes());
+
+    // PE signature and COFF file header, for an x86 image with one section.
+    bytes.extend(b"PE\0\0");
+    bytes.extend(0x014C_u16.to_le_bytes());
+    bytes.extend(1_u16.to_le_bytes());
+    bytes.extend([0; 12]);
+    bytes.extend(OPTIONAL_HEADER_SIZE.to_le_bytes());
+    bytes.extend(0x0102_u16.to_le_bytes());
+
+    // PE32 optional header, with only the resource table's data directory set.
+    let optional_header_start = bytes.len();
+    bytes.extend(0x010B_u16.to_le_bytes());
+    bytes.resize(optional_header_start + 92, 0);
+    bytes.extend(16_u32.to_le_bytes());
+    bytes.extend([0; 16]);
+    bytes.extend(SECTION_RVA.to_le_bytes());
+    bytes.extend(section_size.to_le_bytes());
+    bytes.resize(optional_header_start + usize::from(OPTIONAL_HEADER_SIZE), 0);
+
+    // Section table.
+    bytes.extend(b".rsrc\0\0\0");
+    bytes.extend(section_size.to_le_bytes());
+    bytes.extend(SECTION_RVA.to_le_bytes());
+    bytes.extend(section_size.to_le_bytes());
+    bytes.extend(SECTION_OFFSET.to_le_bytes());
+    bytes.extend([0; 16]);
+
+    bytes.resize(usize::try_from(SECTION_OFFSET).unwrap_or_default(), 0);
+
+    // The resource directory tree has one entry at each of its three levels:
+    // type, name and language. Each table is 0x18 bytes long.
+    for (id, offset) in [
+        (RT_VERSION, SUBDIRECTORY_FLAG | 0x18),
+        (1, SUBDIRECTORY_FLAG | 0x30),
+        (LANGUAGE_ID, 0x48),
+    ] {
+        bytes.extend([0; 12]);
+        bytes.extend(0_u16.to_le_bytes());
+        bytes.extend(1_u16.to_le_bytes());
+        bytes.extend(id.to_le_bytes());
+        bytes.extend(offset.to_le_bytes());
+    }
+
+    // The resource data entry.
+    bytes.extend((SECTION_RVA + VERSION_DATA_OFFSET).to_le_bytes());
+    bytes.extend(version_info_size.to_le_bytes());
+    bytes.extend([0; 8]);
+
+    bytes.extend(version_info);
+
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::{Expression, GameType, State};
+
+    fn names(fs: &MemoryFileSystem, path: &str) -> Vec<OsString> {
+        fs.read_dir(Path::new(path))
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap()
+    }
+
+    fn eval(fs: &MemoryFileSystem, condition: &str) -> bool {
+        let state =
+            State::new(GameType::SkyrimSE, "Skyrim/Data".into()).with_file_system(fs.clone());
+
+        Expression::from_str(condition)
+            .unwrap()
+            .eval(&state)
+            .unwrap()
+    }
+
+    #[test]
+    fn add_file_should_add_the_file_and_its_parent_directories() {
+        let fs = MemoryFileSystem::new().with_file("a/b/c.txt", "abc");
+
+        assert_eq!(
+            Metadata::file(3),
+            fs.metadata(Path::new("a/b/c.txt")).unwrap()
+        );
+        assert!(fs.is_dir(Path::new("a/b")));
+        assert!(fs.is_dir(Path::new("a")));
+        assert_eq!(vec![OsString::from("b")], names(&fs, "a"));
+        assert_eq!(vec![OsString::from("c.txt")], names(&fs, "a/b"));
+    }
+
+    #[test]
+    fn add_file_should_replace_a_directory_and_its_contents() {
+        let fs = MemoryFileSystem::new()
+            .with_file("a/b/c.txt", "abc")
+            .with_dir("a/d")
+            .with_file("a", "a");
+
+        assert_eq!(Metadata::file(1), fs.metadata(Path::new("a")).unwrap());
+        assert!(!fs.exists(Path::new("a/b")));
+        assert!(!fs.exists(Path::new("a/b/c.txt")));
+        assert!(!fs.exists(Path::new("a/d")));
+
+        let fs = fs.with_file("a/e.txt", "e");
+
+        assert_eq!(vec![OsString::from("e.txt")], names(&fs, "a"));
+    }
+
+    #[test]
+    fn add_dir_should_replace_a_file() {
+        let fs = MemoryFileSystem::new().with_file("a", "a").with_dir("a");
+
+        assert!(fs.is_dir(Path::new("a")));
+        assert!(names(&fs, "a").is_empty());
+    }
+
+    #[test]
+    fn add_file_should_only_replace_the_contents_of_the_replaced_directory() {
+        let fs = MemoryFileSystem::new()
+            .with_file("a/b/c.txt", "c")
+            .with_file("a/b0/d.txt", "d")
+            .with_file("a/b.txt", "b")
+            .with_file("a/b", "b");
+
+        assert!(fs.is_file(Path::new("a/b")));
+        assert!(!fs.exists(Path::new("a/b/c.txt")));
+        assert!(fs.is_file(Path::new("a/b0/d.txt")));
+        assert!(fs.is_file(Path::new("a/b.txt")));
+    }
+
+    #[test]
+    fn paths_that_do_not_name_a_file_or_directory_should_be_ignored() {
+        let fs = MemoryFileSystem::new()
+            .with_file("a/b.txt", "b")
+            .with_file("", "")
+            .with_file("a/..", "")
+            .with_dir(".")
+            .with_file("/", "");
+
+        assert!(fs.is_file(Path::new("a/b.txt")));
+        assert_eq!(vec![OsString::from("b.txt")], names(&fs, "a"));
+    }
+
+    #[test]
+    fn add_dir_should_keep_the_contents_of_an_existing_directory() {
+        let fs = MemoryFileSystem::new()
+            .with_file("a/b.txt", "b")
+            .with_dir("A");
+
+        assert!(fs.is_file(Path::new("a/b.txt")));
+        assert_eq!(vec![OsString::from("b.txt")], names(&fs, "a"));
+    }
+
+    #[test]
+    fn paths_should_be_case_insensitive_and_normalised() {
+        let fs = MemoryFileSystem::new().with_file("Data/Blank.esp", "");
+
+        assert!(fs.is_file(Path::new("data/BLANK.ESP")));
+        assert!(fs.is_file(Path::new("./Data/Meshes/../Blank.esp")));
+        assert!(!fs.exists(Path::new("Blank.esp")));
+        assert_eq!(vec![OsString::from("Blank.esp")], names(&fs, "DATA"));
+    }
+
+    #[test]
+    fn open_should_read_the_file_contents() {
+        let fs = MemoryFileSystem::new()
+            .with_file("a.txt", "abc")
+            .with_file_of_size("b.bin", 3)
+            .with_dir("c");
+
+        let mut contents = Vec::new();
+        fs.open(Path::new("a.txt"))
+            .unwrap()
+            .read_to_end(&mut contents)
+            .unwrap();
+        assert_eq!(b"abc".as_slice(), contents);
+
+        contents.clear();
+        let mut reader = fs.open(Path::new("b.bin")).unwrap();
+        reader.seek(SeekFrom::Start(1)).unwrap();
+        reader.read_to_end(&mut contents).unwrap();
+        assert_eq!(vec![0, 0], contents);
+
+        assert!(fs.open(Path::new("c")).is_err());
+        assert!(fs.open(Path::new("d")).is_err());
+    }
+
+    #[test]
+    fn state_should_evaluate_file_functions_against_a_memory_file_system() {
+        let fs = MemoryFileSystem::new()
+            .with_file("Skyrim/Data/Blank.esm", "")
+            .with_ghosted_file("Skyrim/Data/Blank.esp", "")
+            .with_file("Skyrim/Data/meshes/a.nif", "abc")
+            .with_file_of_size("Skyrim/SkyrimSE.exe", 12_345);
+
+        assert!(eval(&fs, "file(\"Blank.esp\") and file(\"Blank.esm\")"));
+        assert!(eval(&fs, "many(\"Blank\\.es(m|p)\")"));
+        assert!(eval(&fs, "file(\"meshes/A.nif\") and readable(\"meshes\")"));
+        assert!(eval(&fs, "checksum(\"meshes/a.nif\", 352441C2)"));
+        assert!(eval(&fs, "file_size(\"../SkyrimSE.exe\", 12345)"));
+        assert!(!eval(&fs, "file(\"Blank.esl\") or file(\"textures\")"));
+    }
+
+    #[test]
+    fn state_should_read_versions_of_fake_executables() {
+        let fs = MemoryFileSystem::new()
+            .with_executable(
+                "Skyrim/Data/SKSE/Plugins/a.dll",
+                Some([1, 2, 3, 4]),
+                Some("1.2.3-beta"),
+            )
+            .with_executable("Skyrim/Data/b.dll", None, Some("5.0"))
+            .with_executable("Skyrim/Data/c.dll", Some([6, 0, 0, 0]), None);
+
+        assert!(eval(
+            &fs,
+            "version(\"SKSE/Plugins/a.dll\", \"1.2.3.4\", ==)"
+        ));
+        assert!(eval(
+            &fs,
+            "product_version(\"SKSE/Plugins/a.dll\", \"1.2.3-beta\", ==)"
+        ));
+        assert!(eval(&fs, "product_version(\"b.dll\", \"5\", ==)"));
+        assert!(eval(&fs, "is_executable(\"b.dll\")"));
+        assert!(!eval(&fs, "version(\"b.dll\", \"0\", >=)"));
+        assert!(eval(&fs, "version(\"c.dll\", \"6\", ==)"));
+        assert!(!eval(&fs, "product_version(\"c.dll\", \"0\", >=)"));
+    }
+}
@@ -0,0 +1,370 @@
+use std::collections::HashSet;
+use std::ffi::OsStr;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use super::{normalise, not_found, DirEntryNames, DiskFileSystem, FileSystem, Metadata, ReadSeek};
+
+const HIDDEN_EXTENSION: &str = ".mohidden";
+
+/// A filesystem that overlays Mod Organizer 2's mod folders onto the game's
+/// data directory, like MO2's virtual filesystem does when it runs the game.
+///
+/// A path inside the data directory is looked up in the overwrite folder, then
+/// in each enabled mod's folder from highest to lowest priority, then in the
+/// data directory itself, and the first match wins. Directories' contents are
+/// merged across all of those folders. Files and directories that MO2 has
+/// hidden by appending `.mohidden` to their names are ignored. Paths outside
+/// the data directory are passed through unchanged.
+#[derive(Debug)]
+pub struct ModOrganizerFileSystem {
+    data_path: PathBuf,
+    mods_path: PathBuf,
+    overwrite_path: PathBuf,
+    /// Enabled mods' folder names, highest priority first.
+    mods: Vec<String>,
+    /// The filesystem that the folders are read from.
+    file_system: Box<dyn FileSystem>,
+}
+
+impl ModOrganizerFileSystem {
+    pub fn new(data_path: PathBuf, mods_path: PathBuf, overwrite_path: PathBuf) -> Self {
+        ModOrganizerFileSystem {
+            data_path,
+            mods_path,
+            overwrite_path,
+            mods: Vec::new(),
+            file_system: Box::new(DiskFileSystem),
+        }
+    }
+
+    /// Creates a filesystem for the given profile of the MO2 instance at the
+    /// given path, using the instance's `mods` and `overwrite` folders and the
+    /// mods enabled in the profile's `modlist.txt`.
+    pub fn from_instance(
+        data_path: PathBuf,
+        instance_path: &Path,
+        profile: &str,
+    ) -> io::Result<Self> {
+        let mut file_system = ModOrganizerFileSystem::new(
+            data_path,
+            instance_path.join("mods"),
+            instance_path.join("overwrite"),
+        );
+
+        file_system.load_modlist(
+            &instance_path
+                .join("profiles")
+                .join(profile)
+                .join("modlist.txt"),
+        )?;
+
+        Ok(file_system)
+    }
+
+    /// Sets the filesystem that the data, mod and overwrite folders are read
+    /// from, which is the real disk by default.
+    #[must_use]
+    pub fn with_file_system<T: FileSystem + 'static>(mut self, file_system: T) -> Self {
+        self.file_system = Box::new(file_system);
+        self
+    }
+
+    /// Sets the enabled mods, highest priority first.
+    #[must_use]
+    pub fn with_mods<T: AsRef<str>>(mut self, mods: &[T]) -> Self {
+        self.set_mods(mods);
+        self
+    }
+
+    /// Sets the enabled mods, highest priority first.
+    pub fn set_mods<T: AsRef<str>>(&mut self, mods: &[T]) {
+        self.mods = mods.iter().map(|m| m.as_ref().to_owned()).collect();
+    }
+
+    /// Sets the enabled mods from a profile's `modlist.txt`, which is read
+    /// using this filesystem's underlying filesystem.
+    pub fn load_modlist(&mut self, modlist_path: &Path) -> io::Result<()> {
+        let mut content = String::new();
+        self.file_system
+            .open(modlist_path)?
+            .read_to_string(&mut content)?;
+
+        self.mods = parse_modlist(&content);
+
+        Ok(())
+    }
+
+    pub fn mods(&self) -> &[String] {
+        &self.mods
+    }
+
+    /// The paths that a path relative to the data directory could be found
+    /// at, highest priority first.
+    fn layers<'a>(&'a self, relative_path: &'a Path) -> impl Iterator<Item = PathBuf> + 'a {
+        std::iter::once(self.overwrite_path.join(relative_path))
+            .chain(
+                self.mods
+                    .iter()
+                    .map(move |m| self.mods_path.join(m).join(relative_path)),
+            )
+            .chain(std::iter::once(self.data_path.join(relative_path)))
+    }
+
+    /// Gets the given path relative to the data directory, or `None` if it is
+    /// outside the data directory.
+    fn relative_path(&self, path: &Path) -> Option<PathBuf> {
+        normalise(path)
+            .strip_prefix(normalise(&self.data_path))
+            .ok()
+            .map(Path::to_path_buf)
+    }
+
+    /// Finds the highest-priority path that the given path resolves to, along
+    /// with its metadata.
+    fn resolve(&self, path: &Path) -> io::Result<(PathBuf, Metadata)> {
+        let Some(relative_path) = self.relative_path(path) else {
+            return Ok((path.to_path_buf(), self.file_system.metadata(path)?));
+        };
+
+        if relative_path.iter().any(is_hidden) {
+            return Err(not_found(path));
+        }
+
+        let resolved = self
+            .layers(&relative_path)
+            .find_map(|p| self.file_system.metadata(&p).ok().map(|m| (p, m)));
+
+        resolved.ok_or_else(|| not_found(path))
+    }
+}
+
+impl FileSystem for ModOrganizerFileSystem {
+    fn metadata(&self, path: &Path) -> io::Result<Metadata> {
+        self.resolve(path).map(|(_, metadata)| metadata)
+    }
+
+    fn read_dir(&self, path: &Path) -> io::Result<DirEntryNames<'_>> {
+        let Some(relative_path) = self.relative_path(path) else {
+            return self.file_system.read_dir(path);
+        };
+
+        if relative_path.iter().any(is_hidden) {
+            return Err(not_found(path));
+        }
+
+        let mut found_dir = false;
+        let mut seen = HashSet::new();
+        let mut names = Vec::new();
+        for layer in self.layers(&relative_path) {
+            if !self.file_system.is_dir(&layer) {
+                continue;
+            }
+            found_dir = true;
+
+            for name in self.file_system.read_dir(&layer)? {
+                let name = name?;
+                if !is_hidden(&name) && seen.insert(name.to_string_lossy().to_lowercase()) {
+                    names.push(name);
+                }
+            }
+        }
+
+        if found_dir {
+            Ok(Box::new(names.into_iter().map(Ok)))
+        } else {
+            Err(not_found(path))
+        }
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn ReadSeek + '_>> {
+        let (path, _) = self.resolve(path)?;
+
+        self.file_system.open(&path)
+    }
+}
+
+fn is_hidden(name: &OsStr) -> bool {
+    name.to_string_lossy()
+        .to_lowercase()
+        .ends_with(HIDDEN_EXTENSION)
+}
+
+/// Gets the enabled mods from the content of a `modlist.txt`, which lists mods
+/// highest priority first, prefixing enabled mods with `+`, disabled mods with
+/// `-` and mods not managed by MO2 with `*`.
+fn parse_modlist(content: &str) -> Vec<String> {
+    content
+        .trim_start_matches('\u{feff}')
+        .lines()
+        .filter_map(|line| line.trim_end().strip_prefix('+'))
+        .map(str::to_owned)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ffi::OsString;
+    use std::str::FromStr;
+
+    use crate::{Expression, GameType, MemoryFileSystem, State};
+
+    fn file_system() -> ModOrganizerFileSystem {
+        let memory = MemoryFileSystem::new()
+            .with_file("Game/Data/Base.esm", "base")
+            .with_file("Game/Data/meshes/base.nif", "")
+            .with_file("MO2/mods/Low/Low.esp", "low")
+            .with_file("MO2/mods/Low/Base.esm", "low base")
+            .with_file("MO2/mods/Low/meshes/low.nif", "")
+            .with_file("MO2/mods/High/Low.esp", "high")
+            .with_file("MO2/mods/High/Hidden.esp.mohidden", "")
+            .with_file("MO2/mods/High/meshes/high.nif", "")
+            .with_file("MO2/mods/Disabled/Disabled.esp", "")
+            .with_file("MO2/overwrite/Low.esp", "overwrite")
+            .with_executable("MO2/mods/High/SKSE/Plugins/a.dll", Some([2, 0, 0, 0]), None)
+            .with_executable("MO2/mods/Low/SKSE/Plugins/a.dll", Some([1, 0, 0, 0]), None)
+            .with_file(
+                "MO2/profiles/Default/modlist.txt",
+                "# This file was automatically generated by Mod Organizer.\n+High\n-Disabled\n*DLC: Dawnguard\n+Low\n",
+            );
+
+        let mut fs = ModOrganizerFileSystem::new(
+            "Game/Data".into(),
+            "MO2/mods".into(),
+            "MO2/overwrite".into(),
+        )
+        .with_file_system(memory);
+
+        fs.load_modlist(Path::new("MO2/profiles/Default/modlist.txt"))
+            .unwrap();
+
+        fs
+    }
+
+    fn read(fs: &ModOrganizerFileSystem, path: &str) -> String {
+        let mut content = String::new();
+        fs.open(Path::new(path))
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        content
+    }
+
+    fn names(fs: &ModOrganizerFileSystem, path: &str) -> Vec<OsString> {
+        let mut names = fs
+            .read_dir(Path::new(path))
+            .unwrap()
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        names.sort();
+        names
+    }
+
+    fn eval(condition: &str) -> bool {
+        let state =
+            State::new(GameType::SkyrimSE, "Game/Data".into()).with_file_system(file_system());
+
+        Expression::from_str(condition)
+            .unwrap()
+            .eval(&state)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_modlist_should_only_include_enabled_mods_in_order() {
+        let content = "\u{feff}# comment\r\n+High\r\n-Disabled\r\n*Unmanaged\r\n+Low\r\n";
+
+        assert_eq!(vec!["High", "Low"], parse_modlist(content));
+    }
+
+    #[test]
+    fn load_modlist_should_read_the_modlist_using_the_underlying_file_system() {
+        let mut fs = ModOrganizerFileSystem::new(
+            "Game/Data".into(),
+            "MO2/mods".into(),
+            "MO2/overwrite".into(),
+        )
+        .with_file_system(MemoryFileSystem::new().with_file("modlist.txt", "+B\n-C\n+A\n"));
+
+        fs.load_modlist(Path::new("modlist.txt")).unwrap();
+
+        assert_eq!(&["B".to_owned(), "A".to_owned()], fs.mods());
+        assert!(fs.load_modlist(Path::new("missing.txt")).is_err());
+    }
+
+    #[test]
+    fn from_instance_should_use_the_instance_folders_and_profile_modlist() {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let profile_path = tmp_dir.path().join("profiles/Default");
+        std::fs::create_dir_all(&profile_path).unwrap();
+        std::fs::create_dir_all(tmp_dir.path().join("mods/A")).unwrap();
+        std::fs::write(profile_path.join("modlist.txt"), "+A\n-B\n").unwrap();
+        std::fs::write(tmp_dir.path().join("mods/A/A.esp"), "").unwrap();
+
+        let fs = ModOrganizerFileSystem::from_instance("Data".into(), tmp_dir.path(), "Default")
+            .unwrap();
+
+        assert_eq!(&["A".to_owned()], fs.mods());
+        assert!(fs.is_file(Path::new("Data/A.esp")));
+        assert!(
+            ModOrganizerFileSystem::from_instance("Data".into(), tmp_dir.path(), "Missing")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn open_should_read_the_highest_priority_file() {
+        let fs = file_system();
+
+        assert_eq!("overwrite", read(&fs, "Game/Data/Low.esp"));
+        assert_eq!("low base", read(&fs, "Game/Data/Base.esm"));
+        assert!(fs.open(Path::new("Game/Data/Disabled.esp")).is_err());
+        assert!(fs.open(Path::new("Game/Data/Hidden.esp")).is_err());
+        assert!(fs.open(Path::new("Game/Data/Hidden.esp.mohidden")).is_err());
+    }
+
+    #[test]
+    fn read_dir_should_merge_directories_and_skip_hidden_entries() {
+        let fs = file_system();
+
+        assert_eq!(
+            vec![
+                OsString::from("Base.esm"),
+                OsString::from("Low.esp"),
+                OsString::from("SKSE"),
+                OsString::from("meshes"),
+            ],
+            names(&fs, "Game/Data")
+        );
+        assert_eq!(
+            vec![
+                OsString::from("base.nif"),
+                OsString::from("high.nif"),
+                OsString::from("low.nif"),
+            ],
+            names(&fs, "Game/Data/meshes")
+        );
+        assert!(fs.read_dir(Path::new("Game/Data/textures")).is_err());
+    }
+
+    #[test]
+    fn paths_outside_the_data_path_should_be_passed_through() {
+        let fs = file_system();
+
+        assert!(fs.is_file(Path::new("MO2/mods/Disabled/Disabled.esp")));
+        assert!(fs.is_file(Path::new("Game/Data/../Data/Low.esp")));
+        assert_eq!("high", read(&fs, "MO2/mods/High/Low.esp"));
+    }
+
+    #[test]
+    fn state_should_see_the_overlaid_files() {
+        assert!(eval("file(\"Low.esp\") and file(\"meshes/high.nif\")"));
+        assert!(!eval("file(\"Disabled.esp\") or file(\"Hidden.esp\")"));
+        assert!(eval("many(\"meshes/.+\\.nif\")"));
+        assert!(!eval("many(\".+\\.esp\")"));
+        assert!(eval("checksum(\"Low.esp\", 2BC18006)"));
+        assert!(eval("version(\"SKSE/Plugins/a.dll\", \"2\", ==)"));
+    }
+}
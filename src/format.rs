@@ -0,0 +1,305 @@
+use std::path::PathBuf;
+
+use crate::visit::Fold;
+use crate::{CompoundCondition, Condition, Expression, Function};
+
+/// Options that control how [`Expression::format`] lays out its output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FormatOptions {
+    max_line_width: Option<usize>,
+    indent_width: usize,
+    normalise_separators: bool,
+}
+
+impl FormatOptions {
+    /// The default options write the expression on a single line.
+    pub fn new() -> Self {
+        Self {
+            max_line_width: None,
+            indent_width: 4,
+            normalise_separators: false,
+        }
+    }
+
+    /// Lines that would be longer than the given number of characters are
+    /// wrapped before each 'or' and 'and' and inside parentheses, as far as
+    /// is needed to make them fit. Functions are never split across lines.
+    #[must_use]
+    pub fn with_max_line_width(mut self, max_line_width: usize) -> Self {
+        self.max_line_width = Some(max_line_width);
+        self
+    }
+
+    /// The number of spaces used to indent each level of nested parentheses
+    /// when wrapping lines.
+    #[must_use]
+    pub fn with_indent_width(mut self, indent_width: usize) -> Self {
+        self.indent_width = indent_width;
+        self
+    }
+
+    /// Whether backslashes in path arguments are replaced with forward
+    /// slashes. Backslashes are only directory separators on Windows, so this
+    /// changes which file a condition refers to on other platforms. Off by
+    /// default.
+    #[must_use]
+    pub fn with_normalised_separators(mut self, normalise_separators: bool) -> Self {
+        self.normalise_separators = normalise_separators;
+        self
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub(crate) fn format(expression: &Expression, options: &FormatOptions) -> String {
+    let mut formatter = Formatter {
+        options,
+        output: String::new(),
+    };
+
+    if options.normalise_separators {
+        let expression = SeparatorNormaliser.fold_expression(expression.clone());
+        formatter.write_expression(&expression, 0);
+    } else {
+        formatter.write_expression(expression, 0);
+    }
+
+    formatter.output
+}
+
+/// Replaces backslashes in path arguments with forward slashes, which are
+/// valid directory separators on all platforms. Regexes are left alone, as
+/// their backslashes are escapes.
+struct SeparatorNormaliser;
+
+impl Fold for SeparatorNormaliser {
+    fn fold_function(&mut self, mut function: Function) -> Function {
+        for path in function.paths_mut() {
+            if let Some(string) = path.to_str().filter(|s| s.contains('\\')) {
+                *path = PathBuf::from(string.replace('\\', "/"));
+            }
+        }
+
+        function
+    }
+}
+
+struct Formatter<'a> {
+    options: &'a FormatOptions,
+    output: String,
+}
+
+impl Formatter<'_> {
+    fn fits(&self, depth: usize, prefix: &str, text: &str) -> bool {
+        self.options.max_line_width.is_none_or(|max_line_width| {
+            depth * self.options.indent_width + prefix.chars().count() + text.chars().count()
+                <= max_line_width
+        })
+    }
+
+    fn new_line(&mut self, depth: usize) {
+        self.output.push('\n');
+        self.output
+            .extend(std::iter::repeat_n(' ', depth * self.options.indent_width));
+    }
+
+    fn write_expression(&mut self, expression: &Expression, depth: usize) {
+        let text = expression.to_string();
+        if self.fits(depth, "", &text) {
+            self.output.push_str(&text);
+            return;
+        }
+
+        for (index, compound_condition) in expression.compound_conditions().iter().enumerate() {
+            let prefix = if index == 0 {
+                ""
+            } else {
+                self.new_line(depth);
+                "or "
+            };
+            self.output.push_str(prefix);
+            self.write_compound_condition(compound_condition, depth, prefix);
+        }
+    }
+
+    fn write_compound_condition(
+        &mut self,
+        compound_condition: &CompoundCondition,
+        depth: usize,
+        prefix: &str,
+    ) {
+        let text = compound_condition.to_string();
+        if self.fits(depth, prefix, &text) {
+            self.output.push_str(&text);
+            return;
+        }
+
+        for (index, condition) in compound_condition.conditions().iter().enumerate() {
+            let prefix = if index == 0 {
+                prefix
+            } else {
+                self.new_line(depth);
+                self.output.push_str("and ");
+                "and "
+            };
+            self.write_condition(condition, depth, prefix);
+        }
+    }
+
+    fn write_condition(&mut self, condition: &Condition, depth: usize, prefix: &str) {
+        let (opening, expression) = match condition {
+            Condition::Expression(e) => ("(", e),
+            Condition::InvertedExpression(e) => ("not (", e),
+            Condition::Function(_) | Condition::InvertedFunction(_) | Condition::Invalid(_) => {
+                self.output.push_str(&condition.to_string());
+                return;
+            }
+        };
+
+        let text = condition.to_string();
+        if expression.compound_conditions().is_empty() || self.fits(depth, prefix, &text) {
+            self.output.push_str(&text);
+            return;
+        }
+
+        self.output.push_str(opening);
+        self.new_line(depth + 1);
+        self.write_expression(expression, depth + 1);
+        self.new_line(depth);
+        self.output.push(')');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    const EXPRESSION: &str = "file(\"a.esp\") and (active(\"b.esp\") or not (many(\"c.*\") and is_master(\"d.esm\"))) or checksum(\"e.esp\", DEADBEEF)";
+
+    fn formatted(string: &str, options: &FormatOptions) -> String {
+        format(&Expression::from_str(string).unwrap(), options)
+    }
+
+    #[test]
+    fn format_should_write_an_expression_on_one_line_by_default() {
+        assert_eq!(EXPRESSION, formatted(EXPRESSION, &FormatOptions::new()));
+    }
+
+    #[test]
+    fn format_should_normalise_whitespace_and_comparator_placement() {
+        assert_eq!(
+            "version(\"a.esp\", ==, \"1.0\") and (file(\"b.esp\") or not active(\"c.esp\"))",
+            formatted(
+                "version(\"a.esp\",\"1.0\",==)   and(\n file(\"b.esp\")or not active(\"c.esp\") )",
+                &FormatOptions::new()
+            )
+        );
+    }
+
+    #[test]
+    fn format_should_not_replace_backslashes_in_paths_by_default() {
+        let string = "file_size(\"..\\Oblivion.exe\", 1234)";
+
+        assert_eq!(string, formatted(string, &FormatOptions::new()));
+    }
+
+    #[test]
+    fn format_should_replace_backslashes_in_paths_if_separators_are_normalised() {
+        let options = FormatOptions::new().with_normalised_separators(true);
+
+        assert_eq!(
+            "file_size(\"../Oblivion.exe\", 1234) and file(\"sub/Blank\\.esm\")",
+            formatted(
+                "file_size(\"..\\Oblivion.exe\", 1234) and file(\"sub/Blank\\.esm\")",
+                &options
+            )
+        );
+    }
+
+    #[test]
+    fn separator_normaliser_should_replace_backslashes_in_every_path_argument() {
+        assert_eq!(
+            Function::ArchiveContains("a/b.bsa".into(), "meshes/c.nif".into()),
+            SeparatorNormaliser.fold_function(Function::ArchiveContains(
+                "a\\b.bsa".into(),
+                "meshes\\c.nif".into()
+            ))
+        );
+        assert_eq!(
+            Function::LoadAfter("d/e.esp".into(), "f/g.esp".into()),
+            SeparatorNormaliser
+                .fold_function(Function::LoadAfter("d\\e.esp".into(), "f\\g.esp".into()))
+        );
+    }
+
+    #[test]
+    fn format_should_not_wrap_lines_that_fit() {
+        let options = FormatOptions::new().with_max_line_width(EXPRESSION.len());
+
+        assert_eq!(EXPRESSION, formatted(EXPRESSION, &options));
+    }
+
+    #[test]
+    fn format_should_wrap_long_lines_before_or_and_and_inside_parentheses() {
+        let options = FormatOptions::new().with_max_line_width(60);
+
+        assert_eq!(
+            "file(\"a.esp\")
+and (
+    active(\"b.esp\")
+    or not (many(\"c.*\") and is_master(\"d.esm\"))
+)
+or checksum(\"e.esp\", DEADBEEF)",
+            formatted(EXPRESSION, &options)
+        );
+    }
+
+    #[test]
+    fn format_should_use_the_given_indent_width() {
+        let options = FormatOptions::new()
+            .with_max_line_width(20)
+            .with_indent_width(2);
+
+        assert_eq!(
+            "file(\"a.esp\")
+and (
+  active(\"b.esp\")
+  or not (
+    many(\"c.*\")
+    and is_master(\"d.esm\")
+  )
+)
+or checksum(\"e.esp\", DEADBEEF)",
+            formatted(EXPRESSION, &options)
+        );
+    }
+
+    #[test]
+    fn format_should_not_split_functions_that_are_longer_than_the_max_line_width() {
+        let options = FormatOptions::new().with_max_line_width(5);
+
+        assert_eq!(
+            "file(\"a.esp\")\nor file(\"b.esp\")",
+            formatted("file(\"a.esp\") or file(\"b.esp\")", &options)
+        );
+    }
+
+    #[test]
+    fn format_output_should_parse_into_an_equal_expression() {
+        let string = format!("{EXPRESSION} or file_size(\"..\\Oblivion.exe\", 1234)");
+        let expression = Expression::from_str(&string).unwrap();
+
+        for width in [0, 20, 40, 80] {
+            let options = FormatOptions::new().with_max_line_width(width);
+            let string = format(&expression, &options);
+
+            assert_eq!(expression, Expression::from_str(&string).unwrap());
+        }
+    }
+}
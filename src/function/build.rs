@@ -0,0 +1,422 @@
+use nom::Parser;
+
+use super::parse::{
+    parse_filename_version_path, parse_header_version, parse_non_regex_path, parse_regex_filename,
+    parse_regex_path, parse_unquoted_path, parse_unquoted_regex, parse_unquoted_version,
+};
+use super::{ComparisonOperator, Function};
+use crate::error::{Error, ParsingError};
+use crate::map_err;
+
+/// Parse a function argument, requiring the whole of the argument to be
+/// consumed so that the argument is written out exactly as it was given.
+fn parse_argument<'a, O>(
+    input: &'a str,
+    mut parser: impl Parser<&'a str, Output = O, Error = ParsingError<&'a str>>,
+) -> Result<O, Error> {
+    let (remaining_input, output) = parser
+        .parse(input)
+        .map_err(|e| Error::from_parsing_error(input, e))?;
+
+    if remaining_input.is_empty() {
+        Ok(output)
+    } else {
+        Err(Error::UnconsumedInput(remaining_input.to_owned()))
+    }
+}
+
+/// These constructors take their arguments as they would be written (without
+/// quotes) in a condition string, and validate them using the same rules as
+/// the condition string parser, so that the constructed function can be
+/// written out using its `Display` implementation and parsed back into an
+/// equal function.
+impl Function {
+    /// Constructs a `file()` function. As when parsing, the path is treated as
+    /// a regex if it contains characters that are not valid in a plain path.
+    pub fn file(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path)
+            .map(Function::FilePath)
+            .or_else(|_| {
+                parse_argument(path, parse_regex_path)
+                    .map(|(path, regex)| Function::FileRegex(path, regex))
+            })
+    }
+
+    pub fn file_size(path: &str, size: u64) -> Result<Self, Error> {
+        parse_argument(path, map_err(parse_unquoted_path))
+            .map(|path| Function::FileSize(path, size))
+    }
+
+    pub fn readable(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path).map(Function::Readable)
+    }
+
+    pub fn is_executable(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path).map(Function::IsExecutable)
+    }
+
+    /// Constructs an `active()` function. As when parsing, the path is treated
+    /// as a regex if it contains characters that are not valid in a plain path.
+    pub fn active(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path)
+            .map(Function::ActivePath)
+            .or_else(|_| parse_argument(path, parse_regex_filename).map(Function::ActiveRegex))
+    }
+
+    pub fn is_master(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path).map(Function::IsMaster)
+    }
+
+    pub fn many(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_regex_path).map(|(path, regex)| Function::Many(path, regex))
+    }
+
+    pub fn many_active(regex: &str) -> Result<Self, Error> {
+        parse_argument(regex, parse_regex_filename).map(Function::ManyActive)
+    }
+
+    pub fn checksum(path: &str, crc: u32) -> Result<Self, Error> {
+        parse_argument(path, map_err(parse_unquoted_path)).map(|path| Function::Checksum(path, crc))
+    }
+
+    pub fn version(
+        path: &str,
+        comparator: ComparisonOperator,
+        version: &str,
+    ) -> Result<Self, Error> {
+        let path = parse_argument(path, map_err(parse_unquoted_path))?;
+        let version = parse_argument(version, map_err(parse_unquoted_version))?;
+
+        Ok(Function::Version(path, version, comparator))
+    }
+
+    pub fn product_version(
+        path: &str,
+        comparator: ComparisonOperator,
+        version: &str,
+    ) -> Result<Self, Error> {
+        let path = parse_argument(path, map_err(parse_unquoted_path))?;
+        let version = parse_argument(version, map_err(parse_unquoted_version))?;
+
+        Ok(Function::ProductVersion(path, version, comparator))
+    }
+
+    /// The last component of the path must be a regex that contains one
+    /// capture group, which is used to capture the version from the filename.
+    pub fn filename_version(
+        path: &str,
+        comparator: ComparisonOperator,
+        version: &str,
+    ) -> Result<Self, Error> {
+        let (parent_path, regex) = parse_argument(path, parse_filename_version_path)?;
+        let version = parse_argument(version, map_err(parse_unquoted_version))?;
+
+        Ok(Function::FilenameVersion(
+            parent_path,
+            regex,
+            version,
+            comparator,
+        ))
+    }
+
+    pub fn description_contains(path: &str, regex: &str) -> Result<Self, Error> {
+        let path = parse_argument(path, map_err(parse_unquoted_path))?;
+        let regex = parse_argument(regex, parse_unquoted_regex)?;
+
+        Ok(Function::DescriptionContains(path, regex))
+    }
+
+    pub fn has_master(path: &str, master: &str) -> Result<Self, Error> {
+        let path = parse_argument(path, parse_non_regex_path)?;
+        let master = parse_argument(master, parse_non_regex_path)?;
+
+        Ok(Function::HasMaster(path, master))
+    }
+
+    pub fn record_count(
+        path: &str,
+        comparator: ComparisonOperator,
+        count: u32,
+    ) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path)
+            .map(|path| Function::RecordCount(path, count, comparator))
+    }
+
+    pub fn override_record_count(
+        path: &str,
+        comparator: ComparisonOperator,
+        count: u32,
+    ) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path)
+            .map(|path| Function::OverrideRecordCount(path, count, comparator))
+    }
+
+    /// Constructs a `header_version()` function. The version must be a
+    /// non-negative finite number, so that it can be written in a condition
+    /// string.
+    pub fn header_version(
+        path: &str,
+        comparator: ComparisonOperator,
+        version: f32,
+    ) -> Result<Self, Error> {
+        let path = parse_argument(path, parse_non_regex_path)?;
+        let version = parse_argument(&version.to_string(), map_err(parse_header_version))?;
+
+        Ok(Function::HeaderVersion(path, version, comparator))
+    }
+
+    pub fn load_after(path: &str, other_path: &str) -> Result<Self, Error> {
+        let path = parse_argument(path, parse_non_regex_path)?;
+        let other_path = parse_argument(other_path, parse_non_regex_path)?;
+
+        Ok(Function::LoadAfter(path, other_path))
+    }
+
+    pub fn load_index(
+        path: &str,
+        comparator: ComparisonOperator,
+        index: u32,
+    ) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path)
+            .map(|path| Function::LoadIndex(path, index, comparator))
+    }
+
+    pub fn is_light(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path).map(Function::IsLight)
+    }
+
+    pub fn is_medium(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path).map(Function::IsMedium)
+    }
+
+    pub fn is_override(path: &str) -> Result<Self, Error> {
+        parse_argument(path, parse_non_regex_path).map(Function::IsOverride)
+    }
+
+    /// Constructs an `archive_contains()` function. As with `file()`, the path
+    /// of the file in the archive is treated as a regex if it contains
+    /// characters that are not valid in a plain path.
+    pub fn archive_contains(archive_path: &str, path: &str) -> Result<Self, Error> {
+        let archive_path = parse_argument(archive_path, parse_non_regex_path)?;
+
+        parse_argument(path, parse_non_regex_path)
+            .map(|path| Function::ArchiveContains(archive_path.clone(), path))
+            .or_else(|_| {
+                parse_argument(path, parse_regex_path).map(|(parent_path, regex)| {
+                    Function::ArchiveContainsRegex(archive_path, parent_path, regex)
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::Path;
+
+    use crate::ParsingErrorKind;
+
+    fn assert_round_trips(function: &Function) {
+        let string = function.to_string();
+        let (remaining_input, parsed) = Function::parse(&string).unwrap();
+
+        assert!(remaining_input.is_empty());
+        assert_eq!(function, &parsed, "{string} did not round-trip");
+    }
+
+    #[test]
+    fn file_should_construct_a_file_path_function_if_given_a_plain_path() {
+        let function = Function::file("subdir/Blank.esm").unwrap();
+
+        match &function {
+            Function::FilePath(p) => assert_eq!(Path::new("subdir/Blank.esm"), p),
+            f => panic!("Expected a file path function, got {f:?}"),
+        }
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn file_should_construct_a_file_regex_function_if_given_a_regex_path() {
+        let function = Function::file("subdir/Blank\\.es(p|m)").unwrap();
+
+        match &function {
+            Function::FileRegex(p, r) => {
+                assert_eq!(Path::new("subdir"), p);
+                assert_eq!("^Blank\\.es(p|m)$", r.as_str());
+            }
+            f => panic!("Expected a file regex function, got {f:?}"),
+        }
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn file_should_error_if_the_path_contains_a_quote() {
+        assert!(Function::file("Blank\".esm").is_err());
+    }
+
+    #[test]
+    fn file_should_error_if_the_regex_is_invalid() {
+        let error = Function::file("Blank.*(").unwrap_err();
+
+        assert!(matches!(
+            error,
+            Error::ParsingError(_, ParsingErrorKind::InvalidRegexSyntax(_), _)
+        ));
+    }
+
+    #[test]
+    fn file_should_error_if_given_an_empty_path() {
+        assert!(Function::file("").is_err());
+    }
+
+    #[test]
+    fn active_should_construct_an_active_path_or_regex_function() {
+        let function = Function::active("Blank.esm").unwrap();
+        assert!(matches!(function, Function::ActivePath(_)));
+        assert_round_trips(&function);
+
+        let function = Function::active("Blank\\.esm").unwrap();
+        assert!(matches!(function, Function::ActiveRegex(_)));
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn readable_is_executable_and_is_master_should_error_if_the_path_contains_a_backslash() {
+        assert!(Function::readable("..\\Oblivion.exe").is_err());
+        assert!(Function::is_executable("..\\Oblivion.exe").is_err());
+        assert!(Function::is_master("subdir\\Blank.esm").is_err());
+    }
+
+    #[test]
+    fn many_and_many_active_should_round_trip() {
+        assert_round_trips(&Function::many("Blank.*").unwrap());
+        assert_round_trips(&Function::many("subdir/Blank.*").unwrap());
+        assert_round_trips(&Function::many_active("Blank.*").unwrap());
+    }
+
+    #[test]
+    fn many_should_error_if_the_path_ends_in_a_directory_separator() {
+        assert!(Function::many("subdir/").is_err());
+    }
+
+    #[test]
+    fn file_size_and_checksum_should_round_trip() {
+        assert_round_trips(&Function::file_size("..\\Oblivion.exe", 1234).unwrap());
+        assert_round_trips(&Function::checksum("Blank.esm", 0xDEAD_BEEF).unwrap());
+    }
+
+    #[test]
+    fn version_and_product_version_should_round_trip() {
+        let function = Function::version(
+            "..\\Oblivion.exe",
+            ComparisonOperator::LessThan,
+            "1.2.0.416",
+        )
+        .unwrap();
+        assert_round_trips(&function);
+
+        let function =
+            Function::product_version("Oblivion.exe", ComparisonOperator::NotEqual, "1.2").unwrap();
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn version_should_error_if_the_version_contains_a_quote() {
+        assert!(Function::version("Blank.esm", ComparisonOperator::Equal, "1.\"0").is_err());
+    }
+
+    #[test]
+    fn version_should_error_if_the_version_is_empty() {
+        assert!(Function::version("Blank.esm", ComparisonOperator::Equal, "").is_err());
+    }
+
+    #[test]
+    fn filename_version_should_round_trip() {
+        let function = Function::filename_version(
+            "subdir/Blank (\\d+)\\.esm",
+            ComparisonOperator::GreaterThanOrEqual,
+            "2",
+        )
+        .unwrap();
+
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn filename_version_should_error_if_the_regex_does_not_have_one_capture_group() {
+        assert!(
+            Function::filename_version("Blank \\d+\\.esm", ComparisonOperator::Equal, "2").is_err()
+        );
+    }
+
+    #[test]
+    fn description_contains_should_round_trip() {
+        let function = Function::description_contains("Blank.esp", "Version: \\d+").unwrap();
+
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn has_master_should_round_trip() {
+        let function = Function::has_master("Blank.esp", "Blank.esm").unwrap();
+
+        assert_round_trips(&function);
+        assert!(Function::has_master("Blank.esp", "Blank.es.*").is_err());
+    }
+
+    #[test]
+    fn record_count_and_override_record_count_should_round_trip() {
+        let function =
+            Function::record_count("Blank.esp", ComparisonOperator::GreaterThanOrEqual, 100)
+                .unwrap();
+        assert_round_trips(&function);
+
+        let function =
+            Function::override_record_count("Blank.esp", ComparisonOperator::Equal, 0).unwrap();
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn header_version_should_round_trip() {
+        let function =
+            Function::header_version("Blank.esp", ComparisonOperator::LessThan, 1.7).unwrap();
+        assert_round_trips(&function);
+
+        assert!(
+            Function::header_version("Blank.esp", ComparisonOperator::LessThan, f32::NAN).is_err()
+        );
+        assert!(Function::header_version("Blank.esp", ComparisonOperator::LessThan, -1.0).is_err());
+    }
+
+    #[test]
+    fn load_after_and_load_index_should_round_trip() {
+        assert_round_trips(&Function::load_after("Patch.esp", "Blank.esp").unwrap());
+
+        let function = Function::load_index("Blank.esp", ComparisonOperator::LessThan, 10).unwrap();
+        assert_round_trips(&function);
+    }
+
+    #[test]
+    fn plugin_flag_functions_should_round_trip() {
+        assert_round_trips(&Function::is_light("Blank.esl").unwrap());
+        assert_round_trips(&Function::is_medium("Blank.esm").unwrap());
+        assert_round_trips(&Function::is_override("Blank.esm").unwrap());
+        assert!(Function::is_light("Blank.es.*").is_err());
+    }
+
+    #[test]
+    fn archive_contains_should_construct_a_path_or_regex_function() {
+        let function = Function::archive_contains("Blank.bsa", "meshes/Blank.nif").unwrap();
+
+        assert!(matches!(function, Function::ArchiveContains(_, _)));
+        assert_round_trips(&function);
+
+        let function = Function::archive_contains("Blank.bsa", "meshes/Blank\\.ni(f|s)").unwrap();
+
+        assert!(matches!(function, Function::ArchiveContainsRegex(_, _, _)));
+        assert_round_trips(&function);
+
+        assert!(Function::archive_contains("Blank.*", "meshes/Blank.nif").is_err());
+    }
+}
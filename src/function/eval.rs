@@ -1,6 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::ffi::OsStr;
-use std::fs::{read_dir, DirEntry, File};
 use std::hash::Hasher;
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
@@ -11,32 +10,38 @@ use regex::Regex;
 use super::path::{has_plugin_file_extension, normalise_file_name, resolve_path};
 use super::version::Version;
 use super::{ComparisonOperator, Function};
-use crate::{Error, GameType, State};
+use crate::archive::{loaded_archives, ArchiveIndex};
+use crate::{Error, FunctionEvaluation, GameType, ResultSource, State};
 
-fn evaluate_file_path(state: &State, file_path: &Path) -> bool {
-    resolve_path(state, file_path).exists()
+fn evaluate_file_path(state: &State, file_path: &Path) -> Result<bool, Error> {
+    if state.file_system.exists(&resolve_path(state, file_path)) {
+        return Ok(true);
+    }
+
+    Ok(loaded_archives(state)?.is_some_and(|a| a.contains(file_path)))
 }
 
-fn is_match(game_type: GameType, regex: &Regex, file_name: &OsStr) -> bool {
-    normalise_file_name(game_type, file_name)
-        .to_str()
+fn is_match(game_type: GameType, regex: &Regex, path: &Path) -> bool {
+    path.file_name()
+        .and_then(|file_name| normalise_file_name(game_type, file_name).to_str())
         .is_some_and(|s| regex.is_match(s))
 }
 
 fn evaluate_dir_entries_from_base_paths<'a>(
+    state: &State,
     base_path_iter: impl Iterator<Item = &'a PathBuf>,
     parent_path: &Path,
-    mut evaluator: impl FnMut(DirEntry) -> bool,
+    mut evaluator: impl FnMut(&Path) -> bool,
 ) -> Result<bool, Error> {
     for base_path in base_path_iter {
         let parent_path = base_path.join(parent_path);
-        let Ok(dir_iterator) = read_dir(&parent_path) else {
+        let Ok(dir_iterator) = state.file_system.read_dir(&parent_path) else {
             return Ok(false);
         };
 
-        for entry in dir_iterator {
-            let entry = entry.map_err(|e| Error::IoError(parent_path.clone(), e))?;
-            if evaluator(entry) {
+        for file_name in dir_iterator {
+            let file_name = file_name.map_err(|e| Error::IoError(parent_path.clone(), e))?;
+            if evaluator(&parent_path.join(file_name)) {
                 return Ok(true);
             }
         }
@@ -48,10 +53,11 @@ fn evaluate_dir_entries_from_base_paths<'a>(
 fn evaluate_dir_entries(
     state: &State,
     parent_path: &Path,
-    evaluator: impl FnMut(DirEntry) -> bool,
+    evaluator: impl FnMut(&Path) -> bool,
 ) -> Result<bool, Error> {
     match state.game_type {
         GameType::OpenMW => evaluate_dir_entries_from_base_paths(
+            state,
             state
                 .additional_data_paths
                 .iter()
@@ -61,6 +67,7 @@ fn evaluate_dir_entries(
             evaluator,
         ),
         _ => evaluate_dir_entries_from_base_paths(
+            state,
             state
                 .additional_data_paths
                 .iter()
@@ -72,35 +79,48 @@ fn evaluate_dir_entries(
 }
 
 fn evaluate_file_regex(state: &State, parent_path: &Path, regex: &Regex) -> Result<bool, Error> {
-    let evaluator = |entry: DirEntry| is_match(state.game_type, regex, &entry.file_name());
+    let evaluator = |path: &Path| is_match(state.game_type, regex, path);
 
-    evaluate_dir_entries(state, parent_path, evaluator)
+    if evaluate_dir_entries(state, parent_path, evaluator)? {
+        return Ok(true);
+    }
+
+    Ok(loaded_archives(state)?
+        .is_some_and(|a| a.dir_entries(parent_path).any(|n| regex.is_match(n))))
 }
 
 fn evaluate_file_size(state: &State, path: &Path, size: u64) -> Result<bool, Error> {
-    std::fs::metadata(resolve_path(state, path))
-        .map(|m| m.len() == size)
-        .or(Ok(false))
+    if let Ok(metadata) = state.file_system.metadata(&resolve_path(state, path)) {
+        return Ok(metadata.len() == size);
+    }
+
+    Ok(loaded_archives(state)?.is_some_and(|a| a.file_size(path) == Some(size)))
 }
 
 fn evaluate_readable(state: &State, path: &Path) -> bool {
-    if path.is_dir() {
-        read_dir(resolve_path(state, path)).is_ok()
+    let path = resolve_path(state, path);
+
+    if state.file_system.is_dir(&path) {
+        state.file_system.read_dir(&path).is_ok()
     } else {
-        File::open(resolve_path(state, path)).is_ok()
+        state.file_system.open(&path).is_ok()
     }
 }
 
 fn evaluate_is_executable(state: &State, path: &Path) -> bool {
-    Version::is_readable(&resolve_path(state, path))
+    Version::is_readable(state.file_system.as_ref(), &resolve_path(state, path))
 }
 
 fn evaluate_many(state: &State, parent_path: &Path, regex: &Regex) -> Result<bool, Error> {
+    if let Some(archives) = loaded_archives(state)? {
+        return evaluate_many_including_archives(state, parent_path, regex, &archives);
+    }
+
     // Share the found_one state across all data paths because they're all
     // treated as if they were merged into one directory.
     let mut found_one = false;
-    let evaluator = |entry: DirEntry| {
-        if is_match(state.game_type, regex, &entry.file_name()) {
+    let evaluator = |path: &Path| {
+        if is_match(state.game_type, regex, path) {
             if found_one {
                 true
             } else {
@@ -115,16 +135,109 @@ fn evaluate_many(state: &State, parent_path: &Path, regex: &Regex) -> Result<boo
     evaluate_dir_entries(state, parent_path, evaluator)
 }
 
+/// Loose files can have the same names as archived files, so count the
+/// distinct matching names instead of stopping at the second match.
+fn evaluate_many_including_archives(
+    state: &State,
+    parent_path: &Path,
+    regex: &Regex,
+    archives: &ArchiveIndex,
+) -> Result<bool, Error> {
+    let mut names = HashSet::new();
+    let evaluator = |path: &Path| {
+        if is_match(state.game_type, regex, path) {
+            if let Some(file_name) = path.file_name() {
+                let file_name = normalise_file_name(state.game_type, file_name);
+                names.insert(file_name.to_string_lossy().to_lowercase());
+            }
+        }
+        false
+    };
+
+    evaluate_dir_entries(state, parent_path, evaluator)?;
+
+    names.extend(
+        archives
+            .dir_entries(parent_path)
+            .filter(|n| regex.is_match(n))
+            .map(str::to_owned),
+    );
+
+    Ok(names.len() > 1)
+}
+
+fn evaluate_archive_contains(
+    state: &State,
+    archive_path: &Path,
+    predicate: impl FnOnce(&ArchiveIndex) -> bool,
+) -> Result<bool, Error> {
+    let archive_path = resolve_path(state, archive_path);
+
+    if !state.file_system.is_file(&archive_path) {
+        return Ok(false);
+    }
+
+    ArchiveIndex::read(state.file_system.as_ref(), &archive_path).map(|a| predicate(&a))
+}
+
+fn evaluate_archive_contains_regex(
+    state: &State,
+    archive_path: &Path,
+    parent_path: &Path,
+    regex: &Regex,
+) -> Result<bool, Error> {
+    evaluate_archive_contains(state, archive_path, |archive| {
+        archive
+            .dir_entries(parent_path)
+            .any(|n| regex.is_match(n) && archive.contains_file(&parent_path.join(n)))
+    })
+}
+
 fn evaluate_active_path(state: &State, path: &Path) -> bool {
     path.to_str()
         .is_some_and(|s| state.active_plugins.contains(&s.to_lowercase()))
 }
 
+fn load_order_index(state: &State, path: &Path) -> Option<usize> {
+    path.to_str()
+        .and_then(|s| state.load_order.get(&s.to_lowercase()))
+        .copied()
+}
+
+fn evaluate_load_after(state: &State, path: &Path, other_path: &Path) -> bool {
+    match (
+        load_order_index(state, path),
+        load_order_index(state, other_path),
+    ) {
+        (Some(index), Some(other_index)) => index > other_index,
+        _ => false,
+    }
+}
+
+fn evaluate_load_index(
+    state: &State,
+    path: &Path,
+    index: u32,
+    comparator: ComparisonOperator,
+) -> bool {
+    load_order_index(state, path)
+        .and_then(|actual| u32::try_from(actual).ok())
+        .is_some_and(|actual| compare(&actual, comparator, &index))
+}
+
 fn evaluate_active_regex(state: &State, regex: &Regex) -> bool {
     state.active_plugins.iter().any(|p| regex.is_match(p))
 }
 
 fn parse_plugin(state: &State, file_path: &Path) -> Option<esplugin::Plugin> {
+    parse_plugin_with_options(state, file_path, ParseOptions::header_only())
+}
+
+fn parse_plugin_with_options(
+    state: &State,
+    file_path: &Path,
+    options: ParseOptions,
+) -> Option<esplugin::Plugin> {
     use esplugin::GameId;
 
     let game_id = match state.game_type {
@@ -140,14 +253,50 @@ fn parse_plugin(state: &State, file_path: &Path) -> Option<esplugin::Plugin> {
 
     let path = resolve_path(state, file_path);
 
+    let reader = state.file_system.open(&path).ok()?;
+
     let mut plugin = esplugin::Plugin::new(game_id, &path);
 
     plugin
-        .parse_file(ParseOptions::header_only())
+        .parse_reader(reader, options)
         .is_ok()
         .then_some(plugin)
 }
 
+/// Parses the whole of a plugin and resolves its record IDs. Morrowind and
+/// Starfield plugins' record IDs can only be resolved using data from their
+/// masters, so they're also parsed, and if any can't be then neither can the
+/// plugin.
+fn parse_plugin_records(state: &State, file_path: &Path) -> Option<esplugin::Plugin> {
+    let mut plugin = parse_plugin_with_options(state, file_path, ParseOptions::whole_plugin())?;
+
+    let master_options = match state.game_type {
+        GameType::Morrowind | GameType::OpenMW => ParseOptions::whole_plugin(),
+        GameType::Starfield => ParseOptions::header_only(),
+        GameType::Oblivion
+        | GameType::Skyrim
+        | GameType::SkyrimSE
+        | GameType::SkyrimVR
+        | GameType::Fallout3
+        | GameType::FalloutNV
+        | GameType::Fallout4
+        | GameType::Fallout4VR => return Some(plugin),
+    };
+
+    let masters = plugin
+        .masters()
+        .ok()?
+        .iter()
+        .map(|master| parse_plugin_with_options(state, Path::new(master), master_options))
+        .collect::<Option<Vec<_>>>()?;
+    let masters: Vec<_> = masters.iter().collect();
+
+    let masters_metadata = esplugin::plugins_metadata(&masters).ok()?;
+    plugin.resolve_record_ids(&masters_metadata).ok()?;
+
+    Some(plugin)
+}
+
 fn evaluate_is_master(state: &State, file_path: &Path) -> bool {
     if state.game_type == GameType::OpenMW {
         false
@@ -156,6 +305,76 @@ fn evaluate_is_master(state: &State, file_path: &Path) -> bool {
     }
 }
 
+fn evaluate_is_light(state: &State, file_path: &Path) -> bool {
+    state.game_type.supports_light_plugins()
+        && parse_plugin(state, file_path).is_some_and(|plugin| plugin.is_light_plugin())
+}
+
+fn evaluate_is_medium(state: &State, file_path: &Path) -> bool {
+    state.game_type == GameType::Starfield
+        && parse_plugin(state, file_path).is_some_and(|plugin| plugin.is_medium_plugin())
+}
+
+fn evaluate_is_override(state: &State, file_path: &Path) -> bool {
+    state.game_type == GameType::Starfield
+        && parse_plugin(state, file_path).is_some_and(|plugin| plugin.is_update_plugin())
+}
+
+/// The count doesn't include the header record or groups. It's counted from
+/// the plugin's record IDs rather than taken from its header, which isn't
+/// always accurate. Like `override_record_count()`, this is false for Morrowind
+/// and Starfield plugins that have a master that can't be found.
+fn evaluate_record_count(
+    state: &State,
+    file_path: &Path,
+    count: u32,
+    comparator: ComparisonOperator,
+) -> bool {
+    // Every record overlaps with itself, and esplugin doesn't expose the
+    // number of records in any other way.
+    parse_plugin_records(state, file_path)
+        .and_then(|plugin| plugin.overlap_size(&[&plugin]).ok())
+        .and_then(|actual| u32::try_from(actual).ok())
+        .is_some_and(|actual| compare(&actual, comparator, &count))
+}
+
+/// This is false for Morrowind and Starfield plugins that have a master that
+/// can't be found, as their records can't be identified as overrides without
+/// it, so e.g. `override_record_count("X.esp", ==, 0)` can't be used to check
+/// that such a plugin is present.
+fn evaluate_override_record_count(
+    state: &State,
+    file_path: &Path,
+    count: u32,
+    comparator: ComparisonOperator,
+) -> bool {
+    parse_plugin_records(state, file_path)
+        .and_then(|plugin| plugin.count_override_records().ok())
+        .and_then(|actual| u32::try_from(actual).ok())
+        .is_some_and(|actual| compare(&actual, comparator, &count))
+}
+
+fn evaluate_header_version(
+    state: &State,
+    file_path: &Path,
+    version: f32,
+    comparator: ComparisonOperator,
+) -> bool {
+    parse_plugin(state, file_path)
+        .and_then(|plugin| plugin.header_version())
+        .is_some_and(|actual| compare(&actual, comparator, &version))
+}
+
+fn evaluate_has_master(state: &State, file_path: &Path, master: &Path) -> bool {
+    let Some(master) = master.to_str() else {
+        return false;
+    };
+
+    parse_plugin(state, file_path)
+        .and_then(|plugin| plugin.masters().ok())
+        .is_some_and(|masters| masters.iter().any(|m| unicase::eq(m.as_str(), master)))
+}
+
 #[expect(clippy::iter_over_hash_type)]
 fn evaluate_many_active(state: &State, regex: &Regex) -> bool {
     let mut found_one = false;
@@ -175,23 +394,25 @@ fn lowercase(path: &Path) -> Option<String> {
     path.to_str().map(str::to_lowercase)
 }
 
+fn cached_crc(state: &State, file_path: &Path) -> Option<u32> {
+    let reader = state.crc_cache.read().ok()?;
+    let key = lowercase(file_path)?;
+    reader.get(&key).copied()
+}
+
 fn evaluate_checksum(state: &State, file_path: &Path, crc: u32) -> Result<bool, Error> {
-    if let Ok(reader) = state.crc_cache.read() {
-        if let Some(key) = lowercase(file_path) {
-            if let Some(cached_crc) = reader.get(&key) {
-                return Ok(*cached_crc == crc);
-            }
-        }
+    if let Some(cached_crc) = cached_crc(state, file_path) {
+        return Ok(cached_crc == crc);
     }
 
     let path = resolve_path(state, file_path);
 
-    if !path.is_file() {
+    if !state.file_system.is_file(&path) {
         return Ok(false);
     }
 
     let io_error_mapper = |e| Error::IoError(file_path.to_path_buf(), e);
-    let file = File::open(path).map_err(io_error_mapper)?;
+    let file = state.file_system.open(&path).map_err(io_error_mapper)?;
     let mut reader = BufReader::new(file);
     let mut hasher = crc32fast::Hasher::new();
 
@@ -225,7 +446,7 @@ fn lowercase_filename(path: &Path) -> Option<String> {
 }
 
 fn get_version(state: &State, file_path: &Path) -> Result<Option<Version>, Error> {
-    if !file_path.is_file() {
+    if !state.file_system.is_file(file_path) {
         return Ok(None);
     }
 
@@ -238,33 +459,35 @@ fn get_version(state: &State, file_path: &Path) -> Result<Option<Version>, Error
     if has_plugin_file_extension(state.game_type, file_path) {
         Ok(None)
     } else {
-        Version::read_file_version(file_path)
+        Version::read_file_version(state.file_system.as_ref(), file_path)
     }
 }
 
-fn get_product_version(file_path: &Path) -> Result<Option<Version>, Error> {
-    if file_path.is_file() {
-        Version::read_product_version(file_path)
+fn get_product_version(state: &State, file_path: &Path) -> Result<Option<Version>, Error> {
+    if state.file_system.is_file(file_path) {
+        Version::read_product_version(state.file_system.as_ref(), file_path)
     } else {
         Ok(None)
     }
 }
 
+fn compare<T: PartialOrd>(actual: &T, comparator: ComparisonOperator, given: &T) -> bool {
+    match comparator {
+        ComparisonOperator::Equal => actual == given,
+        ComparisonOperator::NotEqual => actual != given,
+        ComparisonOperator::LessThan => actual < given,
+        ComparisonOperator::GreaterThan => actual > given,
+        ComparisonOperator::LessThanOrEqual => actual <= given,
+        ComparisonOperator::GreaterThanOrEqual => actual >= given,
+    }
+}
+
 fn compare_versions(
     actual_version: &Version,
     comparator: ComparisonOperator,
     given_version: &str,
 ) -> bool {
-    let given_version = &Version::from(given_version);
-
-    match comparator {
-        ComparisonOperator::Equal => actual_version == given_version,
-        ComparisonOperator::NotEqual => actual_version != given_version,
-        ComparisonOperator::LessThan => actual_version < given_version,
-        ComparisonOperator::GreaterThan => actual_version > given_version,
-        ComparisonOperator::LessThanOrEqual => actual_version <= given_version,
-        ComparisonOperator::GreaterThanOrEqual => actual_version >= given_version,
-    }
+    compare(actual_version, comparator, &Version::from(given_version))
 }
 
 fn evaluate_version<F>(
@@ -292,9 +515,9 @@ fn evaluate_filename_version(
     version: &str,
     comparator: ComparisonOperator,
 ) -> Result<bool, Error> {
-    let evaluator = |entry: DirEntry| {
-        normalise_file_name(state.game_type, &entry.file_name())
-            .to_str()
+    let evaluator = |path: &Path| {
+        path.file_name()
+            .and_then(|file_name| normalise_file_name(state.game_type, file_name).to_str())
             .and_then(|s| regex.captures(s))
             .and_then(|c| c.get(1))
             .map(|m| Version::from(m.as_str()))
@@ -312,16 +535,12 @@ fn evaluate_description_contains(state: &State, file_path: &Path, regex: &Regex)
 
 impl Function {
     pub fn eval(&self, state: &State) -> Result<bool, Error> {
-        if self.is_slow() {
-            if let Ok(reader) = state.condition_cache.read() {
-                if let Some(cached_result) = reader.get(self) {
-                    return Ok(*cached_result);
-                }
-            }
+        if let Some(cached_result) = self.cached_result(state) {
+            return Ok(cached_result);
         }
 
         let result = match self {
-            Function::FilePath(f) => Ok(evaluate_file_path(state, f)),
+            Function::FilePath(f) => evaluate_file_path(state, f),
             Function::FileRegex(p, r) => evaluate_file_regex(state, p, r),
             Function::FileSize(p, s) => evaluate_file_size(state, p, *s),
             Function::Readable(p) => Ok(evaluate_readable(state, p)),
@@ -334,10 +553,27 @@ impl Function {
             Function::Checksum(path, crc) => evaluate_checksum(state, path, *crc),
             Function::Version(p, v, c) => evaluate_version(state, p, v, *c, get_version),
             Function::ProductVersion(p, v, c) => {
-                evaluate_version(state, p, v, *c, |_, p| get_product_version(p))
+                evaluate_version(state, p, v, *c, get_product_version)
             }
             Function::FilenameVersion(p, r, v, c) => evaluate_filename_version(state, p, r, v, *c),
             Function::DescriptionContains(p, r) => Ok(evaluate_description_contains(state, p, r)),
+            Function::ArchiveContains(a, p) => {
+                evaluate_archive_contains(state, a, |archive| archive.contains_file(p))
+            }
+            Function::ArchiveContainsRegex(a, p, r) => {
+                evaluate_archive_contains_regex(state, a, p, r)
+            }
+            Function::HasMaster(p, m) => Ok(evaluate_has_master(state, p, m)),
+            Function::IsLight(p) => Ok(evaluate_is_light(state, p)),
+            Function::IsMedium(p) => Ok(evaluate_is_medium(state, p)),
+            Function::IsOverride(p) => Ok(evaluate_is_override(state, p)),
+            Function::RecordCount(p, n, c) => Ok(evaluate_record_count(state, p, *n, *c)),
+            Function::OverrideRecordCount(p, n, c) => {
+                Ok(evaluate_override_record_count(state, p, *n, *c))
+            }
+            Function::HeaderVersion(p, v, c) => Ok(evaluate_header_version(state, p, *v, *c)),
+            Function::LoadAfter(p, o) => Ok(evaluate_load_after(state, p, o)),
+            Function::LoadIndex(p, i, c) => Ok(evaluate_load_index(state, p, *i, *c)),
         };
 
         if self.is_slow() {
@@ -355,13 +591,87 @@ impl Function {
         result
     }
 
+    /// Evaluates the function, also recording where its result came from and
+    /// the path that its path argument resolved to, if it has one that is
+    /// resolved to a single file.
+    pub(crate) fn explain(&self, state: &State) -> Result<FunctionEvaluation, Error> {
+        let source = if self.cached_result(state).is_some() {
+            ResultSource::ConditionCache
+        } else if matches!(self, Function::Checksum(p, _) if cached_crc(state, p).is_some()) {
+            ResultSource::CrcCache
+        } else {
+            ResultSource::Evaluated
+        };
+
+        let result = self.eval(state)?;
+
+        let resolved_path = match self {
+            Function::FilePath(p)
+            | Function::FileSize(p, _)
+            | Function::Readable(p)
+            | Function::IsExecutable(p)
+            | Function::IsMaster(p)
+            | Function::Checksum(p, _)
+            | Function::Version(p, _, _)
+            | Function::ProductVersion(p, _, _)
+            | Function::DescriptionContains(p, _)
+            | Function::ArchiveContains(p, _)
+            | Function::ArchiveContainsRegex(p, _, _)
+            | Function::HasMaster(p, _)
+            | Function::IsLight(p)
+            | Function::IsMedium(p)
+            | Function::IsOverride(p)
+            | Function::RecordCount(p, _, _)
+            | Function::OverrideRecordCount(p, _, _)
+            | Function::HeaderVersion(p, _, _) => Some(resolve_path(state, p)),
+            _ => None,
+        };
+
+        // A file that was only found in an archive has no path on disk.
+        let resolved_path = match resolved_path {
+            Some(path) if !state.file_system.exists(&path) && self.is_archived(state)? => None,
+            path => path,
+        };
+
+        Ok(FunctionEvaluation {
+            result,
+            source,
+            resolved_path,
+        })
+    }
+
+    /// Whether the file that the function looks up is in the archives that
+    /// are loaded by the active plugins, if archive lookup is enabled.
+    fn is_archived(&self, state: &State) -> Result<bool, Error> {
+        match self {
+            Function::FilePath(p) | Function::FileSize(p, _) => {
+                Ok(loaded_archives(state)?.is_some_and(|a| a.contains(p)))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    fn cached_result(&self, state: &State) -> Option<bool> {
+        if !self.is_slow() {
+            return None;
+        }
+
+        let reader = state.condition_cache.read().ok()?;
+        reader.get(self).copied()
+    }
+
     /// Some functions are faster to evaluate than to look their result up in
     /// the cache, as the data they operate on are already cached separately and
     /// the operation is simple.
     fn is_slow(&self) -> bool {
         !matches!(
             self,
-            Self::ActivePath(_) | Self::ActiveRegex(_) | Self::ManyActive(_) | Self::Checksum(_, _)
+            Self::ActivePath(_)
+                | Self::ActiveRegex(_)
+                | Self::ManyActive(_)
+                | Self::Checksum(_, _)
+                | Self::LoadAfter(_, _)
+                | Self::LoadIndex(_, _, _)
         )
     }
 }
@@ -420,12 +730,16 @@ mod tests {
             data_path,
             additional_data_paths,
             active_plugins: active_plugins.iter().map(|s| s.to_lowercase()).collect(),
+            load_order: HashMap::new(),
             crc_cache: RwLock::default(),
             plugin_versions: plugin_versions
                 .iter()
                 .map(|(p, v)| (p.to_lowercase(), (*v).to_owned()))
                 .collect(),
             condition_cache: RwLock::default(),
+            file_system: Box::new(crate::DiskFileSystem),
+            archive_lookup: false,
+            archive_index: RwLock::default(),
         }
     }
 
@@ -458,17 +772,9 @@ mod tests {
         );
 
         let mut paths = Vec::new();
-        let evaluator = |entry: DirEntry| {
-            if entry.file_name() == "Blank.esp" {
-                paths.push(
-                    entry
-                        .path()
-                        .parent()
-                        .unwrap()
-                        .parent()
-                        .unwrap()
-                        .to_path_buf(),
-                );
+        let evaluator = |path: &Path| {
+            if path.file_name().unwrap() == "Blank.esp" {
+                paths.push(path.parent().unwrap().parent().unwrap().to_path_buf());
             }
             false
         };
@@ -500,17 +806,9 @@ mod tests {
         state.game_type = GameType::OpenMW;
 
         let mut paths = Vec::new();
-        let evaluator = |entry: DirEntry| {
-            if entry.file_name() == "Blank.esp" {
-                paths.push(
-                    entry
-                        .path()
-                        .parent()
-                        .unwrap()
-                        .parent()
-                        .unwrap()
-                        .to_path_buf(),
-                );
+        let evaluator = |path: &Path| {
+            if path.file_name().unwrap() == "Blank.esp" {
+                paths.push(path.parent().unwrap().parent().unwrap().to_path_buf());
             }
             false
         };
@@ -1563,17 +1861,21 @@ mod tests {
 
     #[test]
     fn get_product_version_should_return_ok_none_if_the_path_does_not_exist() {
-        assert!(get_product_version(Path::new("missing")).unwrap().is_none());
+        assert!(get_product_version(&state("."), Path::new("missing"))
+            .unwrap()
+            .is_none());
     }
 
     #[test]
     fn get_product_version_should_return_ok_none_if_the_path_is_not_a_file() {
-        assert!(get_product_version(Path::new("tests")).unwrap().is_none());
+        assert!(get_product_version(&state("."), Path::new("tests"))
+            .unwrap()
+            .is_none());
     }
 
     #[test]
     fn get_product_version_should_return_ok_some_if_the_path_is_an_executable() {
-        let version = get_product_version(Path::new("tests/libloot_win32/loot.dll"))
+        let version = get_product_version(&state("."), Path::new("tests/libloot_win32/loot.dll"))
             .unwrap()
             .unwrap();
 
@@ -1582,7 +1884,7 @@ mod tests {
 
     #[test]
     fn get_product_version_should_error_if_the_path_is_not_an_executable() {
-        assert!(get_product_version(Path::new("Cargo.toml")).is_err());
+        assert!(get_product_version(&state("."), Path::new("Cargo.toml")).is_err());
     }
 
     #[test]
@@ -1758,4 +2060,462 @@ mod tests {
 
         assert!(function.eval(&state).unwrap());
     }
+
+    fn state_with_archives() -> State {
+        use crate::archive::tests::{ba2, ba2_textures, bsa};
+
+        let file_system = crate::MemoryFileSystem::new()
+            .with_file("Data/v103.bsa", bsa(103, &[("meshes\\a.nif", b"")]))
+            .with_file("Data/v104.bsa", bsa(104, &[("meshes\\a.nif", b"")]))
+            .with_file(
+                "Data/v105.bsa",
+                bsa(105, &[("meshes\\a.nif", b""), ("meshes\\b\\c.nif", b"")]),
+            )
+            .with_file("Data/General.ba2", ba2(&[("Meshes\\A.nif", b"")]))
+            .with_file("Data/Textures.ba2", ba2_textures(&["Textures\\A.dds"]))
+            .with_file("Data/Invalid.bsa", "BSA\0");
+
+        State::new(GameType::SkyrimSE, "Data".into()).with_file_system(file_system)
+    }
+
+    #[test]
+    fn function_archive_contains_eval_should_be_true_if_the_archive_contains_the_path() {
+        let state = state_with_archives();
+
+        for archive in ["v103.bsa", "v104.bsa", "v105.bsa", "General.ba2"] {
+            let function = Function::ArchiveContains(archive.into(), "meshes/a.nif".into());
+            assert!(function.eval(&state).unwrap());
+
+            let function = Function::ArchiveContains(archive.into(), "meshes/b.nif".into());
+            assert!(!function.eval(&state).unwrap());
+        }
+
+        let function = Function::ArchiveContains("Textures.ba2".into(), "textures/a.dds".into());
+        assert!(function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_archive_contains_eval_should_not_match_directories() {
+        let state = state_with_archives();
+
+        let function = Function::ArchiveContains("v105.bsa".into(), "meshes/b".into());
+
+        assert!(!function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_archive_contains_eval_should_be_false_if_the_archive_does_not_exist() {
+        let state = state_with_archives();
+
+        let function = Function::ArchiveContains("missing.bsa".into(), "meshes/a.nif".into());
+
+        assert!(!function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_archive_contains_eval_should_error_if_the_archive_is_invalid() {
+        let state = state_with_archives();
+
+        let function = Function::ArchiveContains("Invalid.bsa".into(), "meshes/a.nif".into());
+
+        assert!(function.eval(&state).is_err());
+    }
+
+    #[test]
+    fn function_archive_contains_regex_eval_should_be_true_if_a_file_in_the_directory_matches() {
+        let state = state_with_archives();
+
+        let function =
+            Function::ArchiveContainsRegex("v105.bsa".into(), "meshes".into(), regex("^A\\.ni.$"));
+        assert!(function.eval(&state).unwrap());
+
+        let function =
+            Function::ArchiveContainsRegex("v105.bsa".into(), "meshes".into(), regex("^b$"));
+        assert!(!function.eval(&state).unwrap());
+
+        let function =
+            Function::ArchiveContainsRegex("v105.bsa".into(), "meshes/b".into(), regex(".*"));
+        assert!(function.eval(&state).unwrap());
+    }
+
+    /// Builds a plugin that contains only a header record with the given
+    /// record flags and masters. The header is the same for all games that
+    /// use TES4 records.
+    fn plugin_header(flags: u32, masters: &[&str]) -> Vec<u8> {
+        let mut subrecords = Vec::new();
+        subrecords.extend(b"HEDR");
+        subrecords.extend(12_u16.to_le_bytes());
+        subrecords.extend(1.71_f32.to_le_bytes());
+        subrecords.extend(0_u32.to_le_bytes());
+        subrecords.extend(0x800_u32.to_le_bytes());
+
+        for master in masters {
+            subrecords.extend(b"MAST");
+            subrecords.extend(u16::try_from(master.len() + 1).unwrap().to_le_bytes());
+            subrecords.extend(master.as_bytes());
+            subrecords.push(0);
+            subrecords.extend(b"DATA");
+            subrecords.extend(8_u16.to_le_bytes());
+            subrecords.extend(0_u64.to_le_bytes());
+        }
+
+        let mut bytes = b"TES4".to_vec();
+        bytes.extend(u32::try_from(subrecords.len()).unwrap().to_le_bytes());
+        bytes.extend(flags.to_le_bytes());
+        bytes.extend([0; 12]);
+        bytes.extend(subrecords);
+
+        bytes
+    }
+
+    /// Builds a plugin with the given masters that contains a group with a
+    /// record for each of the given FormIDs, and a header that gives the
+    /// number of records and groups.
+    fn plugin_with_records(masters: &[&str], form_ids: &[u32]) -> Vec<u8> {
+        let mut records = Vec::new();
+        for form_id in form_ids {
+            records.extend(b"GLOB");
+            records.extend([0; 8]);
+            records.extend(form_id.to_le_bytes());
+            records.extend([0; 8]);
+        }
+
+        let mut bytes = plugin_header(0, masters);
+        // The count is after the header record's header, the HEDR subrecord's
+        // header and the header version.
+        bytes.splice(
+            34..38,
+            u32::try_from(form_ids.len() + 1).unwrap().to_le_bytes(),
+        );
+        bytes.extend(b"GRUP");
+        bytes.extend(u32::try_from(records.len() + 24).unwrap().to_le_bytes());
+        bytes.extend(b"GLOB");
+        bytes.extend([0; 12]);
+        bytes.extend(records);
+
+        bytes
+    }
+
+    fn state_with_plugins(game_type: GameType, plugins: &[(&str, Vec<u8>)]) -> State {
+        let mut file_system = crate::MemoryFileSystem::new();
+        for (name, bytes) in plugins {
+            file_system.add_file(Path::new("Data").join(name), bytes.clone());
+        }
+
+        State::new(game_type, "Data".into()).with_file_system(file_system)
+    }
+
+    #[test]
+    fn function_has_master_eval_should_be_true_if_the_plugin_has_the_master() {
+        let state = state_with_plugins(
+            GameType::SkyrimSE,
+            &[("Blank.esp", plugin_header(0, &["Skyrim.esm", "Blank.esm"]))],
+        );
+
+        let function = Function::HasMaster("Blank.esp".into(), "blank.ESM".into());
+
+        assert!(function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_has_master_eval_should_be_false_if_the_plugin_does_not_have_the_master() {
+        let state = state_with_plugins(
+            GameType::SkyrimSE,
+            &[("Blank.esp", plugin_header(0, &["Skyrim.esm"]))],
+        );
+
+        let function = Function::HasMaster("Blank.esp".into(), "Blank.esm".into());
+
+        assert!(!function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_has_master_eval_should_be_false_if_the_path_is_not_a_plugin() {
+        let state = state_with_plugins(
+            GameType::SkyrimSE,
+            &[("Blank.esp", b"not a plugin".to_vec())],
+        );
+
+        let function = Function::HasMaster("Blank.esp".into(), "Blank.esm".into());
+        assert!(!function.eval(&state).unwrap());
+
+        let function = Function::HasMaster("missing.esp".into(), "Blank.esm".into());
+        assert!(!function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_is_light_eval_should_be_true_for_a_light_flagged_plugin() {
+        let state = state_with_plugins(
+            GameType::SkyrimSE,
+            &[
+                ("Blank.esp", plugin_header(0x200, &[])),
+                ("Blank.esl", plugin_header(0, &[])),
+                ("Other.esp", plugin_header(0, &[])),
+            ],
+        );
+
+        assert!(Function::IsLight("Blank.esp".into()).eval(&state).unwrap());
+        assert!(Function::IsLight("Blank.esl".into()).eval(&state).unwrap());
+        assert!(!Function::IsLight("Other.esp".into()).eval(&state).unwrap());
+        assert!(!Function::IsLight("missing.esp".into())
+            .eval(&state)
+            .unwrap());
+    }
+
+    #[test]
+    fn function_is_light_eval_should_be_false_if_the_game_does_not_support_light_plugins() {
+        let state = state_with_plugins(
+            GameType::Skyrim,
+            &[
+                ("Blank.esp", plugin_header(0x200, &[])),
+                ("Blank.esl", plugin_header(0, &[])),
+            ],
+        );
+
+        assert!(!Function::IsLight("Blank.esp".into()).eval(&state).unwrap());
+        assert!(!Function::IsLight("Blank.esl".into()).eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_is_medium_eval_should_be_true_for_a_medium_flagged_starfield_plugin() {
+        let state = state_with_plugins(
+            GameType::Starfield,
+            &[
+                ("Medium.esm", plugin_header(0x401, &[])),
+                ("Light.esm", plugin_header(0x501, &[])),
+                ("Full.esm", plugin_header(0x1, &[])),
+            ],
+        );
+
+        assert!(Function::IsMedium("Medium.esm".into())
+            .eval(&state)
+            .unwrap());
+        assert!(!Function::IsMedium("Light.esm".into()).eval(&state).unwrap());
+        assert!(!Function::IsMedium("Full.esm".into()).eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_is_medium_eval_should_be_false_for_games_other_than_starfield() {
+        let state = state_with_plugins(
+            GameType::SkyrimSE,
+            &[("Medium.esm", plugin_header(0x401, &[]))],
+        );
+
+        assert!(!Function::IsMedium("Medium.esm".into())
+            .eval(&state)
+            .unwrap());
+    }
+
+    #[test]
+    fn function_is_override_eval_should_be_true_for_an_update_flagged_starfield_plugin_with_masters(
+    ) {
+        let state = state_with_plugins(
+            GameType::Starfield,
+            &[
+                ("Override.esm", plugin_header(0x201, &["Starfield.esm"])),
+                ("NoMasters.esm", plugin_header(0x201, &[])),
+                ("Light.esm", plugin_header(0x301, &["Starfield.esm"])),
+                ("Full.esm", plugin_header(0x1, &["Starfield.esm"])),
+            ],
+        );
+
+        assert!(Function::IsOverride("Override.esm".into())
+            .eval(&state)
+            .unwrap());
+        assert!(!Function::IsOverride("NoMasters.esm".into())
+            .eval(&state)
+            .unwrap());
+        assert!(!Function::IsOverride("Light.esm".into())
+            .eval(&state)
+            .unwrap());
+        assert!(!Function::IsOverride("Full.esm".into())
+            .eval(&state)
+            .unwrap());
+    }
+
+    #[test]
+    fn function_is_override_eval_should_be_false_for_games_other_than_starfield() {
+        let state = state_with_plugins(
+            GameType::Fallout4,
+            &[("Override.esm", plugin_header(0x201, &["Fallout4.esm"]))],
+        );
+
+        assert!(!Function::IsOverride("Override.esm".into())
+            .eval(&state)
+            .unwrap());
+    }
+
+    #[test]
+    fn function_record_count_eval_should_compare_the_number_of_records_in_the_plugin() {
+        let state = state_with_plugins(
+            GameType::SkyrimSE,
+            &[
+                (
+                    "Blank.esp",
+                    plugin_with_records(&[], &[0x800, 0x801, 0x802]),
+                ),
+                ("Empty.esp", plugin_header(0, &[])),
+            ],
+        );
+
+        let function = |path: &str, comparator, count| {
+            Function::RecordCount(path.into(), count, comparator)
+                .eval(&state)
+                .unwrap()
+        };
+
+        assert!(function("Blank.esp", ComparisonOperator::Equal, 3));
+        assert!(function("Blank.esp", ComparisonOperator::GreaterThan, 2));
+        assert!(!function("Blank.esp", ComparisonOperator::LessThan, 3));
+        assert!(function("Empty.esp", ComparisonOperator::Equal, 0));
+    }
+
+    #[test]
+    fn function_record_count_eval_should_be_false_if_the_plugin_cannot_be_parsed() {
+        let state = state_with_plugins(GameType::SkyrimSE, &[("Blank.esp", b"TES4".to_vec())]);
+
+        let function = Function::RecordCount("Blank.esp".into(), 0, ComparisonOperator::Equal);
+        assert!(!function.eval(&state).unwrap());
+
+        let function = Function::RecordCount("missing.esp".into(), 0, ComparisonOperator::Equal);
+        assert!(!function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_override_record_count_eval_should_compare_the_number_of_override_records() {
+        let state = state_with_plugins(
+            GameType::SkyrimSE,
+            &[(
+                "Blank.esp",
+                plugin_with_records(&["Blank.esm"], &[0x800, 0x801, 0x0100_0800]),
+            )],
+        );
+
+        let function = |comparator, count| {
+            Function::OverrideRecordCount("Blank.esp".into(), count, comparator)
+                .eval(&state)
+                .unwrap()
+        };
+
+        assert!(function(ComparisonOperator::Equal, 2));
+        assert!(!function(ComparisonOperator::Equal, 0));
+        assert!(function(ComparisonOperator::NotEqual, 3));
+    }
+
+    #[test]
+    fn function_record_counts_eval_should_resolve_starfield_records_using_masters() {
+        let state = state_with_plugins(
+            GameType::Starfield,
+            &[
+                ("Blank.esm", plugin_header(0x1, &[])),
+                (
+                    "Blank.esp",
+                    plugin_with_records(&["Blank.esm"], &[0x800, 0x0100_0800]),
+                ),
+                (
+                    "Orphan.esp",
+                    plugin_with_records(&["missing.esm"], &[0x800]),
+                ),
+            ],
+        );
+
+        let function =
+            Function::OverrideRecordCount("Blank.esp".into(), 1, ComparisonOperator::Equal);
+        assert!(function.eval(&state).unwrap());
+
+        let function = Function::RecordCount("Blank.esp".into(), 2, ComparisonOperator::Equal);
+        assert!(function.eval(&state).unwrap());
+
+        let function =
+            Function::OverrideRecordCount("Orphan.esp".into(), 1, ComparisonOperator::Equal);
+        assert!(!function.eval(&state).unwrap());
+
+        let function =
+            Function::OverrideRecordCount("Orphan.esp".into(), 0, ComparisonOperator::Equal);
+        assert!(!function.eval(&state).unwrap());
+
+        let function = Function::RecordCount("Orphan.esp".into(), 1, ComparisonOperator::Equal);
+        assert!(!function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_header_version_eval_should_compare_the_plugin_header_version() {
+        let state = state_with_plugins(GameType::SkyrimSE, &[("Blank.esp", plugin_header(0, &[]))]);
+
+        let function = |comparator, version| {
+            Function::HeaderVersion("Blank.esp".into(), version, comparator)
+                .eval(&state)
+                .unwrap()
+        };
+
+        assert!(function(ComparisonOperator::Equal, 1.71));
+        assert!(function(ComparisonOperator::GreaterThanOrEqual, 1.7));
+        assert!(!function(ComparisonOperator::LessThan, 1.7));
+    }
+
+    #[test]
+    fn function_header_version_eval_should_be_false_if_the_path_is_not_a_plugin() {
+        let state = state_with_plugins(GameType::SkyrimSE, &[("Blank.esp", b"TES4".to_vec())]);
+
+        let function =
+            Function::HeaderVersion("Blank.esp".into(), 1.7, ComparisonOperator::NotEqual);
+        assert!(!function.eval(&state).unwrap());
+
+        let function =
+            Function::HeaderVersion("missing.esp".into(), 1.7, ComparisonOperator::NotEqual);
+        assert!(!function.eval(&state).unwrap());
+    }
+
+    #[test]
+    fn function_load_after_eval_should_compare_load_order_positions() {
+        let state = State::new(GameType::SkyrimSE, ".".into()).with_load_order(&[
+            "Skyrim.esm",
+            "Blank.esp",
+            "Patch.esp",
+        ]);
+
+        let function = |path: &str, other_path: &str| {
+            Function::LoadAfter(path.into(), other_path.into())
+                .eval(&state)
+                .unwrap()
+        };
+
+        assert!(function("Patch.esp", "blank.ESP"));
+        assert!(!function("Blank.esp", "Patch.esp"));
+        assert!(!function("Blank.esp", "Blank.esp"));
+        assert!(!function("Patch.esp", "missing.esp"));
+        assert!(!function("missing.esp", "Blank.esp"));
+    }
+
+    #[test]
+    fn function_load_index_eval_should_compare_the_load_order_position() {
+        let state = State::new(GameType::SkyrimSE, ".".into()).with_load_order(&[
+            "Skyrim.esm",
+            "Blank.esp",
+            "Patch.esp",
+        ]);
+
+        let function = |path: &str, comparator, index| {
+            Function::LoadIndex(path.into(), index, comparator)
+                .eval(&state)
+                .unwrap()
+        };
+
+        assert!(function("Skyrim.esm", ComparisonOperator::Equal, 0));
+        assert!(function("patch.esp", ComparisonOperator::Equal, 2));
+        assert!(function("Blank.esp", ComparisonOperator::LessThan, 10));
+        assert!(!function("missing.esp", ComparisonOperator::LessThan, 10));
+    }
+
+    #[test]
+    fn function_load_after_eval_should_not_be_cached() {
+        let mut state =
+            State::new(GameType::SkyrimSE, ".".into()).with_load_order(&["Blank.esp", "Patch.esp"]);
+
+        let function = Function::LoadAfter("Patch.esp".into(), "Blank.esp".into());
+        assert!(function.eval(&state).unwrap());
+
+        state.set_load_order(&["Patch.esp", "Blank.esp"]);
+        assert!(!function.eval(&state).unwrap());
+    }
 }
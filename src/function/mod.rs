@@ -1,15 +1,16 @@
 #![allow(
     clippy::multiple_inherent_impl,
-    reason = "impl Function is split between parsing and eval"
+    reason = "impl Function is split between building, parsing and eval"
 )]
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::mem::discriminant;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use regex::Regex;
 use unicase::eq;
 
+mod build;
 pub(crate) mod eval;
 pub(crate) mod parse;
 mod path;
@@ -39,6 +40,7 @@ impl fmt::Display for ComparisonOperator {
 }
 
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub enum Function {
     FilePath(PathBuf),
     FileRegex(PathBuf, Regex),
@@ -55,21 +57,219 @@ pub enum Function {
     ProductVersion(PathBuf, String, ComparisonOperator),
     FilenameVersion(PathBuf, Regex, String, ComparisonOperator),
     DescriptionContains(PathBuf, Regex),
+    ArchiveContains(PathBuf, PathBuf),
+    ArchiveContainsRegex(PathBuf, PathBuf, Regex),
+    HasMaster(PathBuf, PathBuf),
+    IsLight(PathBuf),
+    IsMedium(PathBuf),
+    IsOverride(PathBuf),
+    RecordCount(PathBuf, u32, ComparisonOperator),
+    OverrideRecordCount(PathBuf, u32, ComparisonOperator),
+    HeaderVersion(PathBuf, f32, ComparisonOperator),
+    LoadAfter(PathBuf, PathBuf),
+    LoadIndex(PathBuf, u32, ComparisonOperator),
+}
+
+/// Matches a function's path argument, so that the same match can be used to
+/// get shared and mutable references to it.
+macro_rules! path_argument {
+    ($function:expr) => {
+        match $function {
+            Function::FilePath(p)
+            | Function::FileRegex(p, _)
+            | Function::FileSize(p, _)
+            | Function::Readable(p)
+            | Function::IsExecutable(p)
+            | Function::ActivePath(p)
+            | Function::IsMaster(p)
+            | Function::Many(p, _)
+            | Function::Checksum(p, _)
+            | Function::Version(p, _, _)
+            | Function::ProductVersion(p, _, _)
+            | Function::FilenameVersion(p, _, _, _)
+            | Function::DescriptionContains(p, _)
+            | Function::ArchiveContains(p, _)
+            | Function::ArchiveContainsRegex(p, _, _)
+            | Function::HasMaster(p, _)
+            | Function::IsLight(p)
+            | Function::IsMedium(p)
+            | Function::IsOverride(p)
+            | Function::RecordCount(p, _, _)
+            | Function::OverrideRecordCount(p, _, _)
+            | Function::HeaderVersion(p, _, _)
+            | Function::LoadAfter(p, _)
+            | Function::LoadIndex(p, _, _) => Some(p),
+            Function::ActiveRegex(_) | Function::ManyActive(_) => None,
+        }
+    };
+}
+
+impl Function {
+    /// The name of the function as it appears in condition strings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FilePath(_) | Self::FileRegex(_, _) => "file",
+            Self::FileSize(_, _) => "file_size",
+            Self::Readable(_) => "readable",
+            Self::IsExecutable(_) => "is_executable",
+            Self::ActivePath(_) | Self::ActiveRegex(_) => "active",
+            Self::IsMaster(_) => "is_master",
+            Self::Many(_, _) => "many",
+            Self::ManyActive(_) => "many_active",
+            Self::Checksum(_, _) => "checksum",
+            Self::Version(_, _, _) => "version",
+            Self::ProductVersion(_, _, _) => "product_version",
+            Self::FilenameVersion(_, _, _, _) => "filename_version",
+            Self::DescriptionContains(_, _) => "description_contains",
+            Self::ArchiveContains(_, _) | Self::ArchiveContainsRegex(_, _, _) => "archive_contains",
+            Self::HasMaster(_, _) => "has_master",
+            Self::IsLight(_) => "is_light",
+            Self::IsMedium(_) => "is_medium",
+            Self::IsOverride(_) => "is_override",
+            Self::RecordCount(_, _, _) => "record_count",
+            Self::OverrideRecordCount(_, _, _) => "override_record_count",
+            Self::HeaderVersion(_, _, _) => "header_version",
+            Self::LoadAfter(_, _) => "load_after",
+            Self::LoadIndex(_, _, _) => "load_index",
+        }
+    }
+
+    /// The function's path argument. For functions that take a path with a
+    /// regex as its last component, this is the path's parent. For
+    /// `archive_contains()`, this is the archive's path.
+    pub fn path(&self) -> Option<&Path> {
+        path_argument!(self).map(PathBuf::as_path)
+    }
+
+    /// The function's regex argument, if it has one.
+    pub fn regex(&self) -> Option<&Regex> {
+        match self {
+            Self::FileRegex(_, r)
+            | Self::ActiveRegex(r)
+            | Self::Many(_, r)
+            | Self::ManyActive(r)
+            | Self::FilenameVersion(_, r, _, _)
+            | Self::DescriptionContains(_, r)
+            | Self::ArchiveContainsRegex(_, _, r) => Some(r),
+            Self::FilePath(_)
+            | Self::FileSize(_, _)
+            | Self::Readable(_)
+            | Self::IsExecutable(_)
+            | Self::ActivePath(_)
+            | Self::IsMaster(_)
+            | Self::Checksum(_, _)
+            | Self::Version(_, _, _)
+            | Self::ProductVersion(_, _, _)
+            | Self::ArchiveContains(_, _)
+            | Self::HasMaster(_, _)
+            | Self::IsLight(_)
+            | Self::IsMedium(_)
+            | Self::IsOverride(_)
+            | Self::RecordCount(_, _, _)
+            | Self::OverrideRecordCount(_, _, _)
+            | Self::HeaderVersion(_, _, _)
+            | Self::LoadAfter(_, _)
+            | Self::LoadIndex(_, _, _) => None,
+        }
+    }
+
+    /// The function's version string argument and the operator that it is
+    /// compared with, if it has one.
+    pub fn version_argument(&self) -> Option<(&str, ComparisonOperator)> {
+        match self {
+            Self::Version(_, v, c)
+            | Self::ProductVersion(_, v, c)
+            | Self::FilenameVersion(_, _, v, c) => Some((v, *c)),
+            Self::FilePath(_)
+            | Self::FileRegex(_, _)
+            | Self::FileSize(_, _)
+            | Self::Readable(_)
+            | Self::IsExecutable(_)
+            | Self::ActivePath(_)
+            | Self::ActiveRegex(_)
+            | Self::IsMaster(_)
+            | Self::Many(_, _)
+            | Self::ManyActive(_)
+            | Self::Checksum(_, _)
+            | Self::DescriptionContains(_, _)
+            | Self::ArchiveContains(_, _)
+            | Self::ArchiveContainsRegex(_, _, _)
+            | Self::HasMaster(_, _)
+            | Self::IsLight(_)
+            | Self::IsMedium(_)
+            | Self::IsOverride(_)
+            | Self::RecordCount(_, _, _)
+            | Self::OverrideRecordCount(_, _, _)
+            | Self::HeaderVersion(_, _, _)
+            | Self::LoadAfter(_, _)
+            | Self::LoadIndex(_, _, _) => None,
+        }
+    }
+
+    /// Whether evaluating the function reads the filesystem, as opposed to
+    /// only the active plugins and load order given to the state.
+    pub fn accesses_filesystem(&self) -> bool {
+        !matches!(
+            self,
+            Self::ActivePath(_)
+                | Self::ActiveRegex(_)
+                | Self::ManyActive(_)
+                | Self::LoadAfter(_, _)
+                | Self::LoadIndex(_, _, _)
+        )
+    }
+
+    /// Mutable references to all of the function's path arguments. For regex
+    /// paths these are the paths of the directories that the regexes match
+    /// filenames in.
+    pub(crate) fn paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        match self {
+            Self::ArchiveContains(p1, p2)
+            | Self::ArchiveContainsRegex(p1, p2, _)
+            | Self::HasMaster(p1, p2)
+            | Self::LoadAfter(p1, p2) => vec![p1, p2],
+            _ => path_argument!(self).into_iter().collect(),
+        }
+    }
+}
+
+/// Regexes that are parsed from paths and filenames are anchored so that they
+/// must match a whole filename, so the anchors must be removed when writing
+/// the regexes back out.
+fn unanchored(regex: &Regex) -> &str {
+    let string = regex.as_str();
+    string
+        .strip_prefix('^')
+        .and_then(|s| s.strip_suffix('$'))
+        .unwrap_or(string)
+}
+
+struct RegexPath<'a>(&'a Path, &'a Regex);
+
+impl fmt::Display for RegexPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // A regex with no parent path is parsed as having a parent path of ".".
+        if self.0 == Path::new(".") {
+            write!(f, "{}", unanchored(self.1))
+        } else {
+            write!(f, "{}/{}", self.0.display(), unanchored(self.1))
+        }
+    }
 }
 
 impl fmt::Display for Function {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Self::FilePath(p) => write!(f, "file(\"{}\")", p.display()),
-            Self::FileRegex(p, r) => write!(f, "file(\"{}/{}\")", p.display(), r),
+            Self::FileRegex(p, r) => write!(f, "file(\"{}\")", RegexPath(p, r)),
             Self::FileSize(p, s) => write!(f, "file_size(\"{}\", {})", p.display(), s),
             Self::Readable(p) => write!(f, "readable(\"{}\")", p.display()),
             Self::IsExecutable(p) => write!(f, "is_executable(\"{}\")", p.display()),
             Self::ActivePath(p) => write!(f, "active(\"{}\")", p.display()),
-            Self::ActiveRegex(r) => write!(f, "active(\"{r}\")"),
+            Self::ActiveRegex(r) => write!(f, "active(\"{}\")", unanchored(r)),
             Self::IsMaster(p) => write!(f, "is_master(\"{}\")", p.display()),
-            Self::Many(p, r) => write!(f, "many(\"{}/{}\")", p.display(), r),
-            Self::ManyActive(r) => write!(f, "many_active(\"{r}\")"),
+            Self::Many(p, r) => write!(f, "many(\"{}\")", RegexPath(p, r)),
+            Self::ManyActive(r) => write!(f, "many_active(\"{}\")", unanchored(r)),
             Self::Checksum(p, c) => write!(f, "checksum(\"{}\", {:02X})", p.display(), c),
             Self::Version(p, v, c) => write!(f, "version(\"{}\", {}, \"{}\")", p.display(), c, v),
             Self::ProductVersion(p, v, c) => {
@@ -78,9 +278,8 @@ impl fmt::Display for Function {
             Self::FilenameVersion(path, regex, version, comparator) => {
                 write!(
                     f,
-                    "filename_version(\"{}/{}\", {}, \"{}\")",
-                    path.display(),
-                    regex,
+                    "filename_version(\"{}\", {}, \"{}\")",
+                    RegexPath(path, regex),
                     comparator,
                     version
                 )
@@ -88,10 +287,62 @@ impl fmt::Display for Function {
             Self::DescriptionContains(p, r) => {
                 write!(f, "description_contains(\"{}\", \"{}\")", p.display(), r)
             }
+            Self::ArchiveContains(a, p) => {
+                write!(
+                    f,
+                    "archive_contains(\"{}\", \"{}\")",
+                    a.display(),
+                    p.display()
+                )
+            }
+            Self::ArchiveContainsRegex(a, p, r) => {
+                write!(
+                    f,
+                    "archive_contains(\"{}\", \"{}\")",
+                    a.display(),
+                    RegexPath(p, r)
+                )
+            }
+            Self::HasMaster(p, m) => {
+                write!(f, "has_master(\"{}\", \"{}\")", p.display(), m.display())
+            }
+            Self::IsLight(p) => write!(f, "is_light(\"{}\")", p.display()),
+            Self::IsMedium(p) => write!(f, "is_medium(\"{}\")", p.display()),
+            Self::IsOverride(p) => write!(f, "is_override(\"{}\")", p.display()),
+            Self::RecordCount(p, n, c) => {
+                write!(f, "record_count(\"{}\", {}, {})", p.display(), c, n)
+            }
+            Self::OverrideRecordCount(p, n, c) => {
+                write!(
+                    f,
+                    "override_record_count(\"{}\", {}, {})",
+                    p.display(),
+                    c,
+                    n
+                )
+            }
+            Self::HeaderVersion(p, v, c) => {
+                write!(f, "header_version(\"{}\", {}, {})", p.display(), c, v)
+            }
+            Self::LoadAfter(p, o) => {
+                write!(f, "load_after(\"{}\", \"{}\")", p.display(), o.display())
+            }
+            Self::LoadIndex(p, i, c) => {
+                write!(f, "load_index(\"{}\", {}, {})", p.display(), c, i)
+            }
         }
     }
 }
 
+/// Paths are compared case-insensitively.
+fn path_eq(path1: &Path, path2: &Path) -> bool {
+    eq(&path1.to_string_lossy(), &path2.to_string_lossy())
+}
+
+fn hash_path<H: Hasher>(path: &Path, state: &mut H) {
+    path.to_string_lossy().to_lowercase().hash(state);
+}
+
 impl PartialEq for Function {
     fn eq(&self, other: &Function) -> bool {
         match (self, other) {
@@ -99,31 +350,43 @@ impl PartialEq for Function {
             | (Self::Readable(p1), Self::Readable(p2))
             | (Self::IsExecutable(p1), Self::IsExecutable(p2))
             | (Self::ActivePath(p1), Self::ActivePath(p2))
-            | (Self::IsMaster(p1), Self::IsMaster(p2)) => {
-                eq(&p1.to_string_lossy(), &p2.to_string_lossy())
-            }
+            | (Self::IsMaster(p1), Self::IsMaster(p2))
+            | (Self::IsLight(p1), Self::IsLight(p2))
+            | (Self::IsMedium(p1), Self::IsMedium(p2))
+            | (Self::IsOverride(p1), Self::IsOverride(p2)) => path_eq(p1, p2),
             (Self::FileRegex(p1, r1), Self::FileRegex(p2, r2))
             | (Self::Many(p1, r1), Self::Many(p2, r2))
             | (Self::DescriptionContains(p1, r1), Self::DescriptionContains(p2, r2)) => {
-                eq(r1.as_str(), r2.as_str()) && eq(&p1.to_string_lossy(), &p2.to_string_lossy())
-            }
-            (Self::FileSize(p1, s1), Self::FileSize(p2, s2)) => {
-                s1 == s2 && eq(&p1.to_string_lossy(), &p2.to_string_lossy())
+                eq(r1.as_str(), r2.as_str()) && path_eq(p1, p2)
             }
+            (Self::FileSize(p1, s1), Self::FileSize(p2, s2)) => s1 == s2 && path_eq(p1, p2),
             (Self::ActiveRegex(r1), Self::ActiveRegex(r2))
             | (Self::ManyActive(r1), Self::ManyActive(r2)) => eq(r1.as_str(), r2.as_str()),
-            (Self::Checksum(p1, c1), Self::Checksum(p2, c2)) => {
-                c1 == c2 && eq(&p1.to_string_lossy(), &p2.to_string_lossy())
-            }
+            (Self::Checksum(p1, c1), Self::Checksum(p2, c2)) => c1 == c2 && path_eq(p1, p2),
             (Self::Version(p1, v1, c1), Self::Version(p2, v2, c2))
             | (Self::ProductVersion(p1, v1, c1), Self::ProductVersion(p2, v2, c2)) => {
-                c1 == c2 && eq(&v1, &v2) && eq(&p1.to_string_lossy(), &p2.to_string_lossy())
+                c1 == c2 && eq(&v1, &v2) && path_eq(p1, p2)
             }
             (Self::FilenameVersion(p1, r1, v1, c1), Self::FilenameVersion(p2, r2, v2, c2)) => {
-                c1 == c2
-                    && eq(&v1, &v2)
-                    && eq(r1.as_str(), r2.as_str())
-                    && eq(&p1.to_string_lossy(), &p2.to_string_lossy())
+                c1 == c2 && eq(&v1, &v2) && eq(r1.as_str(), r2.as_str()) && path_eq(p1, p2)
+            }
+            (Self::ArchiveContains(a1, p1), Self::ArchiveContains(a2, p2)) => {
+                path_eq(a1, a2) && path_eq(p1, p2)
+            }
+            (Self::ArchiveContainsRegex(a1, p1, r1), Self::ArchiveContainsRegex(a2, p2, r2)) => {
+                eq(r1.as_str(), r2.as_str()) && path_eq(a1, a2) && path_eq(p1, p2)
+            }
+            (Self::HasMaster(p1, m1), Self::HasMaster(p2, m2))
+            | (Self::LoadAfter(p1, m1), Self::LoadAfter(p2, m2)) => {
+                path_eq(m1, m2) && path_eq(p1, p2)
+            }
+            (Self::RecordCount(p1, n1, c1), Self::RecordCount(p2, n2, c2))
+            | (Self::OverrideRecordCount(p1, n1, c1), Self::OverrideRecordCount(p2, n2, c2))
+            | (Self::LoadIndex(p1, n1, c1), Self::LoadIndex(p2, n2, c2)) => {
+                c1 == c2 && n1 == n2 && path_eq(p1, p2)
+            }
+            (Self::HeaderVersion(p1, v1, c1), Self::HeaderVersion(p2, v2, c2)) => {
+                c1 == c2 && v1.to_bits() == v2.to_bits() && path_eq(p1, p2)
             }
             _ => false,
         }
@@ -139,35 +402,63 @@ impl Hash for Function {
             | Self::Readable(p)
             | Self::IsExecutable(p)
             | Self::ActivePath(p)
-            | Self::IsMaster(p) => {
-                p.to_string_lossy().to_lowercase().hash(state);
+            | Self::IsMaster(p)
+            | Self::IsLight(p)
+            | Self::IsMedium(p)
+            | Self::IsOverride(p) => {
+                hash_path(p, state);
             }
             Self::FileRegex(p, r) | Self::Many(p, r) | Self::DescriptionContains(p, r) => {
-                p.to_string_lossy().to_lowercase().hash(state);
+                hash_path(p, state);
                 r.as_str().to_lowercase().hash(state);
             }
             Self::FileSize(p, s) => {
-                p.to_string_lossy().to_lowercase().hash(state);
+                hash_path(p, state);
                 s.hash(state);
             }
             Self::ActiveRegex(r) | Self::ManyActive(r) => {
                 r.as_str().to_lowercase().hash(state);
             }
             Self::Checksum(p, c) => {
-                p.to_string_lossy().to_lowercase().hash(state);
+                hash_path(p, state);
                 c.hash(state);
             }
             Self::Version(p, v, c) | Self::ProductVersion(p, v, c) => {
-                p.to_string_lossy().to_lowercase().hash(state);
+                hash_path(p, state);
                 v.to_lowercase().hash(state);
                 c.hash(state);
             }
             Self::FilenameVersion(p, r, v, c) => {
-                p.to_string_lossy().to_lowercase().hash(state);
+                hash_path(p, state);
                 r.as_str().to_lowercase().hash(state);
                 v.to_lowercase().hash(state);
                 c.hash(state);
             }
+            Self::ArchiveContains(a, p) => {
+                hash_path(a, state);
+                hash_path(p, state);
+            }
+            Self::ArchiveContainsRegex(a, p, r) => {
+                hash_path(a, state);
+                hash_path(p, state);
+                r.as_str().to_lowercase().hash(state);
+            }
+            Self::HasMaster(p, m) | Self::LoadAfter(p, m) => {
+                hash_path(p, state);
+                hash_path(m, state);
+            }
+            Self::RecordCount(p, n, c)
+            | Self::OverrideRecordCount(p, n, c)
+            | Self::LoadIndex(p, n, c) => {
+                hash_path(p, state);
+                n.hash(state);
+                c.hash(state);
+            }
+            Self::HeaderVersion(p, v, c) => {
+                hash_path(p, state);
+                v.to_bits().hash(state);
+                c.hash(state);
+            }
         }
 
         discriminant(self).hash(state);
@@ -185,6 +476,83 @@ mod tests {
         Regex::new(string).unwrap()
     }
 
+    mod accessors {
+        use super::*;
+
+        #[test]
+        fn function_name_should_match_the_name_used_in_condition_strings() {
+            assert_eq!("file", Function::FilePath("Blank.esm".into()).name());
+            assert_eq!(
+                "file",
+                Function::FileRegex("subdir".into(), regex("Blank.*")).name()
+            );
+            assert_eq!("active", Function::ActiveRegex(regex("Blank.*")).name());
+            assert_eq!(
+                "filename_version",
+                Function::FilenameVersion(
+                    "subdir".into(),
+                    regex("Blank (.+).esm"),
+                    "1.2".into(),
+                    ComparisonOperator::Equal,
+                )
+                .name()
+            );
+        }
+
+        #[test]
+        fn function_path_should_return_the_parent_path_for_a_regex_path() {
+            let function = Function::Many("subdir".into(), regex("Blank.*"));
+
+            assert_eq!(Some(Path::new("subdir")), function.path());
+        }
+
+        #[test]
+        fn function_path_should_return_none_for_functions_without_a_path() {
+            assert!(Function::ManyActive(regex("Blank.*")).path().is_none());
+        }
+
+        #[test]
+        fn function_regex_should_return_the_regex_argument_if_there_is_one() {
+            let function = Function::DescriptionContains("Blank.esp".into(), regex("Blank.*"));
+
+            assert_eq!(Some("Blank.*"), function.regex().map(Regex::as_str));
+            assert!(Function::Checksum("Blank.esp".into(), 1).regex().is_none());
+        }
+
+        #[test]
+        fn function_version_argument_should_return_the_version_argument_if_there_is_one() {
+            let function = Function::ProductVersion(
+                "Blank.exe".into(),
+                "1.2.3".into(),
+                ComparisonOperator::GreaterThan,
+            );
+
+            assert_eq!(
+                Some(("1.2.3", ComparisonOperator::GreaterThan)),
+                function.version_argument()
+            );
+            assert!(Function::FilePath("Blank.esp".into())
+                .version_argument()
+                .is_none());
+        }
+
+        #[test]
+        fn function_accesses_filesystem_should_be_false_for_active_plugin_and_load_order_functions()
+        {
+            assert!(!Function::ActivePath("Blank.esp".into()).accesses_filesystem());
+            assert!(!Function::ManyActive(regex("Blank.*")).accesses_filesystem());
+            assert!(
+                !Function::LoadAfter("Blank.esp".into(), "Blank.esm".into()).accesses_filesystem()
+            );
+            assert!(
+                !Function::LoadIndex("Blank.esp".into(), 1, ComparisonOperator::Equal)
+                    .accesses_filesystem()
+            );
+            assert!(Function::FilePath("Blank.esp".into()).accesses_filesystem());
+            assert!(Function::IsMaster("Blank.esp".into()).accesses_filesystem());
+        }
+    }
+
     mod fmt {
         use super::*;
 
@@ -202,6 +570,20 @@ mod tests {
             assert_eq!("file(\"subdir/Blank.*\")", &format!("{function}"));
         }
 
+        #[test]
+        fn function_fmt_for_file_regex_should_remove_regex_anchors() {
+            let function = Function::FileRegex("subdir".into(), regex("^Blank.*$"));
+
+            assert_eq!("file(\"subdir/Blank.*\")", &format!("{function}"));
+        }
+
+        #[test]
+        fn function_fmt_for_file_regex_should_omit_a_parent_path_of_dot() {
+            let function = Function::FileRegex(".".into(), regex("^Blank.*$"));
+
+            assert_eq!("file(\"Blank.*\")", &format!("{function}"));
+        }
+
         #[test]
         fn function_fmt_for_file_size_should_format_correctly() {
             let function = Function::FileSize("subdir/Blank.esm".into(), 12_345_678);
@@ -257,6 +639,13 @@ mod tests {
             assert_eq!("many(\"subdir/Blank.*\")", &format!("{function}"));
         }
 
+        #[test]
+        fn function_fmt_for_many_active_should_remove_regex_anchors() {
+            let function = Function::ManyActive(regex("^Blank.*$"));
+
+            assert_eq!("many_active(\"Blank.*\")", &format!("{function}"));
+        }
+
         #[test]
         fn function_fmt_for_many_active_should_format_correctly() {
             let function = Function::ManyActive(regex("Blank.*"));
@@ -353,6 +742,14 @@ mod tests {
             );
         }
 
+        #[test]
+        fn function_eq_for_file_path_should_not_treat_backslashes_and_forward_slashes_as_equal() {
+            assert_ne!(
+                Function::FilePath("..\\subdir/Blank.esm".into()),
+                Function::FilePath("../subdir\\Blank.esm".into())
+            );
+        }
+
         #[test]
         fn function_eq_for_file_regex_should_check_pathbuf_and_regex() {
             assert_eq!(
@@ -2,20 +2,20 @@ use std::path::PathBuf;
 use std::str;
 
 use nom::branch::alt;
-use nom::bytes::complete::{is_not, tag};
+use nom::bytes::complete::{is_not, tag, take_while1};
 use nom::character::complete::digit1;
 use nom::character::complete::hex_digit1;
-use nom::combinator::{map, map_parser, value};
-use nom::sequence::delimited;
-use nom::{Err, IResult, Parser};
+use nom::combinator::{map, map_parser, map_res, opt, recognize, value};
+use nom::error::{ErrorKind, ParseError};
+use nom::{Err, IResult, Offset, Parser};
 use regex::{Regex, RegexBuilder};
 
 use super::{ComparisonOperator, Function};
-use crate::error::ParsingErrorKind;
-use crate::{map_err, whitespace, ParsingResult};
+use crate::error::{FunctionArgument, ParsingError, ParsingErrorKind};
+use crate::{fail_with, map_err, whitespace, FunctionSpan, ParsingResult, Span};
 
 impl ComparisonOperator {
-    pub fn parse(input: &str) -> IResult<&str, ComparisonOperator> {
+    pub(crate) fn parse(input: &str) -> IResult<&str, ComparisonOperator> {
         alt((
             value(ComparisonOperator::Equal, tag("==")),
             value(ComparisonOperator::NotEqual, tag("!=")),
@@ -32,6 +32,85 @@ const INVALID_PATH_CHARS: &str = "\":*?<>|";
 const INVALID_NON_REGEX_PATH_CHARS: &str = "\":*?<>|\\"; // \ is treated as invalid to distinguish regex strings.
 const INVALID_REGEX_PATH_CHARS: &str = "\"<>";
 
+/// The names of the functions that can be parsed, for suggesting a name when
+/// an unknown function is called.
+const FUNCTION_NAMES: &[&str] = &[
+    "file",
+    "file_size",
+    "readable",
+    "is_executable",
+    "active",
+    "is_master",
+    "many",
+    "many_active",
+    "checksum",
+    "version",
+    "product_version",
+    "filename_version",
+    "description_contains",
+    "archive_contains",
+    "has_master",
+    "is_light",
+    "is_medium",
+    "is_override",
+    "record_count",
+    "override_record_count",
+    "header_version",
+    "load_after",
+    "load_index",
+];
+
+/// Keywords are parsed as part of the expression that a function appears in,
+/// and may be directly followed by a function name, so neither a keyword nor
+/// a keyword followed by a function name is an unknown function.
+const KEYWORDS: &[&str] = &["and", "or", "not"];
+
+fn starts_with_keyword(name: &str) -> bool {
+    KEYWORDS.iter().any(|keyword| {
+        name.strip_prefix(keyword)
+            .is_some_and(|rest| rest.is_empty() || FUNCTION_NAMES.contains(&rest))
+    })
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Suggests the known function name that is most similar to the given name,
+/// if any is similar enough.
+fn suggest(name: &str) -> Option<&'static str> {
+    FUNCTION_NAMES
+        .iter()
+        .map(|n| (edit_distance(name, n), *n))
+        .filter(|(distance, n)| *distance * 2 <= name.len().max(n.len()))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, n)| n)
+}
+
+/// The Levenshtein distance between the two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous_row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut row = vec![i + 1];
+        for (b_char, costs) in b.iter().zip(previous_row.windows(2)) {
+            let [substitution, deletion] =
+                [costs.first(), costs.last()].map(|c| c.copied().unwrap_or_default());
+            let insertion = row.last().copied().unwrap_or_default();
+
+            row.push(
+                (substitution + usize::from(a_char != *b_char))
+                    .min(deletion + 1)
+                    .min(insertion + 1),
+            );
+        }
+        previous_row = row;
+    }
+
+    previous_row.last().copied().unwrap_or_default()
+}
+
 fn build_regex(input: &str) -> Result<(&'static str, Regex), regex::Error> {
     RegexBuilder::new(input)
         .case_insensitive(true)
@@ -40,147 +119,498 @@ fn build_regex(input: &str) -> Result<(&'static str, Regex), regex::Error> {
 }
 
 fn parse_regex(input: &str) -> ParsingResult<'_, Regex> {
-    build_regex(input).map_err(|e| Err::Failure(ParsingErrorKind::from(e).at(input)))
+    build_regex(input).map_err(|e| Err::Failure(ParsingErrorKind::from(e).spanning(input)))
 }
 
 fn parse_anchored_regex(input: &str) -> ParsingResult<'_, Regex> {
     build_regex(&format!("^{input}$"))
-        .map_err(|e| Err::Failure(ParsingErrorKind::from(e).at(input)))
+        .map_err(|e| Err::Failure(ParsingErrorKind::from(e).spanning(input)))
 }
 
-fn parse_path(input: &str) -> IResult<&str, PathBuf> {
-    map(
-        delimited(tag("\""), is_not(INVALID_PATH_CHARS), tag("\"")),
-        PathBuf::from,
-    )
-    .parse(input)
+pub(super) fn parse_unquoted_path(input: &str) -> IResult<&str, PathBuf> {
+    map(is_not(INVALID_PATH_CHARS), PathBuf::from).parse(input)
+}
+
+fn parse_path(input: &str) -> ParsingResult<'_, PathBuf> {
+    parse_quoted(map_err(parse_unquoted_path)).parse(input)
 }
 
 fn parse_size(input: &str) -> ParsingResult<'_, u64> {
     str::parse(input)
         .map(|c| ("", c))
-        .map_err(|e| Err::Failure(ParsingErrorKind::from(e).at(input)))
+        .map_err(|e| Err::Failure(ParsingErrorKind::from(e).spanning(input)))
 }
 
-fn parse_file_size_args(input: &str) -> ParsingResult<'_, (PathBuf, u64)> {
-    let mut parser = (
-        map_err(parse_path),
-        map_err(whitespace(tag(","))),
-        map_parser(digit1, parse_size),
-    );
+fn parse_count(input: &str) -> ParsingResult<'_, u32> {
+    str::parse(input)
+        .map(|c| ("", c))
+        .map_err(|e| Err::Failure(ParsingErrorKind::from(e).spanning(input)))
+}
 
-    let (remaining_input, (path, _, size)) = parser.parse(input)?;
+fn parse_count_args<'a>(
+    function: &'static str,
+    count: FunctionArgument,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, (PathBuf, u32, ComparisonOperator)> {
+    move |input| {
+        let (remaining_input, (path, (comparator, count))) = (
+            expect_argument(
+                function,
+                FunctionArgument::Path,
+                None,
+                parse_quoted(parse_non_regex_path),
+            ),
+            parse_either_order(
+                function,
+                FunctionArgument::Path,
+                (
+                    FunctionArgument::Comparator,
+                    map_err(ComparisonOperator::parse),
+                ),
+                (count, map_parser(digit1, parse_count)),
+            ),
+        )
+            .parse(input)?;
 
-    Ok((remaining_input, (path, size)))
+        Ok((remaining_input, (path, count, comparator)))
+    }
 }
 
-fn parse_version(input: &str) -> IResult<&str, String> {
-    map(
-        delimited(tag("\""), is_not("\""), tag("\"")),
-        |version: &str| version.to_owned(),
-    )
-    .parse(input)
+/// Parses a plugin header version, which is a decimal number like `1.7`.
+pub(super) fn parse_header_version(input: &str) -> IResult<&str, f32> {
+    map_res(recognize((digit1, opt((tag("."), digit1)))), str::parse).parse(input)
 }
 
-fn parse_version_args(input: &str) -> ParsingResult<'_, (PathBuf, String, ComparisonOperator)> {
-    let parser = alt((
-        map(
+fn parse_header_version_args(input: &str) -> ParsingResult<'_, (PathBuf, f32, ComparisonOperator)> {
+    let function = "header_version";
+
+    let (remaining_input, (path, (comparator, version))) = (
+        expect_argument(
+            function,
+            FunctionArgument::Path,
+            None,
+            parse_quoted(parse_non_regex_path),
+        ),
+        parse_either_order(
+            function,
+            FunctionArgument::Path,
             (
-                parse_path,
-                whitespace(tag(",")),
-                parse_version,
-                whitespace(tag(",")),
-                ComparisonOperator::parse,
+                FunctionArgument::Comparator,
+                map_err(ComparisonOperator::parse),
             ),
-            |(path, _, version, _, comparator)| (path, version, comparator),
-        ),
-        map(
             (
-                parse_path,
-                whitespace(tag(",")),
-                ComparisonOperator::parse,
-                whitespace(tag(",")),
-                parse_version,
+                FunctionArgument::HeaderVersion,
+                map_err(parse_header_version),
             ),
-            |(path, _, comparator, _, version)| (path, version, comparator),
         ),
-    ));
+    )
+        .parse(input)?;
 
-    map_err(parser).parse(input)
+    Ok((remaining_input, (path, version, comparator)))
+}
+
+fn parse_file_size_args(input: &str) -> ParsingResult<'_, (PathBuf, u64)> {
+    let function = "file_size";
+
+    (
+        expect_argument(function, FunctionArgument::Path, None, parse_path),
+        expect_argument(
+            function,
+            FunctionArgument::FileSize,
+            Some(FunctionArgument::Path),
+            map_parser(digit1, parse_size),
+        ),
+    )
+        .parse(input)
+}
+
+pub(super) fn parse_unquoted_version(input: &str) -> IResult<&str, String> {
+    map(is_not("\""), |version: &str| version.to_owned()).parse(input)
+}
+
+fn parse_version(input: &str) -> ParsingResult<'_, String> {
+    parse_quoted(map_err(parse_unquoted_version)).parse(input)
+}
+
+fn parse_version_args<'a>(
+    function: &'static str,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, (PathBuf, String, ComparisonOperator)> {
+    move |input| {
+        let (remaining_input, (path, (version, comparator))) = (
+            expect_argument(function, FunctionArgument::Path, None, parse_path),
+            parse_either_order(
+                function,
+                FunctionArgument::Path,
+                (FunctionArgument::Version, parse_version),
+                (
+                    FunctionArgument::Comparator,
+                    map_err(ComparisonOperator::parse),
+                ),
+            ),
+        )
+            .parse(input)?;
+
+        Ok((remaining_input, (path, version, comparator)))
+    }
 }
 
 fn parse_filename_version_args(
     input: &str,
 ) -> ParsingResult<'_, (PathBuf, Regex, String, ComparisonOperator)> {
-    let mut parser = alt((
-        map(
-            (
-                delimited(map_err(tag("\"")), parse_regex_path, map_err(tag("\""))),
-                map_err(whitespace(tag(","))),
-                map_err(parse_version),
-                map_err(whitespace(tag(","))),
-                map_err(ComparisonOperator::parse),
-            ),
-            |((path, regex), _, version, _, comparator)| (path, regex, version, comparator),
+    let function = "filename_version";
+
+    let (remaining_input, ((path, regex), (version, comparator))) = (
+        expect_argument(
+            function,
+            FunctionArgument::Path,
+            None,
+            parse_quoted(parse_filename_version_path),
         ),
-        map(
+        parse_either_order(
+            function,
+            FunctionArgument::Path,
+            (FunctionArgument::Version, parse_version),
             (
-                delimited(map_err(tag("\"")), parse_regex_path, map_err(tag("\""))),
-                map_err(whitespace(tag(","))),
+                FunctionArgument::Comparator,
                 map_err(ComparisonOperator::parse),
-                map_err(whitespace(tag(","))),
-                map_err(parse_version),
             ),
-            |((path, regex), _, comparator, _, version)| (path, regex, version, comparator),
         ),
-    ));
+    )
+        .parse(input)?;
 
-    let (remaining_input, (path, regex, version, comparator)) = parser.parse(input)?;
+    Ok((remaining_input, (path, regex, version, comparator)))
+}
+
+/// Parse a regex path that has one capture group, for capturing the version
+/// from a filename.
+pub(super) fn parse_filename_version_path(input: &str) -> ParsingResult<'_, (PathBuf, Regex)> {
+    let (remaining_input, (path, regex)) = parse_regex_path(input)?;
 
     if regex.captures_len() != 2 {
         return Err(Err::Failure(
-            ParsingErrorKind::InvalidRegexUnknown.at(input),
+            ParsingErrorKind::InvalidRegexUnknown
+                .at(input)
+                .with_length(input.offset(remaining_input)),
         ));
     }
 
-    Ok((remaining_input, (path, regex, version, comparator)))
+    Ok((remaining_input, (path, regex)))
+}
+
+pub(super) fn parse_unquoted_regex(input: &str) -> ParsingResult<'_, Regex> {
+    map_parser(is_not("\""), parse_regex).parse(input)
 }
 
 fn parse_description_contains_args(input: &str) -> ParsingResult<'_, (PathBuf, Regex)> {
-    let mut parser = (
-        map_err(parse_path),
-        map_err(whitespace(tag(","))),
-        delimited(
-            map_err(tag("\"")),
-            map_parser(is_not("\""), parse_regex),
-            map_err(tag("\"")),
+    let function = "description_contains";
+
+    (
+        expect_argument(function, FunctionArgument::Path, None, parse_path),
+        expect_argument(
+            function,
+            FunctionArgument::Regex,
+            Some(FunctionArgument::Path),
+            parse_quoted(parse_unquoted_regex),
         ),
-    );
+    )
+        .parse(input)
+}
 
-    let (remaining_input, (path, _, regex)) = parser.parse(input)?;
+/// Parses a quoted string using the given parser, which must parse all of the
+/// string's content.
+fn parse_quoted<'a, O>(
+    mut parser: impl Parser<&'a str, Output = O, Error = ParsingError<&'a str>>,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, O> {
+    move |input| {
+        let (content, _) = map_err(tag("\"")).parse(input)?;
+
+        if !content.contains('"') {
+            return Err(Err::Failure(
+                ParsingErrorKind::UnterminatedString
+                    .at(input)
+                    .with_length(1),
+            ));
+        }
 
-    Ok((remaining_input, (path, regex)))
+        let (remaining_input, output) = parser.parse(content)?;
+        let (remaining_input, _) = map_err(tag("\"")).parse(remaining_input)?;
+
+        Ok((remaining_input, output))
+    }
+}
+
+/// Parses the arguments of a call to the named function, including the
+/// parentheses around them.
+fn parse_arguments<'a, O>(
+    function: &'static str,
+    mut parser: impl Parser<&'a str, Output = O, Error = ParsingError<&'a str>>,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, O> {
+    move |input| {
+        let (arguments_input, _) = map_err(tag("(")).parse(input).map_err(fail_with(|| {
+            ParsingErrorKind::ExpectedOpeningParenthesis(function)
+                .at(input)
+                .with_length(0)
+        }))?;
+
+        let (remaining_input, output) = parser.parse(arguments_input)?;
+
+        match map_err(tag(")")).parse(remaining_input) {
+            Ok((remaining_input, _)) => Ok((remaining_input, output)),
+            Err(Err::Error(e)) => {
+                let trimmed_input = remaining_input.trim_start();
+
+                let error = if trimmed_input.is_empty() {
+                    ParsingErrorKind::UnbalancedParenthesis
+                        .at(input)
+                        .with_length(1)
+                } else if let Some(argument_input) = trimmed_input.strip_prefix(',') {
+                    ParsingErrorKind::UnexpectedArgument(function)
+                        .spanning(argument_token(argument_input))
+                } else if let Some(c) = remaining_input.chars().next() {
+                    unexpected_character(function, c, remaining_input)
+                } else {
+                    return Err(Err::Error(e));
+                };
+
+                Err(Err::Failure(error))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn parse_comma(input: &str) -> ParsingResult<'_, ()> {
+    value((), map_err(whitespace(tag(",")))).parse(input)
+}
+
+/// The input that an error about an argument relates to: a quoted string, or
+/// everything up to the next comma or closing parenthesis.
+fn argument_token(input: &str) -> &str {
+    let input = input.trim_start();
+
+    let length = match input.strip_prefix('"') {
+        Some(content) => content.find('"').map_or(input.len(), |i| i + 2),
+        None => input.find([',', ')']).unwrap_or(input.len()),
+    };
+
+    input.get(..length).unwrap_or(input).trim_end()
+}
+
+/// Gets the spans of a function call that was parsed from `call`, which must
+/// be a subslice of `source`.
+pub(crate) fn function_span(source: &str, call: &str) -> FunctionSpan {
+    let name_length = call.find(|c| !is_identifier_char(c)).unwrap_or(call.len());
+    let (name, arguments_input) = call.split_at(name_length);
+
+    let mut arguments = Vec::new();
+    let mut input = arguments_input.strip_prefix('(').unwrap_or_default();
+    loop {
+        let argument = argument_token(input);
+        if argument.is_empty() {
+            break;
+        }
+        arguments.push(Span::of(source, argument));
+
+        let remaining_input = input
+            .get(input.offset(argument) + argument.len()..)
+            .unwrap_or_default();
+        match parse_comma(remaining_input) {
+            Ok((next_input, ())) => input = next_input,
+            Err(_) => break,
+        }
+    }
+
+    FunctionSpan::new(Span::of(source, call), Span::of(source, name), arguments)
+}
+
+fn argument_error<'a>(
+    function: &'static str,
+    expected: FunctionArgument,
+    after: Option<FunctionArgument>,
+    input: &'a str,
+) -> ParsingError<&'a str> {
+    let argument_input = match after {
+        Some(_) => parse_comma(input).map_or(input, |(i, ())| i),
+        None => input,
+    };
+
+    // Whitespace is skipped after commas but not after the opening
+    // parenthesis, so it's the whitespace that's unexpected, not the argument.
+    if let Some(c) = argument_input.chars().next().filter(|c| c.is_whitespace()) {
+        return unexpected_character(function, c, argument_input);
+    }
+
+    ParsingErrorKind::ExpectedArgument {
+        function,
+        expected,
+        after,
+    }
+    .spanning(argument_token(argument_input))
+}
+
+fn unexpected_character<'a>(
+    function: &'static str,
+    c: char,
+    input: &'a str,
+) -> ParsingError<&'a str> {
+    ParsingErrorKind::UnexpectedCharacter(function, c)
+        .at(input)
+        .with_length(c.len_utf8())
+}
+
+/// Parses an argument of a call to the named function, preceded by a comma if
+/// it comes after another argument. If the argument can't be parsed, the
+/// failure says which argument was expected.
+fn expect_argument<'a, O>(
+    function: &'static str,
+    expected: FunctionArgument,
+    after: Option<FunctionArgument>,
+    mut parser: impl Parser<&'a str, Output = O, Error = ParsingError<&'a str>>,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, O> {
+    move |input| {
+        let result = match after {
+            Some(_) => parse_comma(input).and_then(|(i, ())| parser.parse(i)),
+            None => parser.parse(input),
+        };
+
+        result.map_err(fail_with(|| {
+            argument_error(function, expected, after, input)
+        }))
+    }
+}
+
+/// Parses two arguments that may be given in either order, after the given
+/// argument. If neither can be parsed first, the first order is expected.
+fn parse_either_order<'a, A, B>(
+    function: &'static str,
+    after: FunctionArgument,
+    (first, mut parse_first): (
+        FunctionArgument,
+        impl Parser<&'a str, Output = A, Error = ParsingError<&'a str>>,
+    ),
+    (second, mut parse_second): (
+        FunctionArgument,
+        impl Parser<&'a str, Output = B, Error = ParsingError<&'a str>>,
+    ),
+) -> impl FnMut(&'a str) -> ParsingResult<'a, (A, B)> {
+    move |input| {
+        match parse_comma(input).and_then(|(i, ())| parse_first.parse(i)) {
+            Ok((remaining_input, a)) => {
+                let (remaining_input, b) =
+                    expect_argument(function, second, Some(first), |i| parse_second.parse(i))
+                        .parse(remaining_input)?;
+                return Ok((remaining_input, (a, b)));
+            }
+            Err(Err::Error(_)) => {}
+            Err(e) => return Err(e),
+        }
+
+        match parse_comma(input).and_then(|(i, ())| parse_second.parse(i)) {
+            Ok((remaining_input, b)) => {
+                let (remaining_input, a) =
+                    expect_argument(function, first, Some(second), |i| parse_first.parse(i))
+                        .parse(remaining_input)?;
+                Ok((remaining_input, (a, b)))
+            }
+            Err(Err::Error(_)) => Err(Err::Failure(argument_error(
+                function,
+                first,
+                Some(after),
+                input,
+            ))),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn parse_archive_contains_args(input: &str) -> ParsingResult<'_, Function> {
+    let function = "archive_contains";
+
+    let (remaining_input, (archive_path, (path, regex))) = (
+        expect_argument(
+            function,
+            FunctionArgument::Path,
+            None,
+            parse_quoted(parse_non_regex_path),
+        ),
+        expect_argument(
+            function,
+            FunctionArgument::Path,
+            Some(FunctionArgument::Path),
+            alt((
+                map(parse_quoted(parse_non_regex_path), |path| (path, None)),
+                map(parse_quoted(parse_regex_path), |(path, regex)| {
+                    (path, Some(regex))
+                }),
+            )),
+        ),
+    )
+        .parse(input)?;
+
+    let function = match regex {
+        Some(regex) => Function::ArchiveContainsRegex(archive_path, path, regex),
+        None => Function::ArchiveContains(archive_path, path),
+    };
+
+    Ok((remaining_input, function))
+}
+
+fn parse_two_plugin_args<'a>(
+    function: &'static str,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, (PathBuf, PathBuf)> {
+    move |input| {
+        (
+            expect_argument(
+                function,
+                FunctionArgument::Path,
+                None,
+                parse_quoted(parse_non_regex_path),
+            ),
+            expect_argument(
+                function,
+                FunctionArgument::Path,
+                Some(FunctionArgument::Path),
+                parse_quoted(parse_non_regex_path),
+            ),
+        )
+            .parse(input)
+    }
 }
 
 fn parse_crc(input: &str) -> ParsingResult<'_, u32> {
     u32::from_str_radix(input, 16)
         .map(|c| ("", c))
-        .map_err(|e| Err::Failure(ParsingErrorKind::from(e).at(input)))
+        .map_err(|e| Err::Failure(ParsingErrorKind::from(e).spanning(input)))
 }
 
 fn parse_checksum_args(input: &str) -> ParsingResult<'_, (PathBuf, u32)> {
-    let mut parser = (
-        map_err(parse_path),
-        map_err(whitespace(tag(","))),
-        map_parser(hex_digit1, parse_crc),
-    );
-
-    let (remaining_input, (path, _, crc)) = parser.parse(input)?;
+    let function = "checksum";
+
+    (
+        expect_argument(function, FunctionArgument::Path, None, parse_path),
+        expect_argument(
+            function,
+            FunctionArgument::Crc,
+            Some(FunctionArgument::Path),
+            map_parser(hex_digit1, parse_crc),
+        ),
+    )
+        .parse(input)
+}
 
-    Ok((remaining_input, (path, crc)))
+/// Parses the only argument of a call to the named function, which is a path
+/// that is not a regex.
+fn parse_non_regex_path_arg<'a>(
+    function: &'static str,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, PathBuf> {
+    expect_argument(
+        function,
+        FunctionArgument::Path,
+        None,
+        parse_quoted(parse_non_regex_path),
+    )
 }
 
-fn parse_non_regex_path(input: &str) -> ParsingResult<'_, PathBuf> {
+pub(super) fn parse_non_regex_path(input: &str) -> ParsingResult<'_, PathBuf> {
     let (remaining_input, path) = map(is_not(INVALID_NON_REGEX_PATH_CHARS), |path: &str| {
         PathBuf::from(path)
     })
@@ -191,12 +621,14 @@ fn parse_non_regex_path(input: &str) -> ParsingResult<'_, PathBuf> {
 
 /// Parse a string that is a path where the last component is a regex string
 /// that may contain characters that are invalid in paths but valid in regex.
-fn parse_regex_path(input: &str) -> ParsingResult<'_, (PathBuf, Regex)> {
+pub(super) fn parse_regex_path(input: &str) -> ParsingResult<'_, (PathBuf, Regex)> {
     let (remaining_input, string) = is_not(INVALID_REGEX_PATH_CHARS)(input)?;
 
     if string.ends_with('/') {
         return Err(Err::Failure(
-            ParsingErrorKind::PathEndsInADirectorySeparator(string.into()).at(input),
+            ParsingErrorKind::PathEndsInADirectorySeparator(string.into())
+                .at(input)
+                .with_length(string.len()),
         ));
     }
 
@@ -209,138 +641,184 @@ fn parse_regex_path(input: &str) -> ParsingResult<'_, (PathBuf, Regex)> {
     Ok((remaining_input, (parent_path, regex)))
 }
 
-fn parse_regex_filename(input: &str) -> ParsingResult<'_, Regex> {
+pub(super) fn parse_regex_filename(input: &str) -> ParsingResult<'_, Regex> {
     map_parser(is_not(INVALID_REGEX_PATH_CHARS), parse_anchored_regex).parse(input)
 }
 
 impl Function {
+    /// Parses a function call. Input that starts with a name that isn't a
+    /// function or keyword, or that calls a function with the wrong arguments,
+    /// is a failure that describes the problem.
     #[expect(clippy::too_many_lines)]
-    pub fn parse(input: &str) -> ParsingResult<'_, Function> {
-        alt((
-            map(
-                delimited(
-                    map_err(tag("file(\"")),
-                    parse_non_regex_path,
-                    map_err(tag("\")")),
-                ),
-                Function::FilePath,
-            ),
-            map(
-                delimited(
-                    map_err(tag("file(\"")),
-                    parse_regex_path,
-                    map_err(tag("\")")),
-                ),
-                |(path, regex)| Function::FileRegex(path, regex),
-            ),
-            map(
-                delimited(
-                    map_err(tag("file_size(")),
-                    parse_file_size_args,
-                    map_err(tag(")")),
+    pub(crate) fn parse(input: &str) -> ParsingResult<'_, Function> {
+        let (arguments_input, name) = map_err(take_while1(is_identifier_char)).parse(input)?;
+
+        match name {
+            "file" => parse_arguments(
+                "file",
+                expect_argument(
+                    "file",
+                    FunctionArgument::Path,
+                    None,
+                    alt((
+                        map(parse_quoted(parse_non_regex_path), Function::FilePath),
+                        map(parse_quoted(parse_regex_path), |(path, regex)| {
+                            Function::FileRegex(path, regex)
+                        }),
+                    )),
                 ),
+            )
+            .parse(arguments_input),
+            "file_size" => map(
+                parse_arguments("file_size", parse_file_size_args),
                 |(path, size)| Function::FileSize(path, size),
-            ),
-            map(
-                delimited(
-                    map_err(tag("readable(\"")),
-                    parse_non_regex_path,
-                    map_err(tag("\")")),
-                ),
+            )
+            .parse(arguments_input),
+            "readable" => map(
+                parse_arguments("readable", parse_non_regex_path_arg("readable")),
                 Function::Readable,
-            ),
-            map(
-                delimited(
-                    map_err(tag("is_executable(\"")),
-                    parse_non_regex_path,
-                    map_err(tag("\")")),
-                ),
+            )
+            .parse(arguments_input),
+            "is_executable" => map(
+                parse_arguments("is_executable", parse_non_regex_path_arg("is_executable")),
                 Function::IsExecutable,
-            ),
-            map(
-                delimited(
-                    map_err(tag("active(\"")),
-                    parse_non_regex_path,
-                    map_err(tag("\")")),
-                ),
-                Function::ActivePath,
-            ),
-            map(
-                delimited(
-                    map_err(tag("active(\"")),
-                    parse_regex_filename,
-                    map_err(tag("\")")),
-                ),
-                Function::ActiveRegex,
-            ),
-            map(
-                delimited(
-                    map_err(tag("is_master(\"")),
-                    parse_non_regex_path,
-                    map_err(tag("\")")),
+            )
+            .parse(arguments_input),
+            "active" => parse_arguments(
+                "active",
+                expect_argument(
+                    "active",
+                    FunctionArgument::Path,
+                    None,
+                    alt((
+                        map(parse_quoted(parse_non_regex_path), Function::ActivePath),
+                        map(parse_quoted(parse_regex_filename), Function::ActiveRegex),
+                    )),
                 ),
+            )
+            .parse(arguments_input),
+            "is_master" => map(
+                parse_arguments("is_master", parse_non_regex_path_arg("is_master")),
                 Function::IsMaster,
-            ),
-            map(
-                delimited(
-                    map_err(tag("many(\"")),
-                    parse_regex_path,
-                    map_err(tag("\")")),
+            )
+            .parse(arguments_input),
+            "many" => map(
+                parse_arguments(
+                    "many",
+                    expect_argument(
+                        "many",
+                        FunctionArgument::Path,
+                        None,
+                        parse_quoted(parse_regex_path),
+                    ),
                 ),
                 |(path, regex)| Function::Many(path, regex),
-            ),
-            map(
-                delimited(
-                    map_err(tag("many_active(\"")),
-                    parse_regex_filename,
-                    map_err(tag("\")")),
+            )
+            .parse(arguments_input),
+            "many_active" => map(
+                parse_arguments(
+                    "many_active",
+                    expect_argument(
+                        "many_active",
+                        FunctionArgument::Regex,
+                        None,
+                        parse_quoted(parse_regex_filename),
+                    ),
                 ),
                 Function::ManyActive,
-            ),
-            map(
-                delimited(
-                    map_err(tag("version(")),
-                    parse_version_args,
-                    map_err(tag(")")),
-                ),
+            )
+            .parse(arguments_input),
+            "checksum" => map(
+                parse_arguments("checksum", parse_checksum_args),
+                |(path, crc)| Function::Checksum(path, crc),
+            )
+            .parse(arguments_input),
+            "version" => map(
+                parse_arguments("version", parse_version_args("version")),
                 |(path, version, comparator)| Function::Version(path, version, comparator),
-            ),
-            map(
-                delimited(
-                    map_err(tag("product_version(")),
-                    parse_version_args,
-                    map_err(tag(")")),
-                ),
+            )
+            .parse(arguments_input),
+            "product_version" => map(
+                parse_arguments("product_version", parse_version_args("product_version")),
                 |(path, version, comparator)| Function::ProductVersion(path, version, comparator),
-            ),
-            map(
-                delimited(
-                    map_err(tag("filename_version(")),
-                    parse_filename_version_args,
-                    map_err(tag(")")),
-                ),
+            )
+            .parse(arguments_input),
+            "filename_version" => map(
+                parse_arguments("filename_version", parse_filename_version_args),
                 |(path, regex, version, comparator)| {
                     Function::FilenameVersion(path, regex, version, comparator)
                 },
-            ),
-            map(
-                delimited(
-                    map_err(tag("checksum(")),
-                    parse_checksum_args,
-                    map_err(tag(")")),
+            )
+            .parse(arguments_input),
+            "description_contains" => map(
+                parse_arguments("description_contains", parse_description_contains_args),
+                |(path, regex)| Function::DescriptionContains(path, regex),
+            )
+            .parse(arguments_input),
+            "archive_contains" => parse_arguments("archive_contains", parse_archive_contains_args)
+                .parse(arguments_input),
+            "has_master" => map(
+                parse_arguments("has_master", parse_two_plugin_args("has_master")),
+                |(path, master)| Function::HasMaster(path, master),
+            )
+            .parse(arguments_input),
+            "is_light" => map(
+                parse_arguments("is_light", parse_non_regex_path_arg("is_light")),
+                Function::IsLight,
+            )
+            .parse(arguments_input),
+            "is_medium" => map(
+                parse_arguments("is_medium", parse_non_regex_path_arg("is_medium")),
+                Function::IsMedium,
+            )
+            .parse(arguments_input),
+            "is_override" => map(
+                parse_arguments("is_override", parse_non_regex_path_arg("is_override")),
+                Function::IsOverride,
+            )
+            .parse(arguments_input),
+            "record_count" => map(
+                parse_arguments(
+                    "record_count",
+                    parse_count_args("record_count", FunctionArgument::RecordCount),
                 ),
-                |(path, crc)| Function::Checksum(path, crc),
-            ),
-            map(
-                delimited(
-                    map_err(tag("description_contains(")),
-                    parse_description_contains_args,
-                    map_err(tag(")")),
+                |(path, count, comparator)| Function::RecordCount(path, count, comparator),
+            )
+            .parse(arguments_input),
+            "override_record_count" => map(
+                parse_arguments(
+                    "override_record_count",
+                    parse_count_args("override_record_count", FunctionArgument::RecordCount),
                 ),
-                |(path, regex)| Function::DescriptionContains(path, regex),
-            ),
-        ))
-        .parse(input)
+                |(path, count, comparator)| Function::OverrideRecordCount(path, count, comparator),
+            )
+            .parse(arguments_input),
+            "header_version" => map(
+                parse_arguments("header_version", parse_header_version_args),
+                |(path, version, comparator)| Function::HeaderVersion(path, version, comparator),
+            )
+            .parse(arguments_input),
+            "load_after" => map(
+                parse_arguments("load_after", parse_two_plugin_args("load_after")),
+                |(path, other_path)| Function::LoadAfter(path, other_path),
+            )
+            .parse(arguments_input),
+            "load_index" => map(
+                parse_arguments(
+                    "load_index",
+                    parse_count_args("load_index", FunctionArgument::LoadIndex),
+                ),
+                |(path, index, comparator)| Function::LoadIndex(path, index, comparator),
+            )
+            .parse(arguments_input),
+            _ if starts_with_keyword(name) => Err(Err::Error(ParsingError::from_error_kind(
+                input,
+                ErrorKind::Tag,
+            ))),
+            _ => Err(Err::Failure(
+                ParsingErrorKind::UnknownFunction(name.to_owned(), suggest(name)).spanning(name),
+            )),
+        }
     }
 }
 
@@ -769,4 +1247,366 @@ mod tests {
             _ => panic!("Expected a description_contains function"),
         }
     }
+
+    #[test]
+    fn function_parse_should_parse_an_archive_contains_function_with_a_path() {
+        let output =
+            Function::parse("archive_contains(\"Blank.bsa\", \"meshes/Blank.nif\")").unwrap();
+
+        assert!(output.0.is_empty());
+        match output.1 {
+            Function::ArchiveContains(a, p) => {
+                assert_eq!(PathBuf::from("Blank.bsa"), a);
+                assert_eq!(PathBuf::from("meshes/Blank.nif"), p);
+            }
+            _ => panic!("Expected an archive_contains function"),
+        }
+    }
+
+    #[test]
+    fn function_parse_should_parse_an_archive_contains_function_with_a_regex_path() {
+        let output =
+            Function::parse("archive_contains(\"Blank - Main.ba2\", \"meshes/Blank.*\")").unwrap();
+
+        assert!(output.0.is_empty());
+        match output.1 {
+            Function::ArchiveContainsRegex(a, p, r) => {
+                assert_eq!(PathBuf::from("Blank - Main.ba2"), a);
+                assert_eq!(PathBuf::from("meshes"), p);
+                assert_eq!(Regex::new("^Blank.*$").unwrap().as_str(), r.as_str());
+            }
+            _ => panic!("Expected an archive_contains function with a regex"),
+        }
+    }
+
+    #[test]
+    fn function_parse_should_parse_a_has_master_function() {
+        let output = Function::parse("has_master(\"Blank.esp\", \"Blank.esm\")").unwrap();
+
+        assert!(output.0.is_empty());
+        match output.1 {
+            Function::HasMaster(p, m) => {
+                assert_eq!(PathBuf::from("Blank.esp"), p);
+                assert_eq!(PathBuf::from("Blank.esm"), m);
+            }
+            _ => panic!("Expected a has_master function"),
+        }
+    }
+
+    #[test]
+    fn function_parse_should_error_if_the_archive_contains_archive_path_is_a_regex() {
+        assert!(Function::parse("archive_contains(\"Blank.*\", \"meshes/Blank.nif\")").is_err());
+    }
+
+    #[test]
+    fn function_parse_should_parse_plugin_flag_functions() {
+        let (_, function) = Function::parse("is_light(\"Blank.esl\")").unwrap();
+        assert_eq!(Function::IsLight("Blank.esl".into()), function);
+
+        let (_, function) = Function::parse("is_medium(\"Blank.esm\")").unwrap();
+        assert_eq!(Function::IsMedium("Blank.esm".into()), function);
+
+        let (_, function) = Function::parse("is_override(\"Blank.esm\")").unwrap();
+        assert_eq!(Function::IsOverride("Blank.esm".into()), function);
+
+        assert!(Function::parse("is_light(\"Blank.es.*\")").is_err());
+    }
+
+    #[test]
+    fn function_parse_should_parse_record_count_functions_with_either_argument_order() {
+        let expected = Function::RecordCount(
+            "Blank.esp".into(),
+            100,
+            ComparisonOperator::GreaterThanOrEqual,
+        );
+
+        let (_, function) = Function::parse("record_count(\"Blank.esp\", >=, 100)").unwrap();
+        assert_eq!(expected, function);
+
+        let (_, function) = Function::parse("record_count(\"Blank.esp\", 100, >=)").unwrap();
+        assert_eq!(expected, function);
+
+        let (_, function) = Function::parse("override_record_count(\"Blank.esp\", ==, 0)").unwrap();
+        assert_eq!(
+            Function::OverrideRecordCount("Blank.esp".into(), 0, ComparisonOperator::Equal),
+            function
+        );
+    }
+
+    #[test]
+    fn function_parse_should_error_if_a_record_count_is_too_large() {
+        assert!(Function::parse("record_count(\"Blank.esp\", >=, 4294967296)").is_err());
+    }
+
+    #[test]
+    fn function_parse_should_parse_a_header_version_function_with_either_argument_order() {
+        let expected =
+            Function::HeaderVersion("Blank.esp".into(), 1.7, ComparisonOperator::LessThan);
+
+        let (_, function) = Function::parse("header_version(\"Blank.esp\", <, 1.7)").unwrap();
+        assert_eq!(expected, function);
+
+        let (_, function) = Function::parse("header_version(\"Blank.esp\", 1.7, <)").unwrap();
+        assert_eq!(expected, function);
+
+        let (_, function) = Function::parse("header_version(\"Blank.esp\", >=, 1)").unwrap();
+        assert_eq!(
+            Function::HeaderVersion(
+                "Blank.esp".into(),
+                1.0,
+                ComparisonOperator::GreaterThanOrEqual
+            ),
+            function
+        );
+
+        assert!(Function::parse("header_version(\"Blank.esp\", <, -1.7)").is_err());
+        assert!(Function::parse("header_version(\"Blank.esp\", <, \"1.7\")").is_err());
+    }
+
+    #[test]
+    fn function_parse_should_parse_load_order_functions() {
+        let (_, function) = Function::parse("load_after(\"Patch.esp\", \"Blank.esp\")").unwrap();
+        assert_eq!(
+            Function::LoadAfter("Patch.esp".into(), "Blank.esp".into()),
+            function
+        );
+
+        let expected = Function::LoadIndex("Blank.esp".into(), 10, ComparisonOperator::LessThan);
+
+        let (_, function) = Function::parse("load_index(\"Blank.esp\", <, 10)").unwrap();
+        assert_eq!(expected, function);
+
+        let (_, function) = Function::parse("load_index(\"Blank.esp\", 10, <)").unwrap();
+        assert_eq!(expected, function);
+    }
+
+    fn error_kind(input: &str) -> ParsingErrorKind {
+        match Function::parse(input) {
+            Err(e @ Err::Failure(_)) => match crate::Error::from_parsing_error(input, e) {
+                crate::Error::ParsingError(_, kind, _) => kind,
+                e => panic!("Expected a parsing error, got {e:?}"),
+            },
+            r => panic!("Expected a failure, got {r:?}"),
+        }
+    }
+
+    fn expected_argument(
+        function: &'static str,
+        expected: FunctionArgument,
+        after: Option<FunctionArgument>,
+    ) -> ParsingErrorKind {
+        ParsingErrorKind::ExpectedArgument {
+            function,
+            expected,
+            after,
+        }
+    }
+
+    #[test]
+    fn edit_distance_should_count_insertions_deletions_and_substitutions() {
+        assert_eq!(0, edit_distance("active", "active"));
+        assert_eq!(1, edit_distance("activ", "active"));
+        assert_eq!(1, edit_distance("actives", "active"));
+        assert_eq!(1, edit_distance("astive", "active"));
+        assert_eq!(4, edit_distance("is_activ", "active"));
+        assert_eq!(3, edit_distance("", "abc"));
+    }
+
+    #[test]
+    fn function_parse_should_know_every_function_that_it_suggests() {
+        for name in FUNCTION_NAMES {
+            assert!(
+                matches!(
+                    error_kind(&format!("{name}()")),
+                    ParsingErrorKind::ExpectedArgument { after: None, .. }
+                ),
+                "{name} is not a known function"
+            );
+        }
+    }
+
+    #[test]
+    fn function_parse_should_error_without_failing_on_a_keyword() {
+        assert!(matches!(
+            Function::parse("not file(\"Blank.esp\")"),
+            Err(Err::Error(_))
+        ));
+        assert!(matches!(Function::parse("or"), Err(Err::Error(_))));
+        assert!(matches!(
+            Function::parse("notfile(\"Blank.esp\")"),
+            Err(Err::Error(_))
+        ));
+    }
+
+    #[test]
+    fn function_parse_should_suggest_a_similar_function_name() {
+        assert_eq!(
+            ParsingErrorKind::UnknownFunction("is_activ".into(), Some("active")),
+            error_kind("is_activ(\"Blank.esp\")")
+        );
+        assert_eq!(
+            ParsingErrorKind::UnknownFunction("checksun".into(), Some("checksum")),
+            error_kind("checksun(\"Blank.esp\", DEADBEEF)")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_not_suggest_a_dissimilar_function_name() {
+        assert_eq!(
+            ParsingErrorKind::UnknownFunction("foo".into(), None),
+            error_kind("foo(\"Blank.esp\")")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_report_a_missing_argument() {
+        assert_eq!(
+            expected_argument(
+                "version",
+                FunctionArgument::Comparator,
+                Some(FunctionArgument::Version)
+            ),
+            error_kind("version(\"Blank.esp\", \"1.0\")")
+        );
+        assert_eq!(
+            expected_argument(
+                "checksum",
+                FunctionArgument::Crc,
+                Some(FunctionArgument::Path)
+            ),
+            error_kind("checksum(\"Blank.esp\")")
+        );
+        assert_eq!(
+            expected_argument("file", FunctionArgument::Path, None),
+            error_kind("file()")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_report_an_invalid_argument() {
+        assert_eq!(
+            expected_argument(
+                "version",
+                FunctionArgument::Comparator,
+                Some(FunctionArgument::Version)
+            ),
+            error_kind("version(\"Blank.esp\", \"1.0\", =)")
+        );
+        assert_eq!(
+            expected_argument(
+                "file_size",
+                FunctionArgument::FileSize,
+                Some(FunctionArgument::Path)
+            ),
+            error_kind("file_size(\"Blank.esp\", \"12\")")
+        );
+        assert_eq!(
+            expected_argument("readable", FunctionArgument::Path, None),
+            error_kind("readable(\"Blank\\.esp\")")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_expect_the_first_order_of_arguments_that_can_be_swapped() {
+        assert_eq!(
+            expected_argument(
+                "load_index",
+                FunctionArgument::Comparator,
+                Some(FunctionArgument::Path)
+            ),
+            error_kind("load_index(\"Blank.esp\", \"10\", <)")
+        );
+        assert_eq!(
+            expected_argument(
+                "load_index",
+                FunctionArgument::Comparator,
+                Some(FunctionArgument::LoadIndex)
+            ),
+            error_kind("load_index(\"Blank.esp\", 10)")
+        );
+    }
+
+    #[test]
+    fn function_parse_error_span_should_cover_the_invalid_argument() {
+        let input = "checksum(\"Blank.esp\", XYZ)";
+        let error = Function::parse(input).unwrap_err();
+
+        match crate::Error::from_parsing_error(input, error) {
+            crate::Error::ParsingError(_, _, span) => assert_eq!(22..25, span.range()),
+            e => panic!("Expected a parsing error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn function_parse_should_report_an_extra_argument() {
+        assert_eq!(
+            ParsingErrorKind::UnexpectedArgument("file"),
+            error_kind("file(\"Blank.esp\", \"Blank.esm\")")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_report_a_missing_opening_parenthesis() {
+        assert_eq!(
+            ParsingErrorKind::ExpectedOpeningParenthesis("active"),
+            error_kind("active \"Blank.esp\"")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_report_an_unterminated_string() {
+        assert_eq!(
+            ParsingErrorKind::UnterminatedString,
+            error_kind("checksum(\"Blank.esp, DEADBEEF)")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_report_whitespace_after_the_opening_parenthesis() {
+        assert_eq!(
+            ParsingErrorKind::UnexpectedCharacter("file", ' '),
+            error_kind("file( \"Blank.esp\")")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_report_an_unexpected_character_after_the_last_argument() {
+        assert_eq!(
+            ParsingErrorKind::UnexpectedCharacter("file", 'x'),
+            error_kind("file(\"Blank.esp\"x)")
+        );
+        assert_eq!(
+            ParsingErrorKind::UnexpectedCharacter("file", ' '),
+            error_kind("file(\"Blank.esp\" )")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_report_an_unclosed_parenthesis() {
+        assert_eq!(
+            ParsingErrorKind::UnbalancedParenthesis,
+            error_kind("file(\"Blank.esp\"")
+        );
+    }
+
+    #[test]
+    fn function_parse_should_return_a_regex_error() {
+        assert!(matches!(
+            error_kind("many(\"Blank(.esp\")"),
+            ParsingErrorKind::InvalidRegexSyntax(_)
+        ));
+    }
+
+    #[test]
+    fn function_span_should_give_the_spans_of_the_name_and_each_argument() {
+        let call = "record_count(\"Blank, 1.esp\" , <,100)";
+        let span = function_span(call, call);
+
+        assert_eq!(0..36, span.span().range());
+        assert_eq!(0..12, span.name().range());
+        assert_eq!(
+            vec![13..27, 30..31, 32..35],
+            span.arguments().iter().map(Span::range).collect::<Vec<_>>()
+        );
+    }
 }
@@ -3,7 +3,7 @@ use std::{
     path::{Path, PathBuf},
 };
 
-use crate::{GameType, State};
+use crate::{FileSystem, GameType, State};
 
 const GHOST_EXTENSION: &str = "ghost";
 const GHOST_EXTENSION_WITH_PERIOD: &str = ".ghost";
@@ -71,6 +71,7 @@ pub(super) fn normalise_file_name(game_type: GameType, name: &OsStr) -> &OsStr {
 }
 
 pub(super) fn resolve_path_in_parent_paths<'a>(
+    file_system: &dyn FileSystem,
     path: &Path,
     parent_paths: impl Iterator<Item = &'a PathBuf>,
     try_with_ghost_extension: bool,
@@ -78,14 +79,14 @@ pub(super) fn resolve_path_in_parent_paths<'a>(
     for parent_path in parent_paths {
         let joined_path = parent_path.join(path);
 
-        if joined_path.exists() {
+        if file_system.exists(&joined_path) {
             return Some(joined_path);
         }
 
         if try_with_ghost_extension {
             let ghosted_path = add_ghost_extension(&joined_path);
 
-            if ghosted_path.exists() {
+            if file_system.exists(&ghosted_path) {
                 return Some(ghosted_path);
             }
         }
@@ -104,11 +105,13 @@ pub(super) fn resolve_path(state: &State, path: &Path) -> PathBuf {
     // main data path is checked.
     let result = match state.game_type {
         GameType::OpenMW => resolve_path_in_parent_paths(
+            state.file_system.as_ref(),
             path,
             state.additional_data_paths.iter().rev(),
             try_with_ghost_extension,
         ),
         _ => resolve_path_in_parent_paths(
+            state.file_system.as_ref(),
             path,
             state.additional_data_paths.iter(),
             try_with_ghost_extension,
@@ -122,7 +125,7 @@ pub(super) fn resolve_path(state: &State, path: &Path) -> PathBuf {
     // Now check the main data path.
     let joined_path = state.data_path.join(path);
 
-    if !joined_path.exists() && try_with_ghost_extension {
+    if !state.file_system.exists(&joined_path) && try_with_ghost_extension {
         add_ghost_extension(&joined_path)
     } else {
         joined_path
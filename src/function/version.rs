@@ -4,6 +4,7 @@ use std::cmp::Ordering;
 use std::path::Path;
 
 use crate::error::Error;
+use crate::FileSystem;
 use pe::{read_file_version, read_pe_version, read_product_version};
 
 #[derive(Clone, Debug)]
@@ -109,16 +110,22 @@ pub(super) struct Version {
 }
 
 impl Version {
-    pub(super) fn read_file_version(file_path: &Path) -> Result<Option<Self>, Error> {
-        read_pe_version(file_path, read_file_version)
+    pub(super) fn read_file_version(
+        file_system: &dyn FileSystem,
+        file_path: &Path,
+    ) -> Result<Option<Self>, Error> {
+        read_pe_version(file_system, file_path, read_file_version)
     }
 
-    pub(super) fn read_product_version(file_path: &Path) -> Result<Option<Self>, Error> {
-        read_pe_version(file_path, read_product_version)
+    pub(super) fn read_product_version(
+        file_system: &dyn FileSystem,
+        file_path: &Path,
+    ) -> Result<Option<Self>, Error> {
+        read_pe_version(file_system, file_path, read_product_version)
     }
 
-    pub(super) fn is_readable(file_path: &Path) -> bool {
-        read_pe_version(file_path, |_| Ok(None)).is_ok()
+    pub(super) fn is_readable(file_system: &dyn FileSystem, file_path: &Path) -> bool {
+        read_pe_version(file_system, file_path, |_| Ok(None)).is_ok()
     }
 }
 
@@ -331,11 +338,16 @@ mod tests {
     mod constructors {
         use super::super::*;
 
+        use crate::DiskFileSystem;
+
         #[test]
         fn version_read_file_version_should_read_the_file_version_field_of_a_32_bit_executable() {
-            let version = Version::read_file_version(Path::new("tests/libloot_win32/loot.dll"))
-                .unwrap()
-                .unwrap();
+            let version = Version::read_file_version(
+                &DiskFileSystem,
+                Path::new("tests/libloot_win32/loot.dll"),
+            )
+            .unwrap()
+            .unwrap();
 
             assert_eq!(
                 version.release_ids,
@@ -351,9 +363,12 @@ mod tests {
 
         #[test]
         fn version_read_file_version_should_read_the_file_version_field_of_a_64_bit_executable() {
-            let version = Version::read_file_version(Path::new("tests/libloot_win64/loot.dll"))
-                .unwrap()
-                .unwrap();
+            let version = Version::read_file_version(
+                &DiskFileSystem,
+                Path::new("tests/libloot_win64/loot.dll"),
+            )
+            .unwrap()
+            .unwrap();
 
             assert_eq!(
                 version.release_ids,
@@ -369,7 +384,8 @@ mod tests {
 
         #[test]
         fn version_read_file_version_should_error_with_path_if_path_does_not_exist() {
-            let error = Version::read_file_version(Path::new("missing")).unwrap_err();
+            let error =
+                Version::read_file_version(&DiskFileSystem, Path::new("missing")).unwrap_err();
 
             assert!(error
                 .to_string()
@@ -378,16 +394,19 @@ mod tests {
 
         #[test]
         fn version_read_file_version_should_error_with_path_if_the_file_is_not_an_executable() {
-            let error = Version::read_file_version(Path::new("Cargo.toml")).unwrap_err();
+            let error =
+                Version::read_file_version(&DiskFileSystem, Path::new("Cargo.toml")).unwrap_err();
 
             assert_eq!("An error was encountered while reading the version fields of \"Cargo.toml\": Unknown file magic", error.to_string());
         }
 
         #[test]
         fn version_read_file_version_should_return_none_if_there_is_no_version_info() {
-            let version =
-                Version::read_file_version(Path::new("tests/loot_api_python/loot_api.pyd"))
-                    .unwrap();
+            let version = Version::read_file_version(
+                &DiskFileSystem,
+                Path::new("tests/loot_api_python/loot_api.pyd"),
+            )
+            .unwrap();
 
             assert!(version.is_none());
         }
@@ -395,9 +414,12 @@ mod tests {
         #[test]
         fn version_read_product_version_should_read_the_file_version_field_of_a_32_bit_executable()
         {
-            let version = Version::read_product_version(Path::new("tests/libloot_win32/loot.dll"))
-                .unwrap()
-                .unwrap();
+            let version = Version::read_product_version(
+                &DiskFileSystem,
+                Path::new("tests/libloot_win32/loot.dll"),
+            )
+            .unwrap()
+            .unwrap();
 
             assert_eq!(
                 version.release_ids,
@@ -413,9 +435,12 @@ mod tests {
         #[test]
         fn version_read_product_version_should_read_the_file_version_field_of_a_64_bit_executable()
         {
-            let version = Version::read_product_version(Path::new("tests/libloot_win64/loot.dll"))
-                .unwrap()
-                .unwrap();
+            let version = Version::read_product_version(
+                &DiskFileSystem,
+                Path::new("tests/libloot_win64/loot.dll"),
+            )
+            .unwrap()
+            .unwrap();
 
             assert_eq!(
                 version.release_ids,
@@ -441,7 +466,9 @@ mod tests {
 
             std::fs::write(&dll_path, dll_bytes).unwrap();
 
-            let version = Version::read_product_version(&dll_path).unwrap().unwrap();
+            let version = Version::read_product_version(&DiskFileSystem, &dll_path)
+                .unwrap()
+                .unwrap();
 
             assert_eq!(
                 version.release_ids,
@@ -456,7 +483,8 @@ mod tests {
 
         #[test]
         fn version_read_product_version_should_error_with_path_if_path_does_not_exist() {
-            let error = Version::read_product_version(Path::new("missing")).unwrap_err();
+            let error =
+                Version::read_product_version(&DiskFileSystem, Path::new("missing")).unwrap_err();
 
             assert!(error
                 .to_string()
@@ -465,16 +493,19 @@ mod tests {
 
         #[test]
         fn version_read_product_version_should_error_with_path_if_the_file_is_not_an_executable() {
-            let error = Version::read_product_version(Path::new("Cargo.toml")).unwrap_err();
+            let error = Version::read_product_version(&DiskFileSystem, Path::new("Cargo.toml"))
+                .unwrap_err();
 
             assert_eq!("An error was encountered while reading the version fields of \"Cargo.toml\": Unknown file magic", error.to_string());
         }
 
         #[test]
         fn version_read_product_version_should_return_none_if_there_is_no_version_info() {
-            let version =
-                Version::read_product_version(Path::new("tests/loot_api_python/loot_api.pyd"))
-                    .unwrap();
+            let version = Version::read_product_version(
+                &DiskFileSystem,
+                Path::new("tests/loot_api_python/loot_api.pyd"),
+            )
+            .unwrap();
 
             assert!(version.is_none());
         }
@@ -1,10 +1,9 @@
 use std::{
-    fs::File,
     io::{BufReader, Read, Seek, SeekFrom},
     path::Path,
 };
 
-use crate::Error;
+use crate::{Error, FileSystem};
 
 use super::{ReleaseId, Version};
 
@@ -21,13 +20,16 @@ enum ReadResult {
 }
 
 pub(super) fn read_pe_version<F>(
+    file_system: &dyn FileSystem,
     file_path: &Path,
     read_from_version_info: F,
 ) -> Result<Option<Version>, Error>
 where
     F: Fn(&[u8]) -> Result<Option<Version>, String>,
 {
-    let file = File::open(file_path).map_err(|e| Error::IoError(file_path.to_path_buf(), e))?;
+    let file = file_system
+        .open(file_path)
+        .map_err(|e| Error::IoError(file_path.to_path_buf(), e))?;
     let mut reader = BufReader::new(file);
 
     let data = read_version_resource_data(&mut reader)
@@ -14,27 +14,51 @@
         clippy::unwrap_used,
     )
 )]
+mod archive;
 mod error;
+mod explain;
+mod filesystem;
+mod format;
 mod function;
+mod partial;
+mod recover;
+mod simplify;
+mod span;
+pub mod visit;
 
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::fmt;
-use std::ops::DerefMut;
+use std::hash::{Hash, Hasher};
+use std::iter;
+use std::ops::{DerefMut, Not};
 use std::path::PathBuf;
 use std::str;
-use std::sync::{PoisonError, RwLock, RwLockWriteGuard};
+use std::sync::{Arc, PoisonError, RwLock, RwLockWriteGuard};
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
 use nom::character::complete::multispace0;
-use nom::combinator::map;
-use nom::multi::separated_list0;
+use nom::combinator::{consumed, map, opt};
+use nom::multi::many0;
 use nom::sequence::{delimited, preceded};
 use nom::{IResult, Parser};
 
+use archive::ArchiveIndex;
 use error::ParsingError;
-pub use error::{Error, MoreDataNeeded, ParsingErrorKind};
-use function::Function;
+pub use error::{Error, FunctionArgument, MoreDataNeeded, ParsingErrorKind};
+pub use explain::{
+    CompoundConditionTrace, ConditionTrace, ExpressionTrace, FunctionEvaluation, ResultSource,
+};
+pub use filesystem::{
+    DirEntryNames, DiskFileSystem, FileSystem, MemoryFileSystem, Metadata, ModOrganizerFileSystem,
+    ReadSeek,
+};
+pub use format::FormatOptions;
+pub use function::{ComparisonOperator, Function};
+pub use partial::PartialResult;
+pub use recover::RecoveredExpression;
+pub use span::{FunctionSpan, Location, Span};
 
 type ParsingResult<'a, T> = IResult<&'a str, T, ParsingError<&'a str>>;
 
@@ -81,12 +105,20 @@ pub struct State {
     additional_data_paths: Vec<PathBuf>,
     /// Lowercased plugin filenames.
     active_plugins: HashSet<String>,
+    /// Lowercased plugin filenames and their positions in the load order.
+    load_order: HashMap<String, usize>,
     /// Lowercased paths.
     crc_cache: RwLock<HashMap<String, u32>>,
     /// Lowercased plugin filenames and their versions as found in description fields.
     plugin_versions: HashMap<String, String>,
     /// Conditions that have already been evaluated, and their results.
     condition_cache: RwLock<HashMap<Function, bool>>,
+    /// The filesystem that paths are looked up in.
+    file_system: Box<dyn FileSystem>,
+    /// Whether functions also look for files in archives loaded by active plugins.
+    archive_lookup: bool,
+    /// The files in archives loaded by active plugins, read when first needed.
+    archive_index: RwLock<Option<Arc<ArchiveIndex>>>,
 }
 
 impl State {
@@ -96,9 +128,13 @@ impl State {
             data_path,
             additional_data_paths: Vec::default(),
             active_plugins: HashSet::default(),
+            load_order: HashMap::default(),
             crc_cache: RwLock::default(),
             plugin_versions: HashMap::default(),
             condition_cache: RwLock::default(),
+            file_system: Box::new(DiskFileSystem),
+            archive_lookup: false,
+            archive_index: RwLock::default(),
         }
     }
 
@@ -117,11 +153,41 @@ impl State {
         self
     }
 
+    #[must_use]
+    pub fn with_load_order<T: AsRef<str>>(mut self, load_order: &[T]) -> Self {
+        self.set_load_order(load_order);
+        self
+    }
+
+    #[must_use]
+    pub fn with_file_system<T: FileSystem + 'static>(mut self, file_system: T) -> Self {
+        self.set_file_system(file_system);
+        self
+    }
+
+    #[must_use]
+    pub fn with_archive_lookup(mut self, archive_lookup: bool) -> Self {
+        self.set_archive_lookup(archive_lookup);
+        self
+    }
+
     pub fn set_active_plugins<T: AsRef<str>>(&mut self, active_plugins: &[T]) {
         self.active_plugins = active_plugins
             .iter()
             .map(|s| s.as_ref().to_lowercase())
             .collect();
+        self.archive_index = RwLock::default();
+    }
+
+    /// Sets the load order that `load_after()` and `load_index()` use, as
+    /// plugin filenames in the order that they load. The load order may
+    /// include inactive plugins, and doesn't affect which plugins are active.
+    pub fn set_load_order<T: AsRef<str>>(&mut self, load_order: &[T]) {
+        self.load_order = load_order
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.as_ref().to_lowercase(), i))
+            .collect();
     }
 
     pub fn set_plugin_versions<T: AsRef<str>, V: ToString>(&mut self, plugin_versions: &[(T, V)]) {
@@ -163,33 +229,191 @@ impl State {
         Ok(())
     }
 
+    /// Sets the filesystem that functions are evaluated against, which is the
+    /// real disk by default. This doesn't clear the condition or CRC caches.
+    pub fn set_file_system<T: FileSystem + 'static>(&mut self, file_system: T) {
+        self.file_system = Box::new(file_system);
+        self.archive_index = RwLock::default();
+    }
+
+    /// Sets whether the `file()` (with a plain or regex path), `file_size()`
+    /// and `many()` functions also look for files in the BSA or BA2 archives
+    /// that the game loads for the active plugins, which they don't by
+    /// default. Loose files take precedence over archived files. The archives
+    /// are read when they are first needed, and are read again if the active
+    /// plugins, filesystem or additional data paths change. This doesn't clear
+    /// the condition cache.
+    ///
+    /// Only archives that are loaded because their filenames match an active
+    /// plugin's are looked in. Archives that the game loads because they're
+    /// listed in its INI files (e.g. `Skyrim - Textures0.bsa` for Skyrim
+    /// Special Edition) are not, and nor are any of Morrowind's archives.
+    pub fn set_archive_lookup(&mut self, archive_lookup: bool) {
+        self.archive_lookup = archive_lookup;
+    }
+
     pub fn set_additional_data_paths(&mut self, additional_data_paths: Vec<PathBuf>) {
         self.additional_data_paths = additional_data_paths;
+        self.archive_index = RwLock::default();
     }
 }
 
 /// Compound conditions joined by 'or'
-#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
-pub struct Expression(Vec<CompoundCondition>);
+#[derive(Clone, Debug, Default)]
+pub struct Expression {
+    compound_conditions: Vec<CompoundCondition>,
+    span: Option<Span>,
+}
 
 impl Expression {
+    pub(crate) fn new(compound_conditions: Vec<CompoundCondition>) -> Self {
+        Expression {
+            compound_conditions,
+            span: None,
+        }
+    }
+
+    /// Combines the given expressions with 'or'. Returns `None` if no
+    /// expressions are given, as there is no condition string for an empty
+    /// expression.
+    pub fn any_of<T: IntoIterator<Item = Expression>>(expressions: T) -> Option<Self> {
+        let compound_conditions: Vec<_> = expressions
+            .into_iter()
+            .flat_map(|e| e.compound_conditions)
+            .collect();
+
+        if compound_conditions.is_empty() {
+            None
+        } else {
+            Some(Expression::new(compound_conditions))
+        }
+    }
+
+    /// Combines the given expressions with 'and', wrapping any that contain
+    /// 'or' in parentheses. Returns `None` if no expressions are given, as
+    /// there is no condition string for an empty expression.
+    pub fn all_of<T: IntoIterator<Item = Expression>>(expressions: T) -> Option<Self> {
+        let conditions: Vec<_> = expressions
+            .into_iter()
+            .flat_map(Expression::into_conditions)
+            .collect();
+
+        if conditions.is_empty() {
+            None
+        } else {
+            Some(Expression::new(vec![CompoundCondition::new(conditions)]))
+        }
+    }
+
+    fn into_conditions(self) -> Vec<Condition> {
+        match <[CompoundCondition; 1]>::try_from(self.compound_conditions) {
+            Ok([compound_condition]) => compound_condition.conditions,
+            Err(compound_conditions) => {
+                vec![Condition::Expression(Expression::new(compound_conditions))]
+            }
+        }
+    }
+
+    pub fn compound_conditions(&self) -> &[CompoundCondition] {
+        &self.compound_conditions
+    }
+
+    /// The part of the parsed string that the expression was parsed from,
+    /// excluding any enclosing parentheses. Expressions that were not parsed
+    /// from a string have no span.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// Returns an equivalent expression that has had redundant parentheses,
+    /// duplicate conditions and contradictory compound conditions removed, and
+    /// that has had De Morgan's laws applied so that only functions are
+    /// negated.
+    #[must_use]
+    pub fn simplify(self) -> Self {
+        simplify::simplify(self)
+    }
+
+    /// Writes the expression out in a canonical form, with consistent
+    /// whitespace, and optionally wrapped over multiple lines. Unless the
+    /// options normalise directory separators, the result can be parsed back
+    /// into an equivalent expression.
+    pub fn format(&self, options: &FormatOptions) -> String {
+        format::format(self, options)
+    }
+
+    /// Parses as much of the given string as possible instead of stopping at
+    /// the first problem. Conditions that can't be parsed are skipped up to
+    /// the next 'and', 'or' or closing parenthesis and replaced by
+    /// [`Condition::Invalid`], and every problem found is recorded.
+    pub fn parse_recovering(s: &str) -> RecoveredExpression {
+        recover::parse_recovering(s)
+    }
+
     pub fn eval(&self, state: &State) -> Result<bool, Error> {
-        for compound_condition in &self.0 {
+        for compound_condition in &self.compound_conditions {
             if compound_condition.eval(state)? {
                 return Ok(true);
             }
         }
         Ok(false)
     }
+
+    /// Evaluates the expression in the same way as [`Expression::eval`], but
+    /// returns a trace of the evaluation that records each function's result,
+    /// and which conditions were skipped because the result was already
+    /// known.
+    pub fn eval_explained(&self, state: &State) -> Result<ExpressionTrace, Error> {
+        explain::explain(self, state)
+    }
+
+    /// Evaluates the expression without evaluating the functions that
+    /// `is_unknown` returns true for. If the result depends on any of those
+    /// functions, the simplified expression that still needs to be evaluated
+    /// is returned.
+    pub fn eval_partial(
+        &self,
+        state: &State,
+        is_unknown: impl Fn(&Function) -> bool,
+    ) -> Result<PartialResult, Error> {
+        partial::eval_partial(self, state, &is_unknown)
+    }
+}
+
+impl From<Function> for Expression {
+    fn from(function: Function) -> Self {
+        Condition::Function(function).into()
+    }
+}
+
+impl From<Condition> for Expression {
+    fn from(condition: Condition) -> Self {
+        Expression::new(vec![CompoundCondition::new(vec![condition])])
+    }
+}
+
+impl Not for Expression {
+    type Output = Expression;
+
+    fn not(self) -> Self::Output {
+        if let [compound_condition] = self.compound_conditions.as_slice() {
+            if let [Condition::Function(function)] = compound_condition.conditions.as_slice() {
+                return Condition::InvertedFunction(function.clone()).into();
+            }
+        }
+
+        Condition::InvertedExpression(self).into()
+    }
 }
 
 impl str::FromStr for Expression {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (remaining_input, expression) = parse_expression(s)?;
+        let (remaining_input, expression) =
+            parse_source(ParsingContext::new(s)).map_err(|e| Error::from_parsing_error(s, e))?;
 
-        if remaining_input.is_empty() {
+        if remaining_input.trim_start().is_empty() {
             Ok(expression)
         } else {
             Err(Error::UnconsumedInput(remaining_input.to_owned()))
@@ -197,28 +421,150 @@ impl str::FromStr for Expression {
     }
 }
 
-fn parse_expression(input: &str) -> ParsingResult<'_, Expression> {
-    map(
-        separated_list0(map_err(whitespace(tag("or"))), CompoundCondition::parse),
-        Expression,
+/// What the parsers of an expression's nodes share.
+#[derive(Clone, Copy)]
+struct ParsingContext<'a> {
+    /// The whole string being parsed, which spans are given relative to.
+    source: &'a str,
+    /// Where errors are recorded when parsing with recovery.
+    recovered_errors: Option<&'a RefCell<Vec<Error>>>,
+    /// Whether the input being parsed is inside parentheses.
+    nested: bool,
+}
+
+impl<'a> ParsingContext<'a> {
+    fn new(source: &'a str) -> Self {
+        ParsingContext {
+            source,
+            recovered_errors: None,
+            nested: false,
+        }
+    }
+
+    fn recovering(&self) -> bool {
+        self.recovered_errors.is_some()
+    }
+
+    /// When parsing with recovery, records the error so that parsing can
+    /// carry on. Otherwise, gives the error as a failure.
+    fn recover(&self, error: ParsingError<&'a str>) -> Result<(), nom::Err<ParsingError<&'a str>>> {
+        match self.recovered_errors {
+            Some(errors) => {
+                let error = Error::from_parsing_error(self.source, nom::Err::Failure(error));
+                errors.borrow_mut().push(error);
+                Ok(())
+            }
+            None => Err(nom::Err::Failure(error)),
+        }
+    }
+}
+
+/// Parses the whole source string, apart from any trailing whitespace.
+fn parse_source(context: ParsingContext<'_>) -> ParsingResult<'_, Expression> {
+    preceded(map_err(multispace0), |i| parse_expression(context, i)).parse(context.source)
+}
+
+/// `input` must be a subslice of the context's source.
+fn parse_expression<'a>(
+    context: ParsingContext<'a>,
+    input: &'a str,
+) -> ParsingResult<'a, Expression> {
+    let (remaining_input, (first, rest)) = (
+        |i| CompoundCondition::parse(context, None, i),
+        many0(preceded(keyword("or"), |i| {
+            CompoundCondition::parse(context, Some("or"), i)
+        })),
     )
-    .parse(input)
+        .parse(input)?;
+
+    // A compound condition after 'or' is only empty if it's missing, which is
+    // an error that has been recovered from.
+    let compound_conditions = iter::once(first)
+        .chain(rest.into_iter().filter(|c| !c.conditions.is_empty()))
+        .collect();
+
+    let expression = Expression {
+        compound_conditions,
+        span: Some(Span::consumed(context.source, input, remaining_input)),
+    };
+
+    Ok((remaining_input, expression))
+}
+
+/// Spans are ignored when comparing expressions, so that equal expressions
+/// are equal however they were written.
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.compound_conditions == other.compound_conditions
+    }
+}
+
+impl Eq for Expression {}
+
+impl Hash for Expression {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.compound_conditions.hash(state);
+    }
 }
 
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let strings: Vec<String> = self.0.iter().map(CompoundCondition::to_string).collect();
+        let strings: Vec<String> = self
+            .compound_conditions
+            .iter()
+            .map(CompoundCondition::to_string)
+            .collect();
         write!(f, "{}", strings.join(" or "))
     }
 }
 
 /// Conditions joined by 'and'
-#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
-struct CompoundCondition(Vec<Condition>);
+#[derive(Clone, Debug, Default)]
+pub struct CompoundCondition {
+    conditions: Vec<Condition>,
+    /// Either empty or the same length as `conditions`.
+    condition_spans: Vec<Span>,
+    /// Either empty or the same length as `conditions`.
+    function_spans: Vec<Option<FunctionSpan>>,
+    span: Option<Span>,
+}
 
 impl CompoundCondition {
-    fn eval(&self, state: &State) -> Result<bool, Error> {
-        for condition in &self.0 {
+    pub(crate) fn new(conditions: Vec<Condition>) -> Self {
+        CompoundCondition {
+            conditions,
+            condition_spans: Vec::new(),
+            function_spans: Vec::new(),
+            span: None,
+        }
+    }
+
+    pub fn conditions(&self) -> &[Condition] {
+        &self.conditions
+    }
+
+    /// The part of the parsed string that the compound condition was parsed
+    /// from. Compound conditions that were not parsed from a string have no
+    /// span.
+    pub fn span(&self) -> Option<Span> {
+        self.span
+    }
+
+    /// The span of the condition at the given index in `conditions()`,
+    /// including its 'not' and parentheses, if any.
+    pub fn condition_span(&self, index: usize) -> Option<Span> {
+        self.condition_spans.get(index).copied()
+    }
+
+    /// The spans of the function call and its arguments in the condition at
+    /// the given index in `conditions()`, if the condition is a function or
+    /// an inverted function.
+    pub fn function_span(&self, index: usize) -> Option<&FunctionSpan> {
+        self.function_spans.get(index).and_then(Option::as_ref)
+    }
+
+    pub fn eval(&self, state: &State) -> Result<bool, Error> {
+        for condition in &self.conditions {
             if !condition.eval(state)? {
                 return Ok(false);
             }
@@ -226,63 +572,121 @@ impl CompoundCondition {
         Ok(true)
     }
 
-    fn parse(input: &str) -> ParsingResult<'_, CompoundCondition> {
-        map(
-            separated_list0(map_err(whitespace(tag("and"))), Condition::parse),
-            CompoundCondition,
-        )
-        .parse(input)
+    /// The first condition is optional unless it comes `after` a keyword.
+    fn parse<'a>(
+        context: ParsingContext<'a>,
+        after: Option<&'static str>,
+        input: &'a str,
+    ) -> ParsingResult<'a, CompoundCondition> {
+        let mut conditions = Vec::new();
+        let mut condition_spans = Vec::new();
+        let mut function_spans = Vec::new();
+
+        let (mut remaining_input, first) =
+            consumed(|i| recover::recover_condition(context, after, i)).parse(input)?;
+
+        let mut next = first.1.is_some().then_some(first);
+        while let Some((consumed_input, condition)) = next {
+            if let Some(condition) = condition {
+                let consumed_input = consumed_input.trim();
+                let call = match condition {
+                    Condition::Function(_) => Some(consumed_input),
+                    Condition::InvertedFunction(_) => {
+                        keyword("not")(consumed_input).ok().map(|(call, _)| call)
+                    }
+                    _ => None,
+                };
+
+                conditions.push(condition);
+                condition_spans.push(Span::of(context.source, consumed_input));
+                function_spans
+                    .push(call.map(|c| function::parse::function_span(context.source, c)));
+            }
+
+            (remaining_input, next) = opt(alt((
+                preceded(
+                    keyword("and"),
+                    consumed(|i| recover::recover_condition(context, Some("and"), i)),
+                ),
+                |i| recover::recover_missing_operator(context, i),
+            )))
+            .parse(remaining_input)?;
+        }
+
+        let compound_condition = CompoundCondition {
+            conditions,
+            condition_spans,
+            function_spans,
+            span: Some(Span::consumed(context.source, input, remaining_input)),
+        };
+
+        Ok((remaining_input, compound_condition))
+    }
+}
+
+impl PartialEq for CompoundCondition {
+    fn eq(&self, other: &Self) -> bool {
+        self.conditions == other.conditions
+    }
+}
+
+impl Eq for CompoundCondition {}
+
+impl Hash for CompoundCondition {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.conditions.hash(state);
     }
 }
 
 impl fmt::Display for CompoundCondition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let strings: Vec<String> = self.0.iter().map(Condition::to_string).collect();
+        let strings: Vec<String> = self.conditions.iter().map(Condition::to_string).collect();
         write!(f, "{}", strings.join(" and "))
     }
 }
 
+/// A function or a parenthesised expression, optionally negated with 'not'
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-enum Condition {
+#[non_exhaustive]
+pub enum Condition {
     Function(Function),
     InvertedFunction(Function),
     Expression(Expression),
     InvertedExpression(Expression),
+    /// Input that could not be parsed as a condition, as written in the parsed
+    /// string. These are only produced by [`Expression::parse_recovering`],
+    /// and evaluating one is an error.
+    Invalid(String),
 }
 
 impl Condition {
-    fn eval(&self, state: &State) -> Result<bool, Error> {
+    pub fn eval(&self, state: &State) -> Result<bool, Error> {
         match self {
             Condition::Function(f) => f.eval(state),
             Condition::InvertedFunction(f) => f.eval(state).map(|r| !r),
             Condition::Expression(e) => e.eval(state),
             Condition::InvertedExpression(e) => e.eval(state).map(|r| !r),
+            Condition::Invalid(i) => Err(Error::UnconsumedInput(i.clone())),
         }
     }
 
-    fn parse(input: &str) -> ParsingResult<'_, Condition> {
+    fn parse<'a>(context: ParsingContext<'a>, input: &'a str) -> ParsingResult<'a, Condition> {
         alt((
             map(Function::parse, Condition::Function),
-            map(
-                preceded(map_err(whitespace(tag("not"))), Function::parse),
-                Condition::InvertedFunction,
-            ),
-            map(
-                delimited(
-                    map_err(whitespace(tag("("))),
-                    parse_expression,
-                    map_err(whitespace(tag(")"))),
-                ),
-                Condition::Expression,
-            ),
-            map(
-                delimited(
-                    map_err(preceded(whitespace(tag("not")), whitespace(tag("(")))),
-                    parse_expression,
-                    map_err(whitespace(tag(")"))),
+            preceded(
+                keyword("not"),
+                expect_condition(
+                    "not",
+                    alt((
+                        map(Function::parse, Condition::InvertedFunction),
+                        map(
+                            |i| parse_parenthesised(context, i),
+                            Condition::InvertedExpression,
+                        ),
+                    )),
                 ),
-                Condition::InvertedExpression,
             ),
+            map(|i| parse_parenthesised(context, i), Condition::Expression),
         ))
         .parse(input)
     }
@@ -295,7 +699,65 @@ impl fmt::Display for Condition {
             Self::InvertedFunction(function) => write!(f, "not {function}"),
             Self::Expression(e) => write!(f, "({e})"),
             Self::InvertedExpression(e) => write!(f, "not ({e})"),
+            Self::Invalid(i) => write!(f, "{i}"),
+        }
+    }
+}
+
+/// Parses an expression in parentheses. Once the opening parenthesis has been
+/// parsed, a missing closing parenthesis is an error.
+fn parse_parenthesised<'a>(
+    context: ParsingContext<'a>,
+    input: &'a str,
+) -> ParsingResult<'a, Expression> {
+    let (opening_parenthesis, _) = map_err(multispace0).parse(input)?;
+    let (expression_input, _) = map_err(whitespace(tag("("))).parse(opening_parenthesis)?;
+
+    let nested_context = ParsingContext {
+        nested: true,
+        ..context
+    };
+    let (remaining_input, expression) = parse_expression(nested_context, expression_input)?;
+
+    match map_err(whitespace(tag(")"))).parse(remaining_input) {
+        Ok((remaining_input, _)) => Ok((remaining_input, expression)),
+        Err(nom::Err::Error(_)) => {
+            context.recover(
+                ParsingErrorKind::UnbalancedParenthesis
+                    .at(opening_parenthesis)
+                    .with_length(1),
+            )?;
+            Ok((remaining_input, expression))
         }
+        Err(e) => Err(e),
+    }
+}
+
+/// Parses a condition that must come after the given keyword.
+fn expect_condition<'a, O>(
+    keyword: &'static str,
+    mut parser: impl Parser<&'a str, Output = O, Error = ParsingError<&'a str>>,
+) -> impl FnMut(&'a str) -> ParsingResult<'a, O> {
+    move |input| {
+        parser
+            .parse(input)
+            .map_err(fail_with(|| recover::missing_condition(keyword, input)))
+    }
+}
+
+/// Parses a keyword and the whitespace around it.
+fn keyword<'a>(keyword: &'static str) -> impl FnMut(&'a str) -> ParsingResult<'a, &'a str> {
+    map_err(whitespace(tag(keyword)))
+}
+
+/// Turns a parser error into a failure with the given error, so that no other
+/// parsers are tried.
+fn fail_with<'a>(
+    error: impl FnOnce() -> ParsingError<&'a str>,
+) -> impl FnOnce(nom::Err<ParsingError<&'a str>>) -> nom::Err<ParsingError<&'a str>> {
+    |e| match e {
+        nom::Err::Error(_) => nom::Err::Failure(error()),
+        e => e,
     }
 }
 
@@ -313,11 +775,10 @@ fn whitespace<'a, O>(
 
 #[cfg(test)]
 mod tests {
-    use crate::function::ComparisonOperator;
-
     use super::*;
 
     use std::fs::create_dir_all;
+    use std::path::Path;
     use std::str::FromStr;
 
     fn state<T: Into<PathBuf>>(data_path: T) -> State {
@@ -331,9 +792,13 @@ mod tests {
             data_path,
             additional_data_paths: Vec::default(),
             active_plugins: HashSet::new(),
+            load_order: HashMap::new(),
             crc_cache: RwLock::default(),
             plugin_versions: HashMap::default(),
             condition_cache: RwLock::default(),
+            file_system: Box::new(DiskFileSystem),
+            archive_lookup: false,
+            archive_index: RwLock::default(),
         }
     }
 
@@ -361,11 +826,37 @@ mod tests {
         let error = Expression::from_str("file(\"Carg").unwrap_err();
 
         assert_eq!(
-            "The parser did not consume the following input: \"file(\"Carg\"",
+            "An error was encountered while parsing the expression \"\\\"Carg\": String has no closing quote",
             error.to_string()
         );
     }
 
+    #[test]
+    fn expression_from_str_should_suggest_a_function_name_if_given_an_unknown_one() {
+        let error = Expression::from_str("file(\"a.esp\") and is_activ(\"b.esp\")").unwrap_err();
+
+        assert_eq!(
+            "An error was encountered while parsing the expression \"is_activ\": Unknown function `is_activ`, did you mean `active`?",
+            error.to_string()
+        );
+    }
+
+    #[test]
+    fn expression_from_str_should_describe_a_missing_function_argument() {
+        let error = Expression::from_str("version(\"a.esp\", \"1.0\")").unwrap_err();
+
+        match error {
+            Error::ParsingError(_, kind, span) => {
+                assert_eq!(
+                    "Expected comparator after version in `version(...)`",
+                    kind.to_string()
+                );
+                assert_eq!(22..22, span.range());
+            }
+            e => panic!("Expected a parsing error, got {e:?}"),
+        }
+    }
+
     #[test]
     fn expression_from_str_should_error_with_input_on_invalid_regex() {
         let error = Expression::from_str("file(\"Carg\\.*(\")").unwrap_err();
@@ -396,12 +887,115 @@ mod tests {
         );
     }
 
+    #[test]
+    fn expression_from_str_error_span_should_cover_the_invalid_argument() {
+        let string = "file(\"a.esp\") and\nchecksum(\"Cargo.toml\", DEADBEEFDEAD)";
+        let error = Expression::from_str(string).unwrap_err();
+
+        match error {
+            Error::ParsingError(_, ParsingErrorKind::InvalidCrc(_), span) => {
+                assert_eq!(41..53, span.range());
+                assert_eq!(2, span.start().line());
+                assert_eq!(24, span.start().column());
+            }
+            e => panic!("Expected an invalid CRC error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn error_span_should_be_the_span_of_a_parsing_error() {
+        let error = Expression::from_str("checksum(\"Cargo.toml\", XYZ)").unwrap_err();
+        assert_eq!(Some(23..26), error.span().map(|s| s.range()));
+
+        let error = Expression::from_str("file(\"Cargo.toml\") foobar").unwrap_err();
+        assert_eq!(None, error.span());
+    }
+
+    #[test]
+    fn expression_from_str_error_span_should_cover_only_the_invalid_path() {
+        let error = Expression::from_str("file(\"targ.*et/\")").unwrap_err();
+
+        match error {
+            Error::ParsingError(_, ParsingErrorKind::PathEndsInADirectorySeparator(_), span) => {
+                assert_eq!(6..15, span.range());
+            }
+            e => panic!("Expected a path error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn expression_from_str_should_record_the_spans_of_parsed_nodes() {
+        let string = "file(\"a.esp\") and\n  not ( active(\"b.esp\") or many(\"c.*\") )";
+        let expression = Expression::from_str(string).unwrap();
+
+        assert_eq!(Some(0..58), expression.span().map(|s| s.range()));
+
+        let [compound_condition] = expression.compound_conditions() else {
+            panic!("Expected one compound condition");
+        };
+        assert_eq!(Some(0..58), compound_condition.span().map(|s| s.range()));
+        assert_eq!(
+            Some(0..13),
+            compound_condition.condition_span(0).map(|s| s.range())
+        );
+
+        let span = compound_condition.condition_span(1).unwrap();
+        assert_eq!(20..58, span.range());
+        assert_eq!(2, span.start().line());
+        assert_eq!(3, span.start().column());
+        assert_eq!(None, compound_condition.condition_span(2));
+
+        let Condition::InvertedExpression(inner) = &compound_condition.conditions()[1] else {
+            panic!("Expected an inverted expression");
+        };
+        assert_eq!(Some(26..56), inner.span().map(|s| s.range()));
+        assert_eq!(
+            Some(45..56),
+            inner.compound_conditions()[1].span().map(|s| s.range())
+        );
+    }
+
+    #[test]
+    fn expression_from_str_should_record_the_spans_of_functions_and_their_arguments() {
+        let string = "not version(\"a, b.esp\",  \"1.0\" , >=) and (many(\"(c|d)\\.esp\"))";
+        let expression = Expression::from_str(string).unwrap();
+        let compound_condition = &expression.compound_conditions()[0];
+
+        let function_span = compound_condition.function_span(0).unwrap();
+        let arguments: Vec<_> = function_span.arguments().iter().map(Span::range).collect();
+        assert_eq!(4..36, function_span.span().range());
+        assert_eq!(4..11, function_span.name().range());
+        assert_eq!(vec![12..22, 25..30, 33..35], arguments);
+
+        assert_eq!(None, compound_condition.function_span(1));
+
+        let Condition::Expression(inner) = &compound_condition.conditions()[1] else {
+            panic!("Expected an expression");
+        };
+        let function_span = inner.compound_conditions()[0].function_span(0).unwrap();
+        assert_eq!(42..60, function_span.span().range());
+        assert_eq!(&[Span::new(string, 47, 59)], function_span.arguments());
+    }
+
+    #[test]
+    fn expression_eq_should_ignore_spans() {
+        let parsed = Expression::from_str("file(\"a.esp\")  and (active(\"b.esp\"))").unwrap();
+        let built = Expression::all_of([
+            Function::file("a.esp").unwrap().into(),
+            Condition::Expression(Function::active("b.esp").unwrap().into()).into(),
+        ])
+        .unwrap();
+
+        assert_eq!(None, built.span());
+        assert_eq!(built, parsed);
+    }
+
     #[test]
     fn expression_parse_should_handle_a_single_compound_condition() {
         let result = Expression::from_str("file(\"Cargo.toml\")").unwrap();
 
-        match result.0.as_slice() {
-            [CompoundCondition(_)] => {}
+        match result.compound_conditions() {
+            [_] => {}
             _ => panic!("Expected an expression with one compound condition"),
         }
     }
@@ -410,8 +1004,8 @@ mod tests {
     fn expression_parse_should_handle_multiple_compound_conditions() {
         let result = Expression::from_str("file(\"Cargo.toml\") or file(\"Cargo.toml\")").unwrap();
 
-        match result.0.as_slice() {
-            [CompoundCondition(_), CompoundCondition(_)] => {}
+        match result.compound_conditions() {
+            [_, _] => {}
             v => panic!("Expected an expression with two compound conditions, got {v:?}"),
         }
     }
@@ -426,6 +1020,129 @@ mod tests {
         );
     }
 
+    #[test]
+    fn expression_from_str_should_find_an_unknown_function_in_a_nested_expression() {
+        let error =
+            Expression::from_str("file(\"a.esp\") and (active(\"b.esp\") or is_activ(\"c.esp\"))")
+                .unwrap_err();
+
+        match error {
+            Error::ParsingError(_, kind, span) => {
+                assert_eq!(
+                    ParsingErrorKind::UnknownFunction("is_activ".into(), Some("active")),
+                    kind
+                );
+                assert_eq!(38..46, span.range());
+            }
+            e => panic!("Expected a parsing error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn expression_from_str_should_report_an_unbalanced_opening_parenthesis() {
+        let error =
+            Expression::from_str("file(\"a.esp\") and\n(active(\"b.esp\") or file(\"c.esp\")")
+                .unwrap_err();
+
+        match error {
+            Error::ParsingError(_, ParsingErrorKind::UnbalancedParenthesis, span) => {
+                assert_eq!(18..19, span.range());
+                assert_eq!(2, span.start().line());
+                assert_eq!(1, span.start().column());
+            }
+            e => panic!("Expected an unbalanced parenthesis error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn expression_from_str_should_report_an_unmatched_closing_parenthesis() {
+        let error = Expression::from_str("file(\"a.esp\")) or file(\"b.esp\")").unwrap_err();
+
+        match error {
+            Error::ParsingError(_, ParsingErrorKind::UnmatchedClosingParenthesis, span) => {
+                assert_eq!(13..14, span.range());
+            }
+            e => panic!("Expected an unmatched parenthesis error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn expression_from_str_should_report_a_missing_condition_after_a_keyword() {
+        match Expression::from_str("file(\"a.esp\") and").unwrap_err() {
+            Error::ParsingError(_, ParsingErrorKind::ExpectedCondition("and"), span) => {
+                assert_eq!(17..17, span.range());
+            }
+            e => panic!("Expected a missing condition error, got {e:?}"),
+        }
+
+        match Expression::from_str("file(\"a.esp\") or not").unwrap_err() {
+            Error::ParsingError(_, kind, _) => {
+                assert_eq!(ParsingErrorKind::ExpectedCondition("not"), kind);
+            }
+            e => panic!("Expected a parsing error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn expression_from_str_should_report_a_missing_condition_at_the_end_of_the_input() {
+        match Expression::from_str("file(\"a.esp\") or").unwrap_err() {
+            Error::ParsingError(_, ParsingErrorKind::ExpectedCondition("or"), span) => {
+                assert_eq!(16..16, span.range());
+            }
+            e => panic!("Expected a missing condition error, got {e:?}"),
+        }
+    }
+
+    #[test]
+    fn expression_from_str_should_parse_keywords_that_are_directly_followed_by_a_function() {
+        for string in [
+            "notfile(\"a.esp\")",
+            "file(\"a.esp\") andfile(\"b.esp\")",
+            "file(\"a.esp\")orfile(\"b.esp\")",
+        ] {
+            let expression = Expression::from_str(string).unwrap();
+            assert_eq!(
+                Expression::from_str(&expression.to_string()).unwrap(),
+                expression
+            );
+        }
+
+        assert_eq!(
+            "not file(\"a.esp\")",
+            Expression::from_str("notfile(\"a.esp\")")
+                .unwrap()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn expression_from_str_should_ignore_leading_and_trailing_whitespace() {
+        let expression = Expression::from_str(" \tfile(\"a.esp\") or file(\"b.esp\")\n").unwrap();
+
+        assert_eq!(2, expression.compound_conditions().len());
+        assert_eq!(2..32, expression.span().unwrap().range());
+    }
+
+    #[test]
+    fn expression_from_str_should_report_an_invalid_function_argument() {
+        let error = Expression::from_str("not version(\"a.esp\", \"1.0\", =)").unwrap_err();
+
+        match error {
+            Error::ParsingError(_, kind, span) => {
+                assert_eq!(
+                    ParsingErrorKind::ExpectedArgument {
+                        function: "version",
+                        expected: FunctionArgument::Comparator,
+                        after: Some(FunctionArgument::Version),
+                    },
+                    kind
+                );
+                assert_eq!(28..29, span.range());
+            }
+            e => panic!("Expected a parsing error, got {e:?}"),
+        }
+    }
+
     #[test]
     fn expression_parsing_should_ignore_whitespace_between_function_arguments() {
         let is_ok = |s: &str| Expression::from_str(s).is_ok();
@@ -456,25 +1173,47 @@ mod tests {
     fn expression_parsing_should_ignore_line_breaks_when_ignoring_whitespace() {
         let result = Expression::from_str("file(\"Cargo.toml\")\r\nor\nversion(\"Cargo.toml\",\n\"1.2\",\r\n==)\nand\r\nfile(\"Cargo.toml\")").unwrap();
 
-        match result.0.as_slice() {
-            [CompoundCondition(c1), CompoundCondition(c2)] => {
-                match (c1.as_slice(), c2.as_slice()) {
-                    (
-                        [Condition::Function(_)],
-                        [Condition::Function(_), Condition::Function(_)],
-                    ) => {}
-                    v => panic!("Expected an expression with two compound conditions, got {v:?}"),
+        match result.compound_conditions() {
+            [c1, c2] => match (c1.conditions(), c2.conditions()) {
+                ([Condition::Function(_)], [Condition::Function(_), Condition::Function(_)]) => {}
+                v => panic!("Expected an expression with two compound conditions, got {v:?}"),
+            },
+            v => panic!("Expected an expression with two compound conditions, got {v:?}"),
+        }
+    }
+
+    #[test]
+    fn expression_compound_conditions_and_compound_condition_conditions_should_expose_the_parsed_tree(
+    ) {
+        let expression =
+            Expression::from_str("file(\"Cargo.toml\") or not active(\"Blank.esm\")").unwrap();
+
+        match expression.compound_conditions() {
+            [c1, c2] => match (c1.conditions(), c2.conditions()) {
+                (
+                    [Condition::Function(Function::FilePath(f))],
+                    [Condition::InvertedFunction(Function::ActivePath(p))],
+                ) => {
+                    assert_eq!(Path::new("Cargo.toml"), f);
+                    assert_eq!(Path::new("Blank.esm"), p);
                 }
-            }
+                v => panic!("Unexpected conditions {v:?}"),
+            },
             v => panic!("Expected an expression with two compound conditions, got {v:?}"),
         }
     }
 
     #[test]
     fn compound_condition_parse_should_handle_a_single_condition() {
-        let result = CompoundCondition::parse("file(\"Cargo.toml\")").unwrap().1;
+        let result = CompoundCondition::parse(
+            ParsingContext::new("file(\"Cargo.toml\")"),
+            None,
+            "file(\"Cargo.toml\")",
+        )
+        .unwrap()
+        .1;
 
-        match result.0.as_slice() {
+        match result.conditions() {
             [Condition::Function(Function::FilePath(f))] => {
                 assert_eq!(&PathBuf::from("Cargo.toml"), f);
             }
@@ -484,11 +1223,15 @@ mod tests {
 
     #[test]
     fn compound_condition_parse_should_handle_multiple_conditions() {
-        let result = CompoundCondition::parse("file(\"Cargo.toml\") and file(\"README.md\")")
-            .unwrap()
-            .1;
+        let result = CompoundCondition::parse(
+            ParsingContext::new("file(\"Cargo.toml\") and file(\"README.md\")"),
+            None,
+            "file(\"Cargo.toml\") and file(\"README.md\")",
+        )
+        .unwrap()
+        .1;
 
-        match result.0.as_slice() {
+        match result.conditions() {
             [Condition::Function(Function::FilePath(f1)), Condition::Function(Function::FilePath(f2))] =>
             {
                 assert_eq!(&PathBuf::from("Cargo.toml"), f1);
@@ -500,7 +1243,12 @@ mod tests {
 
     #[test]
     fn condition_parse_should_handle_a_function() {
-        let result = Condition::parse("file(\"Cargo.toml\")").unwrap().1;
+        let result = Condition::parse(
+            ParsingContext::new("file(\"Cargo.toml\")"),
+            "file(\"Cargo.toml\")",
+        )
+        .unwrap()
+        .1;
 
         match result {
             Condition::Function(Function::FilePath(f)) => {
@@ -512,7 +1260,12 @@ mod tests {
 
     #[test]
     fn condition_parse_should_handle_an_inverted_function() {
-        let result = Condition::parse("not file(\"Cargo.toml\")").unwrap().1;
+        let result = Condition::parse(
+            ParsingContext::new("not file(\"Cargo.toml\")"),
+            "not file(\"Cargo.toml\")",
+        )
+        .unwrap()
+        .1;
 
         match result {
             Condition::InvertedFunction(Function::FilePath(f)) => {
@@ -524,7 +1277,12 @@ mod tests {
 
     #[test]
     fn condition_parse_should_handle_an_expression_in_parentheses() {
-        let result = Condition::parse("(not file(\"Cargo.toml\"))").unwrap().1;
+        let result = Condition::parse(
+            ParsingContext::new("(not file(\"Cargo.toml\"))"),
+            "(not file(\"Cargo.toml\"))",
+        )
+        .unwrap()
+        .1;
 
         match result {
             Condition::Expression(_) => {}
@@ -534,7 +1292,12 @@ mod tests {
 
     #[test]
     fn condition_parse_should_handle_an_expression_in_parentheses_with_whitespace() {
-        let result = Condition::parse("( not file(\"Cargo.toml\") )").unwrap().1;
+        let result = Condition::parse(
+            ParsingContext::new("( not file(\"Cargo.toml\") )"),
+            "( not file(\"Cargo.toml\") )",
+        )
+        .unwrap()
+        .1;
 
         match result {
             Condition::Expression(_) => {}
@@ -544,7 +1307,12 @@ mod tests {
 
     #[test]
     fn condition_parse_should_handle_an_inverted_expression_in_parentheses() {
-        let result = Condition::parse("not(not file(\"Cargo.toml\"))").unwrap().1;
+        let result = Condition::parse(
+            ParsingContext::new("not(not file(\"Cargo.toml\"))"),
+            "not(not file(\"Cargo.toml\"))",
+        )
+        .unwrap()
+        .1;
 
         match result {
             Condition::InvertedExpression(_) => {}
@@ -554,9 +1322,12 @@ mod tests {
 
     #[test]
     fn condition_parse_should_handle_an_inverted_expression_in_parentheses_with_whitespace() {
-        let result = Condition::parse("not ( not file(\"Cargo.toml\") )")
-            .unwrap()
-            .1;
+        let result = Condition::parse(
+            ParsingContext::new("not ( not file(\"Cargo.toml\") )"),
+            "not ( not file(\"Cargo.toml\") )",
+        )
+        .unwrap()
+        .1;
 
         match result {
             Condition::InvertedExpression(_) => {}
@@ -581,7 +1352,7 @@ mod tests {
     fn condition_eval_should_return_expression_eval_for_an_expression_condition() {
         let state = state(".");
 
-        let condition = Condition::Expression(Expression(vec![CompoundCondition(vec![
+        let condition = Condition::Expression(Expression::new(vec![CompoundCondition::new(vec![
             Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
         ])]));
 
@@ -606,9 +1377,10 @@ mod tests {
     fn condition_eval_should_return_inverse_of_expression_eval_for_a_not_expression_condition() {
         let state = state(".");
 
-        let condition = Condition::InvertedExpression(Expression(vec![CompoundCondition(vec![
-            Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
-        ])]));
+        let condition =
+            Condition::InvertedExpression(Expression::new(vec![CompoundCondition::new(vec![
+                Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
+            ])]));
 
         assert!(!condition.eval(&state).unwrap());
     }
@@ -630,7 +1402,7 @@ mod tests {
 
     #[test]
     fn condition_fmt_should_format_expression_correctly() {
-        let condition = Condition::Expression(Expression(vec![CompoundCondition(vec![
+        let condition = Condition::Expression(Expression::new(vec![CompoundCondition::new(vec![
             Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
         ])]));
 
@@ -639,9 +1411,10 @@ mod tests {
 
     #[test]
     fn condition_fmt_should_format_inverted_expression_correctly() {
-        let condition = Condition::InvertedExpression(Expression(vec![CompoundCondition(vec![
-            Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
-        ])]));
+        let condition =
+            Condition::InvertedExpression(Expression::new(vec![CompoundCondition::new(vec![
+                Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
+            ])]));
 
         assert_eq!("not (file(\"Cargo.toml\"))", &format!("{condition}"));
     }
@@ -650,7 +1423,7 @@ mod tests {
     fn compound_condition_eval_should_be_true_if_all_conditions_are_true() {
         let state = state(".");
 
-        let compound_condition = CompoundCondition(vec![
+        let compound_condition = CompoundCondition::new(vec![
             Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
             Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
         ]);
@@ -662,7 +1435,7 @@ mod tests {
     fn compound_condition_eval_should_be_false_if_any_condition_is_false() {
         let state = state(".");
 
-        let compound_condition = CompoundCondition(vec![
+        let compound_condition = CompoundCondition::new(vec![
             Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
             Condition::Function(Function::FilePath(PathBuf::from("missing"))),
         ]);
@@ -676,7 +1449,7 @@ mod tests {
         let path = "Cargo.toml";
 
         // If the second function is evaluated, it will result in an error.
-        let compound_condition = CompoundCondition(vec![
+        let compound_condition = CompoundCondition::new(vec![
             Condition::InvertedFunction(Function::Readable(PathBuf::from(path))),
             Condition::Function(Function::ProductVersion(
                 PathBuf::from(path),
@@ -690,7 +1463,7 @@ mod tests {
 
     #[test]
     fn compound_condition_fmt_should_format_correctly() {
-        let compound_condition = CompoundCondition(vec![
+        let compound_condition = CompoundCondition::new(vec![
             Condition::Function(Function::FilePath(PathBuf::from("Cargo.toml"))),
             Condition::Function(Function::FilePath(PathBuf::from("missing"))),
         ]);
@@ -700,9 +1473,9 @@ mod tests {
             &format!("{compound_condition}")
         );
 
-        let compound_condition = CompoundCondition(vec![Condition::Function(Function::FilePath(
-            PathBuf::from("Cargo.toml"),
-        ))]);
+        let compound_condition = CompoundCondition::new(vec![Condition::Function(
+            Function::FilePath(PathBuf::from("Cargo.toml")),
+        )]);
 
         assert_eq!("file(\"Cargo.toml\")", &format!("{compound_condition}"));
     }
@@ -711,11 +1484,11 @@ mod tests {
     fn expression_eval_should_be_true_if_any_compound_condition_is_true() {
         let state = state(".");
 
-        let expression = Expression(vec![
-            CompoundCondition(vec![Condition::Function(Function::FilePath(
+        let expression = Expression::new(vec![
+            CompoundCondition::new(vec![Condition::Function(Function::FilePath(
                 PathBuf::from("Cargo.toml"),
             ))]),
-            CompoundCondition(vec![Condition::Function(Function::FilePath(
+            CompoundCondition::new(vec![Condition::Function(Function::FilePath(
                 PathBuf::from("missing"),
             ))]),
         ]);
@@ -726,11 +1499,11 @@ mod tests {
     fn expression_eval_should_be_false_if_all_compound_conditions_are_false() {
         let state = state(".");
 
-        let expression = Expression(vec![
-            CompoundCondition(vec![Condition::Function(Function::FilePath(
+        let expression = Expression::new(vec![
+            CompoundCondition::new(vec![Condition::Function(Function::FilePath(
                 PathBuf::from("missing"),
             ))]),
-            CompoundCondition(vec![Condition::Function(Function::FilePath(
+            CompoundCondition::new(vec![Condition::Function(Function::FilePath(
                 PathBuf::from("missing"),
             ))]),
         ]);
@@ -739,11 +1512,11 @@ mod tests {
 
     #[test]
     fn expression_fmt_should_format_correctly() {
-        let expression = Expression(vec![
-            CompoundCondition(vec![Condition::Function(Function::FilePath(
+        let expression = Expression::new(vec![
+            CompoundCondition::new(vec![Condition::Function(Function::FilePath(
                 PathBuf::from("Cargo.toml"),
             ))]),
-            CompoundCondition(vec![Condition::Function(Function::FilePath(
+            CompoundCondition::new(vec![Condition::Function(Function::FilePath(
                 PathBuf::from("missing"),
             ))]),
         ]);
@@ -753,10 +1526,99 @@ mod tests {
             &format!("{expression}")
         );
 
-        let expression = Expression(vec![CompoundCondition(vec![Condition::Function(
+        let expression = Expression::new(vec![CompoundCondition::new(vec![Condition::Function(
             Function::FilePath(PathBuf::from("Cargo.toml")),
         )])]);
 
         assert_eq!("file(\"Cargo.toml\")", &format!("{expression}"));
     }
+
+    fn function(string: &str) -> Expression {
+        Function::file(string).unwrap().into()
+    }
+
+    fn assert_round_trips(expression: &Expression) {
+        let string = expression.to_string();
+
+        assert_eq!(expression, &Expression::from_str(&string).unwrap());
+    }
+
+    #[test]
+    fn expression_any_of_should_join_expressions_with_or() {
+        let expression = Expression::any_of([
+            Expression::all_of([function("a.esp"), function("b.esp")]).unwrap(),
+            function("c.esp"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            "file(\"a.esp\") and file(\"b.esp\") or file(\"c.esp\")",
+            expression.to_string()
+        );
+        assert_round_trips(&expression);
+    }
+
+    #[test]
+    fn expression_all_of_should_wrap_expressions_containing_or_in_parentheses() {
+        let expression = Expression::all_of([
+            Expression::any_of([function("a.esp"), function("b.esp")]).unwrap(),
+            function("c.esp"),
+        ])
+        .unwrap();
+
+        assert_eq!(
+            "(file(\"a.esp\") or file(\"b.esp\")) and file(\"c.esp\")",
+            expression.to_string()
+        );
+        assert_round_trips(&expression);
+    }
+
+    #[test]
+    fn expression_all_of_no_expressions_should_be_none() {
+        assert!(Expression::all_of([]).is_none());
+    }
+
+    #[test]
+    fn expression_any_of_no_expressions_should_be_none() {
+        assert!(Expression::any_of([]).is_none());
+    }
+
+    #[test]
+    fn expression_not_should_invert_a_single_function() {
+        let expression = !function("a.esp");
+
+        assert_eq!("not file(\"a.esp\")", expression.to_string());
+        assert_round_trips(&expression);
+    }
+
+    #[test]
+    fn expression_not_should_invert_a_compound_expression() {
+        let expression = !Expression::all_of([function("a.esp"), !function("b.esp")]).unwrap();
+
+        assert_eq!(
+            "not (file(\"a.esp\") and not file(\"b.esp\"))",
+            expression.to_string()
+        );
+        assert_round_trips(&expression);
+
+        let expression = !!function("a.esp");
+
+        assert_eq!("not (not file(\"a.esp\"))", expression.to_string());
+        assert_round_trips(&expression);
+    }
+
+    #[test]
+    fn expression_built_from_regex_functions_should_round_trip() {
+        let expression = Expression::any_of([
+            Function::file("subdir/Blank.*\\.esp").unwrap().into(),
+            Function::many("Blank.*").unwrap().into(),
+            !Expression::from(Function::active("Blank.*").unwrap()),
+            Function::filename_version("Blank (\\d+)\\.esp", ComparisonOperator::GreaterThan, "1")
+                .unwrap()
+                .into(),
+        ])
+        .unwrap();
+
+        assert_round_trips(&expression);
+    }
 }
@@ -0,0 +1,223 @@
+//! Evaluation of expressions when the results of some functions are not yet
+//! known, e.g. because the load order or the installed files aren't known.
+use std::ops::Not;
+
+use crate::{CompoundCondition, Condition, Error, Expression, Function, State};
+
+/// The result of [`Expression::eval_partial`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum PartialResult {
+    True,
+    False,
+    /// The result depends on functions whose results are unknown. The
+    /// expression is what remains of the evaluated expression once the known
+    /// results have been substituted in, simplified, so that it only contains
+    /// the unknown functions.
+    Unknown(Expression),
+}
+
+impl From<bool> for PartialResult {
+    fn from(result: bool) -> Self {
+        if result {
+            PartialResult::True
+        } else {
+            PartialResult::False
+        }
+    }
+}
+
+impl Not for PartialResult {
+    type Output = PartialResult;
+
+    fn not(self) -> Self::Output {
+        match self {
+            PartialResult::True => PartialResult::False,
+            PartialResult::False => PartialResult::True,
+            PartialResult::Unknown(e) => PartialResult::Unknown(!e),
+        }
+    }
+}
+
+pub(crate) fn eval_partial(
+    expression: &Expression,
+    state: &State,
+    is_unknown: &dyn Fn(&Function) -> bool,
+) -> Result<PartialResult, Error> {
+    let evaluator = PartialEvaluator { state, is_unknown };
+
+    match evaluator.expression(expression)? {
+        PartialResult::Unknown(e) => Ok(PartialResult::Unknown(e.simplify())),
+        result => Ok(result),
+    }
+}
+
+struct PartialEvaluator<'a> {
+    state: &'a State,
+    is_unknown: &'a dyn Fn(&Function) -> bool,
+}
+
+impl PartialEvaluator<'_> {
+    /// An expression is true if any of its compound conditions are true, and
+    /// false if they're all false, whatever the unknown functions' results.
+    fn expression(&self, expression: &Expression) -> Result<PartialResult, Error> {
+        let mut residual = Vec::new();
+
+        for compound_condition in expression.compound_conditions() {
+            match self.compound_condition(compound_condition)? {
+                PartialResult::True => return Ok(PartialResult::True),
+                PartialResult::False => {}
+                PartialResult::Unknown(e) => residual.extend(e.compound_conditions),
+            }
+        }
+
+        if residual.is_empty() {
+            Ok(PartialResult::False)
+        } else {
+            Ok(PartialResult::Unknown(Expression::new(residual)))
+        }
+    }
+
+    /// A compound condition is false if any of its conditions are false, and
+    /// true if they're all true, whatever the unknown functions' results.
+    fn compound_condition(
+        &self,
+        compound_condition: &CompoundCondition,
+    ) -> Result<PartialResult, Error> {
+        let mut residual = Vec::new();
+
+        for condition in compound_condition.conditions() {
+            match self.condition(condition)? {
+                PartialResult::True => {}
+                PartialResult::False => return Ok(PartialResult::False),
+                PartialResult::Unknown(e) => residual.extend(e.into_conditions()),
+            }
+        }
+
+        if residual.is_empty() {
+            Ok(PartialResult::True)
+        } else {
+            Ok(PartialResult::Unknown(Expression::new(vec![
+                CompoundCondition::new(residual),
+            ])))
+        }
+    }
+
+    fn condition(&self, condition: &Condition) -> Result<PartialResult, Error> {
+        match condition {
+            Condition::Function(f) => self.function(f),
+            Condition::InvertedFunction(f) => self.function(f).map(Not::not),
+            Condition::Expression(e) => self.expression(e),
+            Condition::InvertedExpression(e) => self.expression(e).map(Not::not),
+            Condition::Invalid(i) => Err(Error::UnconsumedInput(i.clone())),
+        }
+    }
+
+    fn function(&self, function: &Function) -> Result<PartialResult, Error> {
+        if (self.is_unknown)(function) {
+            Ok(PartialResult::Unknown(function.clone().into()))
+        } else {
+            function.eval(self.state).map(PartialResult::from)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    use crate::GameType;
+
+    fn state() -> State {
+        State::new(GameType::Oblivion, "does-not-exist".into())
+            .with_active_plugins(&["Blank.esp", "Blank.esm"])
+            .with_load_order(&["Blank.esm", "Blank.esp"])
+    }
+
+    fn eval_partial(string: &str) -> PartialResult {
+        Expression::from_str(string)
+            .unwrap()
+            .eval_partial(&state(), Function::accesses_filesystem)
+            .unwrap()
+    }
+
+    fn unknown(string: &str) -> PartialResult {
+        PartialResult::Unknown(Expression::from_str(string).unwrap())
+    }
+
+    #[test]
+    fn eval_partial_should_be_known_if_no_functions_are_unknown() {
+        assert_eq!(PartialResult::True, eval_partial("active(\"Blank.esp\")"));
+        assert_eq!(
+            PartialResult::False,
+            eval_partial("not active(\"Blank.esp\")")
+        );
+        assert_eq!(
+            PartialResult::True,
+            eval_partial("active(\"a.esp\") or many_active(\"Blank\\.es(p|m)\")")
+        );
+    }
+
+    #[test]
+    fn eval_partial_should_evaluate_load_order_functions() {
+        assert_eq!(
+            PartialResult::True,
+            eval_partial(
+                "load_after(\"Blank.esp\", \"Blank.esm\") and load_index(\"Blank.esm\", ==, 0)"
+            )
+        );
+    }
+
+    #[test]
+    fn eval_partial_should_be_known_if_unknown_functions_do_not_affect_the_result() {
+        assert_eq!(
+            PartialResult::True,
+            eval_partial("file(\"a.esp\") or active(\"Blank.esp\")")
+        );
+        assert_eq!(
+            PartialResult::False,
+            eval_partial("file(\"a.esp\") and active(\"a.esp\")")
+        );
+        assert_eq!(
+            PartialResult::False,
+            eval_partial("not (file(\"a.esp\") or active(\"Blank.esm\"))")
+        );
+    }
+
+    #[test]
+    fn eval_partial_should_give_the_residual_expression_if_the_result_is_unknown() {
+        assert_eq!(
+            unknown("file(\"a.esp\")"),
+            eval_partial("file(\"a.esp\") and active(\"Blank.esp\")")
+        );
+        assert_eq!(
+            unknown("file(\"a.esp\") or is_master(\"b.esp\")"),
+            eval_partial("(file(\"a.esp\") and active(\"Blank.esp\")) or active(\"b.esp\") or is_master(\"b.esp\")")
+        );
+    }
+
+    #[test]
+    fn eval_partial_should_simplify_the_residual_expression() {
+        assert_eq!(
+            unknown("not file(\"a.esp\") and not checksum(\"b.esp\", DEADBEEF)"),
+            eval_partial(
+                "not (active(\"a.esp\") or file(\"a.esp\") or checksum(\"b.esp\", DEADBEEF))"
+            )
+        );
+        assert_eq!(
+            unknown("file(\"a.esp\")"),
+            eval_partial("(file(\"a.esp\") and not active(\"b.esp\")) or (file(\"a.esp\"))")
+        );
+    }
+
+    #[test]
+    fn eval_partial_should_error_if_it_reaches_an_invalid_condition() {
+        let recovered = Expression::parse_recovering("file(\"a.esp\") and fiel(\"b.esp\")");
+
+        assert!(recovered
+            .expression()
+            .eval_partial(&state(), Function::accesses_filesystem)
+            .is_err());
+    }
+}
@@ -0,0 +1,389 @@
+//! Parsing that keeps going after it finds a problem, so that every problem
+//! in an expression can be reported at once. It uses the expression parser,
+//! which records errors in its context instead of failing, and skips over any
+//! condition that can't be parsed up to the next 'and', 'or' or closing
+//! parenthesis.
+use std::cell::RefCell;
+use std::fmt;
+
+use nom::combinator::consumed;
+use nom::error::{ErrorKind, ParseError};
+use nom::{Err, Parser};
+
+use crate::error::{ParsingError, ParsingErrorKind};
+use crate::{keyword, parse_source, Condition, Error, Expression, ParsingContext, ParsingResult};
+
+/// An expression parsed by [`Expression::parse_recovering`], and the problems
+/// that were found while parsing it.
+#[derive(Debug)]
+pub struct RecoveredExpression {
+    expression: Expression,
+    errors: Vec<Error>,
+}
+
+impl RecoveredExpression {
+    /// The parsed expression, which contains a [`Condition::Invalid`] in place
+    /// of each condition that could not be parsed.
+    pub fn expression(&self) -> &Expression {
+        &self.expression
+    }
+
+    pub fn into_expression(self) -> Expression {
+        self.expression
+    }
+
+    /// The problems that were found, in the order that they appear in the
+    /// parsed string. If there are none, the expression is the same as the
+    /// one that [`str::parse`] would give.
+    pub fn errors(&self) -> &[Error] {
+        &self.errors
+    }
+}
+
+/// Lists each problem on its own line, prefixed by its location.
+impl fmt::Display for RecoveredExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, error) in self.errors.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            match (error, error.span()) {
+                (Error::ParsingError(_, kind, ..), Some(span)) => write!(
+                    f,
+                    "line {}, column {}: {kind}",
+                    span.start().line(),
+                    span.start().column()
+                )?,
+                (e, _) => write!(f, "{e}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub(crate) fn parse_recovering(source: &str) -> RecoveredExpression {
+    let errors = RefCell::new(Vec::new());
+    let context = ParsingContext {
+        recovered_errors: Some(&errors),
+        ..ParsingContext::new(source)
+    };
+
+    let expression = match parse_source(context) {
+        Ok((remaining_input, expression)) => {
+            if !remaining_input.trim_start().is_empty() {
+                errors
+                    .borrow_mut()
+                    .push(Error::UnconsumedInput(remaining_input.to_owned()));
+            }
+            expression
+        }
+        Err(e) => {
+            errors
+                .borrow_mut()
+                .push(Error::from_parsing_error(source, e));
+            Expression::default()
+        }
+    };
+
+    let mut errors = errors.into_inner();
+    errors.sort_by_key(|error| error.span().map_or(source.len(), |s| s.start().offset()));
+
+    RecoveredExpression { expression, errors }
+}
+
+/// Parses a condition, which is optional unless it comes `after` a keyword.
+/// When parsing with recovery, a condition that can't be parsed is skipped
+/// and given as a [`Condition::Invalid`].
+pub(crate) fn recover_condition<'a>(
+    context: ParsingContext<'a>,
+    after: Option<&'static str>,
+    input: &'a str,
+) -> ParsingResult<'a, Option<Condition>> {
+    let trimmed_input = input.trim_start();
+
+    let error = match Condition::parse(context, input) {
+        Ok((remaining_input, condition)) => return Ok((remaining_input, Some(condition))),
+        Err(Err::Failure(error)) => error,
+        Err(Err::Error(_)) => match after {
+            Some(keyword) => missing_condition(keyword, trimmed_input),
+            None if context.recovering() && !ends_condition(trimmed_input) => {
+                let (_, skipped) = skip_condition(trimmed_input);
+                ParsingErrorKind::InvalidCondition.spanning(skipped)
+            }
+            None => return Ok((input, None)),
+        },
+        Err(e) => return Err(e),
+    };
+
+    context.recover(error)?;
+
+    if ends_condition(trimmed_input) {
+        Ok((input, None))
+    } else {
+        let (remaining_input, skipped) = skip_condition(trimmed_input);
+        Ok((
+            remaining_input,
+            Some(Condition::Invalid(skipped.to_owned())),
+        ))
+    }
+}
+
+/// Parses what comes after a condition that isn't followed by a keyword. When
+/// parsing with recovery, an unmatched closing parenthesis is skipped, and
+/// anything else that isn't the end of a condition is parsed as if it
+/// followed 'and'.
+pub(crate) fn recover_missing_operator<'a>(
+    context: ParsingContext<'a>,
+    input: &'a str,
+) -> ParsingResult<'a, (&'a str, Option<Condition>)> {
+    let trimmed_input = input.trim_start();
+
+    if let Some(remaining_input) = trimmed_input.strip_prefix(')') {
+        if !context.nested {
+            context.recover(
+                ParsingErrorKind::UnmatchedClosingParenthesis
+                    .at(trimmed_input)
+                    .with_length(1),
+            )?;
+            return Ok((remaining_input, ("", None)));
+        }
+    }
+
+    if !context.recovering() || ends_condition(trimmed_input) {
+        return Err(Err::Error(ParsingError::from_error_kind(
+            input,
+            ErrorKind::Tag,
+        )));
+    }
+
+    context.recover(
+        ParsingErrorKind::ExpectedOperator
+            .at(trimmed_input)
+            .with_length(0),
+    )?;
+
+    match consumed(|i| Condition::parse(context, i)).parse(trimmed_input) {
+        Ok((remaining_input, (consumed_input, condition))) => {
+            Ok((remaining_input, (consumed_input, Some(condition))))
+        }
+        Err(Err::Failure(error)) => {
+            context.recover(error)?;
+            Ok(skip_invalid_condition(trimmed_input))
+        }
+        Err(Err::Error(_)) => Ok(skip_invalid_condition(trimmed_input)),
+        Err(e) => Err(e),
+    }
+}
+
+/// The error for a condition that is missing after the given keyword, which
+/// spans whatever is in its place.
+pub(crate) fn missing_condition<'a>(
+    keyword: &'static str,
+    input: &'a str,
+) -> ParsingError<&'a str> {
+    let trimmed_input = input.trim_start();
+    let kind = ParsingErrorKind::ExpectedCondition(keyword);
+
+    if ends_condition(trimmed_input) {
+        kind.at(trimmed_input).with_length(0)
+    } else {
+        let (_, skipped) = skip_condition(trimmed_input);
+        kind.spanning(skipped)
+    }
+}
+
+fn skip_invalid_condition(input: &str) -> (&str, (&str, Option<Condition>)) {
+    let (remaining_input, skipped) = skip_condition(input);
+    (
+        remaining_input,
+        (skipped, Some(Condition::Invalid(skipped.to_owned()))),
+    )
+}
+
+fn starts_keyword(input: &str) -> bool {
+    ["and", "or"].into_iter().any(|k| keyword(k)(input).is_ok())
+}
+
+fn ends_condition(input: &str) -> bool {
+    input.is_empty() || input.starts_with(')') || starts_keyword(input)
+}
+
+/// Splits off the input up to the next 'and', 'or' or closing parenthesis that
+/// is not inside a string or a pair of parentheses. The skipped input is
+/// returned without trailing whitespace, and always includes at least the
+/// first character of the input, so that parsing makes progress.
+fn skip_condition(input: &str) -> (&str, &str) {
+    let mut in_string = false;
+    let mut depth = 0_usize;
+    let mut previous = None;
+    let mut end = input.len();
+
+    for (index, c) in input.char_indices() {
+        if in_string {
+            in_string = c != '"';
+        } else {
+            match c {
+                '"' => in_string = true,
+                '(' => depth += 1,
+                ')' if depth > 0 => depth -= 1,
+                _ if depth == 0 && index > 0 => {
+                    let rest = input.get(index..).unwrap_or_default();
+                    let at_keyword = previous.is_some_and(|p: char| p.is_whitespace() || p == ')')
+                        && starts_keyword(rest);
+
+                    if c == ')' || at_keyword {
+                        end = index;
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+        previous = Some(c);
+    }
+
+    let (skipped, remaining_input) = input.split_at(end);
+    (remaining_input, skipped.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::ops::Range;
+    use std::str::FromStr;
+
+    use crate::{Function, FunctionArgument};
+
+    fn errors(recovered: &RecoveredExpression) -> Vec<(ParsingErrorKind, Range<usize>)> {
+        recovered
+            .errors()
+            .iter()
+            .map(|e| match e {
+                Error::ParsingError(_, kind, span) => (kind.clone(), span.range()),
+                e => panic!("Expected a parsing error, got {e:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_recovering_should_give_the_same_expression_as_from_str_for_valid_input() {
+        let input = "file(\"a.esp\") and (not active(\"b.esp\") or ()) or not (many(\"c.*\"))";
+        let recovered = Expression::parse_recovering(input);
+        let expression = Expression::from_str(input).unwrap();
+
+        assert!(recovered.errors().is_empty());
+        assert_eq!(&expression, recovered.expression());
+        assert_eq!(expression.span(), recovered.expression().span());
+        assert_eq!(
+            expression.compound_conditions()[0].condition_span(1),
+            recovered.expression().compound_conditions()[0].condition_span(1)
+        );
+    }
+
+    #[test]
+    fn parse_recovering_should_accept_keywords_that_are_directly_followed_by_a_function() {
+        let input = "notfile(\"a.esp\") andfile(\"b.esp\")orfile(\"c.esp\")";
+        let recovered = Expression::parse_recovering(input);
+
+        assert!(recovered.errors().is_empty());
+        assert_eq!(
+            &Expression::from_str(input).unwrap(),
+            recovered.expression()
+        );
+    }
+
+    #[test]
+    fn parse_recovering_should_report_every_bad_function_call() {
+        let input = "fiel(\"a.esp\") and active(\"b.esp\") or (checksum(\"c.esp\", XYZ) or is_master(\"d.esm\"))";
+        let recovered = Expression::parse_recovering(input);
+
+        assert_eq!(
+            vec![
+                (
+                    ParsingErrorKind::UnknownFunction("fiel".into(), Some("file")),
+                    0..4
+                ),
+                (
+                    ParsingErrorKind::ExpectedArgument {
+                        function: "checksum",
+                        expected: FunctionArgument::Crc,
+                        after: Some(FunctionArgument::Path)
+                    },
+                    56..59
+                ),
+            ],
+            errors(&recovered)
+        );
+        assert_eq!(
+            "fiel(\"a.esp\") and active(\"b.esp\") or (checksum(\"c.esp\", XYZ) or is_master(\"d.esm\"))",
+            recovered.expression().to_string()
+        );
+    }
+
+    #[test]
+    fn parse_recovering_should_replace_bad_conditions_with_invalid_nodes() {
+        let recovered = Expression::parse_recovering("not fiel(\"a.esp\") and file(\"b.esp\")");
+        let compound_condition = &recovered.expression().compound_conditions()[0];
+
+        assert_eq!(
+            &[
+                Condition::Invalid("not fiel(\"a.esp\")".into()),
+                Condition::Function(Function::FilePath("b.esp".into())),
+            ],
+            compound_condition.conditions()
+        );
+        assert_eq!(0..17, compound_condition.condition_span(0).unwrap().range());
+    }
+
+    #[test]
+    fn parse_recovering_should_report_misplaced_parentheses_and_missing_keywords() {
+        let recovered = Expression::parse_recovering(
+            "(file(\"a.esp\") file(\"b.esp\")) ) and (is_master(\"c.esm\") or",
+        );
+
+        assert_eq!(
+            vec![
+                (ParsingErrorKind::ExpectedOperator, 15..15),
+                (ParsingErrorKind::UnmatchedClosingParenthesis, 30..31),
+                (ParsingErrorKind::UnbalancedParenthesis, 36..37),
+                (ParsingErrorKind::ExpectedCondition("or"), 58..58),
+            ],
+            errors(&recovered)
+        );
+        assert_eq!(
+            "(file(\"a.esp\") and file(\"b.esp\")) and (is_master(\"c.esm\"))",
+            recovered.expression().to_string()
+        );
+    }
+
+    #[test]
+    fn parse_recovering_should_skip_input_that_is_not_a_condition() {
+        let recovered =
+            Expression::parse_recovering("file(\"a.esp\") and = 1 or file(\"b.esp\") \"c\"");
+
+        assert_eq!(
+            vec![
+                (ParsingErrorKind::ExpectedCondition("and"), 18..21),
+                (ParsingErrorKind::ExpectedOperator, 39..39),
+            ],
+            errors(&recovered)
+        );
+        assert_eq!(
+            "file(\"a.esp\") and = 1 or file(\"b.esp\") and \"c\"",
+            recovered.expression().to_string()
+        );
+    }
+
+    #[test]
+    fn recovered_expression_display_should_list_every_error_with_its_location() {
+        let recovered = Expression::parse_recovering("fiel(\"a.esp\")\nor actve(\"b.esp\")");
+
+        assert_eq!(
+            "line 1, column 1: Unknown function `fiel`, did you mean `file`?\nline 2, column 4: Unknown function `actve`, did you mean `active`?",
+            recovered.to_string()
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use crate::visit::{fold_compound_condition, fold_expression, Fold};
+use crate::{CompoundCondition, Condition, Expression};
+
+pub(crate) fn simplify(expression: Expression) -> Expression {
+    Simplifier.fold_expression(expression)
+}
+
+struct Simplifier;
+
+impl Fold for Simplifier {
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        let expression = fold_expression(self, expression);
+
+        let mut compound_conditions: Vec<CompoundCondition> = Vec::new();
+        for compound_condition in expression.compound_conditions {
+            match <[Condition; 1]>::try_from(compound_condition.conditions) {
+                Ok([Condition::Expression(inner)]) => {
+                    for inner_compound_condition in inner.compound_conditions {
+                        push_unique(&mut compound_conditions, inner_compound_condition);
+                    }
+                }
+                Ok([condition]) => {
+                    push_unique(
+                        &mut compound_conditions,
+                        CompoundCondition::new(vec![condition]),
+                    );
+                }
+                Err(conditions) => {
+                    push_unique(&mut compound_conditions, CompoundCondition::new(conditions));
+                }
+            }
+        }
+
+        // A contradictory compound condition can never be true, so it can be
+        // removed unless it's all that's left, as the expression must still
+        // evaluate to false.
+        if compound_conditions.iter().any(|c| !is_contradiction(c)) {
+            compound_conditions.retain(|c| !is_contradiction(c));
+        } else {
+            compound_conditions.truncate(1);
+        }
+
+        Expression::new(compound_conditions)
+    }
+
+    fn fold_compound_condition(
+        &mut self,
+        compound_condition: CompoundCondition,
+    ) -> CompoundCondition {
+        let compound_condition = fold_compound_condition(self, compound_condition);
+
+        let mut conditions: Vec<Condition> = Vec::new();
+        for condition in compound_condition.conditions {
+            match condition {
+                Condition::Expression(inner) if inner.compound_conditions.len() == 1 => {
+                    for inner_condition in inner
+                        .compound_conditions
+                        .into_iter()
+                        .flat_map(|c| c.conditions)
+                    {
+                        push_unique(&mut conditions, inner_condition);
+                    }
+                }
+                c => push_unique(&mut conditions, c),
+            }
+        }
+
+        CompoundCondition::new(conditions)
+    }
+
+    fn fold_condition(&mut self, condition: Condition) -> Condition {
+        match condition {
+            Condition::Function(_) | Condition::InvertedFunction(_) | Condition::Invalid(_) => {
+                condition
+            }
+            Condition::Expression(e) => {
+                let expression = self.fold_expression(e);
+
+                match <[CompoundCondition; 1]>::try_from(expression.compound_conditions) {
+                    Ok([compound_condition]) => {
+                        match <[Condition; 1]>::try_from(compound_condition.conditions) {
+                            Ok([condition]) => condition,
+                            Err(conditions) => Condition::Expression(Expression::new(vec![
+                                CompoundCondition::new(conditions),
+                            ])),
+                        }
+                    }
+                    Err(compound_conditions) => {
+                        Condition::Expression(Expression::new(compound_conditions))
+                    }
+                }
+            }
+            Condition::InvertedExpression(e) => {
+                let negated = negate_expression(e);
+                self.fold_condition(Condition::Expression(Expression::new(vec![negated])))
+            }
+        }
+    }
+}
+
+fn push_unique<T: PartialEq>(vec: &mut Vec<T>, value: T) {
+    if !vec.contains(&value) {
+        vec.push(value);
+    }
+}
+
+fn is_contradiction(compound_condition: &CompoundCondition) -> bool {
+    compound_condition
+        .conditions
+        .iter()
+        .any(|condition| match condition {
+            Condition::Function(f) => compound_condition
+                .conditions
+                .iter()
+                .any(|c| matches!(c, Condition::InvertedFunction(g) if f == g)),
+            _ => false,
+        })
+}
+
+/// Uses De Morgan's laws to push the negation of an expression down to its
+/// functions: not (a or b) is (not a and not b), and not (a and b) is
+/// (not a or not b).
+fn negate_expression(expression: Expression) -> CompoundCondition {
+    CompoundCondition::new(
+        expression
+            .compound_conditions
+            .into_iter()
+            .map(|compound_condition| {
+                match <[Condition; 1]>::try_from(compound_condition.conditions) {
+                    Ok([condition]) => negate_condition(condition),
+                    Err(conditions) => Condition::Expression(Expression::new(
+                        conditions
+                            .into_iter()
+                            .map(|c| CompoundCondition::new(vec![negate_condition(c)]))
+                            .collect(),
+                    )),
+                }
+            })
+            .collect(),
+    )
+}
+
+fn negate_condition(condition: Condition) -> Condition {
+    match condition {
+        Condition::Function(f) => Condition::InvertedFunction(f),
+        Condition::InvertedFunction(f) => Condition::Function(f),
+        Condition::Expression(e) => {
+            Condition::Expression(Expression::new(vec![negate_expression(e)]))
+        }
+        Condition::InvertedExpression(e) => Condition::Expression(e),
+        Condition::Invalid(i) => Condition::Invalid(format!("not ({i})")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::str::FromStr;
+
+    fn simplified(string: &str) -> String {
+        simplify(Expression::from_str(string).unwrap()).to_string()
+    }
+
+    #[test]
+    fn simplify_should_not_change_a_simple_expression() {
+        let string = "file(\"a.esp\") and not active(\"b.esp\") or many(\"c.*\")";
+
+        assert_eq!(string, simplified(string));
+    }
+
+    #[test]
+    fn simplify_should_remove_duplicate_conditions() {
+        assert_eq!(
+            "file(\"a.esp\") and active(\"b.esp\")",
+            simplified("file(\"a.esp\") and active(\"b.esp\") and file(\"A.esp\")")
+        );
+    }
+
+    #[test]
+    fn simplify_should_remove_duplicate_compound_conditions() {
+        assert_eq!(
+            "file(\"a.esp\") and active(\"b.esp\") or file(\"c.esp\")",
+            simplified("file(\"a.esp\") and active(\"b.esp\") or file(\"c.esp\") or file(\"a.esp\") and active(\"b.esp\")")
+        );
+    }
+
+    #[test]
+    fn simplify_should_remove_parentheses_around_a_single_condition() {
+        assert_eq!(
+            "file(\"a.esp\") and not file(\"b.esp\")",
+            simplified("((file(\"a.esp\"))) and (not file(\"b.esp\"))")
+        );
+    }
+
+    #[test]
+    fn simplify_should_flatten_nested_conjunctions_and_disjunctions() {
+        assert_eq!(
+            "file(\"a.esp\") and file(\"b.esp\") and file(\"c.esp\")",
+            simplified("file(\"a.esp\") and (file(\"b.esp\") and file(\"c.esp\"))")
+        );
+        assert_eq!(
+            "file(\"a.esp\") or file(\"b.esp\") or file(\"c.esp\")",
+            simplified("file(\"a.esp\") or (file(\"b.esp\") or file(\"c.esp\"))")
+        );
+    }
+
+    #[test]
+    fn simplify_should_keep_parentheses_that_are_needed() {
+        let string = "file(\"a.esp\") and (file(\"b.esp\") or file(\"c.esp\"))";
+
+        assert_eq!(string, simplified(string));
+    }
+
+    #[test]
+    fn simplify_should_remove_empty_parentheses() {
+        assert_eq!("file(\"a.esp\")", simplified("file(\"a.esp\") and ()"));
+    }
+
+    #[test]
+    fn simplify_should_apply_de_morgans_laws_to_inverted_expressions() {
+        assert_eq!(
+            "not file(\"a.esp\") and not file(\"b.esp\")",
+            simplified("not (file(\"a.esp\") or file(\"b.esp\"))")
+        );
+        assert_eq!(
+            "not file(\"a.esp\") or not file(\"b.esp\")",
+            simplified("not (file(\"a.esp\") and file(\"b.esp\"))")
+        );
+    }
+
+    #[test]
+    fn simplify_should_remove_double_negation() {
+        assert_eq!("file(\"a.esp\")", simplified("not (not file(\"a.esp\"))"));
+        assert_eq!(
+            "file(\"a.esp\") or file(\"b.esp\")",
+            simplified("not (not (file(\"a.esp\") or file(\"b.esp\")))")
+        );
+    }
+
+    #[test]
+    fn simplify_should_apply_de_morgans_laws_to_nested_inverted_expressions() {
+        assert_eq!(
+            "not file(\"a.esp\") and (not file(\"b.esp\") or file(\"c.esp\"))",
+            simplified("not (file(\"a.esp\") or (file(\"b.esp\") and not file(\"c.esp\")))")
+        );
+    }
+
+    #[test]
+    fn simplify_should_remove_contradictory_compound_conditions() {
+        assert_eq!(
+            "active(\"b.esp\")",
+            simplified("file(\"x.esp\") and active(\"a.esp\") and not file(\"X.esp\") or active(\"b.esp\")")
+        );
+    }
+
+    #[test]
+    fn simplify_should_keep_one_contradiction_if_every_compound_condition_is_contradictory() {
+        assert_eq!(
+            "file(\"x.esp\") and not file(\"x.esp\")",
+            simplified("file(\"x.esp\") and not file(\"x.esp\") or active(\"a.esp\") and not active(\"a.esp\")")
+        );
+    }
+
+    #[test]
+    fn simplify_should_detect_contradictions_revealed_by_de_morgans_laws() {
+        assert_eq!(
+            "active(\"b.esp\")",
+            simplified("file(\"x.esp\") and not (file(\"x.esp\") or active(\"a.esp\")) or active(\"b.esp\")")
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use std::ops::Range;
+
+use nom::Offset;
+
+/// A position in a condition string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Location {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Location {
+    fn new(source: &str, offset: usize) -> Self {
+        let preceding = source.get(..offset).unwrap_or(source);
+        let line_start = preceding.rfind('\n').map_or(0, |i| i + 1);
+        let line_prefix = preceding.get(line_start..).unwrap_or_default();
+
+        Location {
+            offset: preceding.len(),
+            line: preceding.matches('\n').count() + 1,
+            column: line_prefix.chars().count() + 1,
+        }
+    }
+
+    /// The byte offset from the start of the string.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The line number, counting from 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The column number in characters, counting from 1.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
+/// The part of a condition string that a parsed node or a parsing error
+/// corresponds to. Spans of parsing errors that are not specific to a piece of
+/// the input are empty, and just mark the point at which the error occurred.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    start: Location,
+    end: Location,
+}
+
+impl Span {
+    /// `slice` must be a subslice of `source`.
+    pub(crate) fn of(source: &str, slice: &str) -> Self {
+        let start = source.offset(slice);
+        Span::new(source, start, start + slice.len())
+    }
+
+    /// The span of what was consumed by a parser that was given `input` and
+    /// left `remaining_input`, ignoring any leading and trailing whitespace.
+    pub(crate) fn consumed(source: &str, input: &str, remaining_input: &str) -> Self {
+        let consumed = input
+            .get(..input.offset(remaining_input))
+            .unwrap_or(input)
+            .trim();
+
+        if consumed.is_empty() {
+            let offset = source.offset(remaining_input);
+            Span::new(source, offset, offset)
+        } else {
+            Span::of(source, consumed)
+        }
+    }
+
+    pub(crate) fn new(source: &str, start: usize, end: usize) -> Self {
+        Span {
+            start: Location::new(source, start),
+            end: Location::new(source, end),
+        }
+    }
+
+    pub fn start(&self) -> Location {
+        self.start
+    }
+
+    /// The location just past the end of the span.
+    pub fn end(&self) -> Location {
+        self.end
+    }
+
+    /// The span's byte offsets, for slicing the string that it refers to.
+    pub fn range(&self) -> Range<usize> {
+        self.start.offset..self.end.offset
+    }
+}
+
+/// The parts of a condition string that a function call was parsed from.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FunctionSpan {
+    span: Span,
+    name: Span,
+    arguments: Vec<Span>,
+}
+
+impl FunctionSpan {
+    pub(crate) fn new(span: Span, name: Span, arguments: Vec<Span>) -> Self {
+        FunctionSpan {
+            span,
+            name,
+            arguments,
+        }
+    }
+
+    /// The span of the whole function call, excluding any 'not'.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// The span of the function's name.
+    pub fn name(&self) -> Span {
+        self.name
+    }
+
+    /// The spans of the function's arguments, in the order that they appear.
+    /// A quoted argument's span includes its quotes.
+    pub fn arguments(&self) -> &[Span] {
+        &self.arguments
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn location_new_should_count_lines_and_columns_from_one() {
+        let location = Location::new("ab\ncd", 0);
+
+        assert_eq!(0, location.offset());
+        assert_eq!(1, location.line());
+        assert_eq!(1, location.column());
+    }
+
+    #[test]
+    fn location_new_should_count_columns_from_the_start_of_the_line() {
+        let location = Location::new("ab\r\ncd\nef", 8);
+
+        assert_eq!(8, location.offset());
+        assert_eq!(3, location.line());
+        assert_eq!(2, location.column());
+    }
+
+    #[test]
+    fn location_new_should_count_columns_in_characters() {
+        let location = Location::new("\u{e9}=f", 3);
+
+        assert_eq!(3, location.offset());
+        assert_eq!(3, location.column());
+    }
+
+    #[test]
+    fn span_of_should_give_the_range_of_the_slice_in_the_source() {
+        let source = "file(\"a.esp\")";
+        let span = Span::of(source, source.get(5..12).unwrap());
+
+        assert_eq!(5..12, span.range());
+        assert_eq!(6, span.start().column());
+        assert_eq!(13, span.end().column());
+    }
+
+    #[test]
+    fn span_consumed_should_ignore_surrounding_whitespace() {
+        let source = " ( a ) and b";
+        let span = Span::consumed(source, source, source.get(7..).unwrap());
+
+        assert_eq!(1..6, span.range());
+    }
+
+    #[test]
+    fn span_consumed_should_be_empty_if_nothing_but_whitespace_was_consumed() {
+        let source = "  b";
+        let span = Span::consumed(source, source, source.get(2..).unwrap());
+
+        assert_eq!(2..2, span.range());
+    }
+}
@@ -0,0 +1,235 @@
+//! Traversal of parsed expressions.
+//!
+//! [`Visitor`] walks an expression tree by reference, while [`Fold`] takes
+//! ownership of the tree and rebuilds it, allowing nodes to be replaced. The
+//! default implementations of each trait's methods call the free function of
+//! the same name in this module, which visits or folds the node's children.
+//! Overriding implementations can call those functions to continue the
+//! traversal below the node that they handle. Nodes rebuilt by the default
+//! fold implementations keep the spans that they were parsed with.
+use crate::{CompoundCondition, Condition, Expression, Function};
+
+pub trait Visitor {
+    fn visit_expression(&mut self, expression: &Expression) {
+        visit_expression(self, expression);
+    }
+
+    fn visit_compound_condition(&mut self, compound_condition: &CompoundCondition) {
+        visit_compound_condition(self, compound_condition);
+    }
+
+    fn visit_condition(&mut self, condition: &Condition) {
+        visit_condition(self, condition);
+    }
+
+    fn visit_function(&mut self, _function: &Function) {}
+}
+
+pub fn visit_expression<V: Visitor + ?Sized>(visitor: &mut V, expression: &Expression) {
+    for compound_condition in expression.compound_conditions() {
+        visitor.visit_compound_condition(compound_condition);
+    }
+}
+
+pub fn visit_compound_condition<V: Visitor + ?Sized>(
+    visitor: &mut V,
+    compound_condition: &CompoundCondition,
+) {
+    for condition in compound_condition.conditions() {
+        visitor.visit_condition(condition);
+    }
+}
+
+pub fn visit_condition<V: Visitor + ?Sized>(visitor: &mut V, condition: &Condition) {
+    match condition {
+        Condition::Function(f) | Condition::InvertedFunction(f) => visitor.visit_function(f),
+        Condition::Expression(e) | Condition::InvertedExpression(e) => {
+            visitor.visit_expression(e);
+        }
+        Condition::Invalid(_) => {}
+    }
+}
+
+pub trait Fold {
+    fn fold_expression(&mut self, expression: Expression) -> Expression {
+        fold_expression(self, expression)
+    }
+
+    fn fold_compound_condition(
+        &mut self,
+        compound_condition: CompoundCondition,
+    ) -> CompoundCondition {
+        fold_compound_condition(self, compound_condition)
+    }
+
+    fn fold_condition(&mut self, condition: Condition) -> Condition {
+        fold_condition(self, condition)
+    }
+
+    fn fold_function(&mut self, function: Function) -> Function {
+        function
+    }
+}
+
+pub fn fold_expression<F: Fold + ?Sized>(folder: &mut F, expression: Expression) -> Expression {
+    Expression {
+        compound_conditions: expression
+            .compound_conditions
+            .into_iter()
+            .map(|c| folder.fold_compound_condition(c))
+            .collect(),
+        ..expression
+    }
+}
+
+pub fn fold_compound_condition<F: Fold + ?Sized>(
+    folder: &mut F,
+    compound_condition: CompoundCondition,
+) -> CompoundCondition {
+    CompoundCondition {
+        conditions: compound_condition
+            .conditions
+            .into_iter()
+            .map(|c| folder.fold_condition(c))
+            .collect(),
+        ..compound_condition
+    }
+}
+
+pub fn fold_condition<F: Fold + ?Sized>(folder: &mut F, condition: Condition) -> Condition {
+    match condition {
+        Condition::Function(f) => Condition::Function(folder.fold_function(f)),
+        Condition::InvertedFunction(f) => Condition::InvertedFunction(folder.fold_function(f)),
+        Condition::Expression(e) => Condition::Expression(folder.fold_expression(e)),
+        Condition::InvertedExpression(e) => {
+            Condition::InvertedExpression(folder.fold_expression(e))
+        }
+        Condition::Invalid(i) => Condition::Invalid(i),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::path::{Path, PathBuf};
+    use std::str::FromStr;
+
+    const EXPRESSION: &str = "file(\"Blank.esm\") and not (active(\"Blank.esp\") or is_master(\"Other.esp\")) or checksum(\"Blank.esm\", DEADBEEF)";
+
+    #[derive(Default)]
+    struct PathCollector(Vec<PathBuf>);
+
+    impl Visitor for PathCollector {
+        fn visit_function(&mut self, function: &Function) {
+            if let Some(path) = function.path() {
+                self.0.push(path.to_path_buf());
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct NodeCounter {
+        expressions: usize,
+        compound_conditions: usize,
+        conditions: usize,
+    }
+
+    impl Visitor for NodeCounter {
+        fn visit_expression(&mut self, expression: &Expression) {
+            self.expressions += 1;
+            visit_expression(self, expression);
+        }
+
+        fn visit_compound_condition(&mut self, compound_condition: &CompoundCondition) {
+            self.compound_conditions += 1;
+            visit_compound_condition(self, compound_condition);
+        }
+
+        fn visit_condition(&mut self, condition: &Condition) {
+            self.conditions += 1;
+            visit_condition(self, condition);
+        }
+    }
+
+    struct PathRenamer;
+
+    impl Fold for PathRenamer {
+        fn fold_function(&mut self, function: Function) -> Function {
+            match function {
+                Function::FilePath(p) if p == Path::new("Blank.esm") => {
+                    Function::FilePath("Renamed.esm".into())
+                }
+                f => f,
+            }
+        }
+    }
+
+    struct Inverter;
+
+    impl Fold for Inverter {
+        fn fold_condition(&mut self, condition: Condition) -> Condition {
+            match condition {
+                Condition::Function(f) => Condition::InvertedFunction(f),
+                c => fold_condition(self, c),
+            }
+        }
+    }
+
+    #[test]
+    fn visitor_should_visit_every_function_in_order() {
+        let expression = Expression::from_str(EXPRESSION).unwrap();
+
+        let mut collector = PathCollector::default();
+        collector.visit_expression(&expression);
+
+        assert_eq!(
+            vec![
+                PathBuf::from("Blank.esm"),
+                PathBuf::from("Blank.esp"),
+                PathBuf::from("Other.esp"),
+                PathBuf::from("Blank.esm"),
+            ],
+            collector.0
+        );
+    }
+
+    #[test]
+    fn visitor_should_visit_nested_expressions() {
+        let expression = Expression::from_str(EXPRESSION).unwrap();
+
+        let mut counter = NodeCounter::default();
+        counter.visit_expression(&expression);
+
+        assert_eq!(2, counter.expressions);
+        assert_eq!(4, counter.compound_conditions);
+        assert_eq!(5, counter.conditions);
+    }
+
+    #[test]
+    fn fold_should_replace_functions_throughout_the_tree() {
+        let expression = Expression::from_str(EXPRESSION).unwrap();
+
+        let folded = PathRenamer.fold_expression(expression);
+
+        assert_eq!(
+            "file(\"Renamed.esm\") and not (active(\"Blank.esp\") or is_master(\"Other.esp\")) or checksum(\"Blank.esm\", DEADBEEF)",
+            folded.to_string()
+        );
+    }
+
+    #[test]
+    fn fold_should_allow_conditions_to_be_replaced() {
+        let expression = Expression::from_str(
+            "file(\"Blank.esm\") and (not active(\"Blank.esp\") or active(\"Blank.esm\"))",
+        )
+        .unwrap();
+
+        let folded = Inverter.fold_expression(expression);
+
+        assert_eq!(
+            "not file(\"Blank.esm\") and (not active(\"Blank.esp\") or not active(\"Blank.esm\"))",
+            folded.to_string()
+        );
+    }
+}